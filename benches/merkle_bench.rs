@@ -1,9 +1,9 @@
 use {
-    fast_merkle_tree::{hash_leaf, MerkleTree as FastMerkleTree, LEAF_PREFIX},
+    fast_merkle_tree::{hash_leaf, MerkleTree as FastMerkleTree},
     glassbench::*,
     rayon::prelude::*,
     solana_merkle_tree::MerkleTree as SolanaMerkleTree,
-    solana_program::hash::{hashv, Hash},
+    solana_program::hash::Hash,
     solana_sdk::signature::Signature,
 };
 
@@ -29,6 +29,16 @@ fn benchmark_merkle_tree(b: &mut Bench) {
         },
     );
 
+    b.task(
+        format!("fast-merkle-tree | {} leaves | From leaves", leaf_count),
+        |task| {
+            task.iter(|| {
+                let merkle_tree = FastMerkleTree::from_leaves(&leaves);
+                let _root = merkle_tree.nodes.last();
+            });
+        },
+    );
+
     b.task(
         format!(
             "solana-merkle-tree | {} leaves | Insert sequential & get root",
@@ -51,7 +61,7 @@ fn benchmark_merkle_tree(b: &mut Bench) {
             task.iter(|| {
                 let mut merkle_tree = FastMerkleTree::new(leaf_count);
                 let hashed_leaves: Vec<Hash> =
-                    leaves.par_iter().map(|leaf| hash_leaf!(leaf)).collect();
+                    leaves.par_iter().map(|leaf| hash_leaf(leaf)).collect();
                 merkle_tree.nodes = hashed_leaves;
                 let _root = merkle_tree.get_root();
             });
@@ -0,0 +1,48 @@
+/// SHA-256 implementation picked at runtime for off-chain hashing. On-chain, hashing goes
+/// through the `sol_sha256` syscall instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashAcceleration {
+    /// x86 SHA extensions (SHA-NI).
+    ShaNi,
+    /// ARMv8 cryptography extensions.
+    ArmSha2,
+    /// Portable software implementation.
+    Software,
+}
+
+/// Returns the SHA-256 implementation used on this CPU. The hasher behind
+/// `solana_program::hash` already detects these features at runtime and dispatches to the
+/// fastest path, so this reports its choice rather than changing it.
+pub fn hash_acceleration() -> HashAcceleration {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if std::is_x86_feature_detected!("sha")
+            && std::is_x86_feature_detected!("sse2")
+            && std::is_x86_feature_detected!("ssse3")
+            && std::is_x86_feature_detected!("sse4.1")
+        {
+            return HashAcceleration::ShaNi;
+        }
+    }
+    #[cfg(all(target_arch = "aarch64", any(target_os = "linux", target_os = "macos")))]
+    {
+        if std::arch::is_aarch64_feature_detected!("sha2") {
+            return HashAcceleration::ArmSha2;
+        }
+    }
+    HashAcceleration::Software
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_acceleration() {
+        let acceleration = hash_acceleration();
+        assert_eq!(acceleration, hash_acceleration());
+        if !cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
+            assert_ne!(acceleration, HashAcceleration::ShaNi);
+        }
+    }
+}
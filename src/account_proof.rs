@@ -0,0 +1,109 @@
+use crate::{MerkleTree, MerkleTreeError, Opening};
+use solana_program::{hash::Hash, pubkey::Pubkey};
+use std::collections::HashMap;
+
+/// Commitment to a set of accounts, e.g. from a snapshot. Accounts are sorted by address so
+/// the root only depends on the set, and each leaf is the address followed by the hash of
+/// the account's data.
+#[derive(Debug)]
+pub struct AccountStateTree {
+    pub tree: MerkleTree,
+    /// `(address, account_data_hash)` pairs in leaf order.
+    pub accounts: Vec<(Pubkey, Hash)>,
+    indices: HashMap<Pubkey, usize>,
+}
+
+impl AccountStateTree {
+    /// Returns the leaf committed for an account.
+    pub fn leaf(address: &Pubkey, data_hash: &Hash) -> [u8; 64] {
+        let mut leaf = [0u8; 64];
+        leaf[..32].copy_from_slice(address.as_ref());
+        leaf[32..].copy_from_slice(data_hash.as_ref());
+        leaf
+    }
+
+    /// Sorts the accounts by address and builds the tree, rejecting duplicate addresses.
+    pub fn new<I: IntoIterator<Item = (Pubkey, Hash)>>(
+        accounts: I,
+    ) -> Result<Self, MerkleTreeError> {
+        let mut accounts: Vec<(Pubkey, Hash)> = accounts.into_iter().collect();
+        accounts.sort_unstable_by_key(|(address, _)| *address);
+        if let Some(pair) = accounts.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(MerkleTreeError::DuplicateLeaf(format!(
+                "Account {} appears more than once",
+                pair[0].0
+            )));
+        }
+        let leaves: Vec<[u8; 64]> = accounts
+            .iter()
+            .map(|(address, data_hash)| Self::leaf(address, data_hash))
+            .collect();
+        let indices = accounts
+            .iter()
+            .enumerate()
+            .map(|(index, (address, _))| (*address, index))
+            .collect();
+        Ok(Self {
+            tree: MerkleTree::from_leaves(&leaves),
+            accounts,
+            indices,
+        })
+    }
+
+    /// Returns the state root, or `None` if there are no accounts.
+    pub fn root(&self) -> Option<&Hash> {
+        self.tree.nodes.last()
+    }
+
+    /// Returns the index of the account's leaf.
+    pub fn index_of(&self, address: &Pubkey) -> Option<usize> {
+        self.indices.get(address).copied()
+    }
+
+    /// Returns the proof of the account's state.
+    pub fn proof(&self, address: &Pubkey) -> Result<Opening, MerkleTreeError> {
+        let index = self.index_of(address).ok_or_else(|| {
+            MerkleTreeError::LeafIndexOutOfBounds(format!("{} isn't in the snapshot", address))
+        })?;
+        self.tree.get_opening(index)
+    }
+
+    /// Returns the proof of every account, in leaf order.
+    pub fn proofs(&self) -> Result<Vec<(Pubkey, Opening)>, MerkleTreeError> {
+        self.accounts
+            .iter()
+            .enumerate()
+            .map(|(index, (address, _))| Ok((*address, self.tree.get_opening(index)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LEAF_PREFIX;
+    use solana_program::hash::{hash, hashv};
+
+    #[test]
+    fn test_account_state_tree() {
+        let accounts: Vec<(Pubkey, Hash)> = (0..5u8)
+            .map(|data| (Pubkey::new_unique(), hash(&[data])))
+            .collect();
+        let state = AccountStateTree::new(accounts.iter().rev().copied()).unwrap();
+        assert_eq!(state.accounts, accounts);
+        assert_eq!(
+            state.root(),
+            AccountStateTree::new(accounts.clone()).unwrap().root()
+        );
+
+        let (address, data_hash) = accounts[3];
+        let proof = state.proof(&address).unwrap();
+        let leaf = AccountStateTree::leaf(&address, &data_hash);
+        assert!(proof.verify(&hash_leaf!(leaf)));
+        assert_eq!(state.proofs().unwrap()[3], (address, proof));
+        assert!(state.proof(&Pubkey::new_unique()).is_err());
+
+        let duplicate = [accounts[0], (accounts[0].0, Hash::default())];
+        assert!(AccountStateTree::new(duplicate).is_err());
+    }
+}
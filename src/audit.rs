@@ -0,0 +1,133 @@
+use crate::{HashDomain, MerkleHasher, MerkleTree, MerkleTreeError};
+use solana_program::hash::Hash;
+use std::collections::HashSet;
+
+/// A single node hash computed while building the tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AuditStep {
+    pub left: Hash,
+    pub right: Hash,
+    pub output: Hash,
+}
+
+/// Every hash computed by the last full build, in order, so a third party can recompute
+/// the published root from the leaves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditTrace {
+    /// Domain separation each step was hashed with, unless the tree has a custom hasher.
+    pub domain: HashDomain,
+    pub leaves: Vec<Hash>,
+    pub steps: Vec<AuditStep>,
+}
+
+impl AuditTrace {
+    pub(crate) fn new(domain: HashDomain) -> Self {
+        Self {
+            domain,
+            leaves: vec![],
+            steps: vec![],
+        }
+    }
+
+    pub(crate) fn record(&mut self, left: &Hash, right: &Hash, output: Hash) {
+        self.steps.push(AuditStep {
+            left: *left,
+            right: *right,
+            output,
+        });
+    }
+
+    /// Rehashes every step, checking that its inputs are leaves or outputs of earlier steps,
+    /// and returns the root the trace certifies. Traces of trees with a custom hasher need
+    /// `replay_with`.
+    pub fn replay(&self) -> Result<Hash, MerkleTreeError> {
+        self.replay_with(&self.domain)
+    }
+
+    /// Like `replay`, rehashing with the tree's custom hasher.
+    pub fn replay_with<H: MerkleHasher + ?Sized>(
+        &self,
+        hasher: &H,
+    ) -> Result<Hash, MerkleTreeError> {
+        let mut known: HashSet<Hash> = self.leaves.iter().copied().collect();
+        for (i, step) in self.steps.iter().enumerate() {
+            if !known.contains(&step.left) || !known.contains(&step.right) {
+                return Err(MerkleTreeError::ProofMismatch(format!(
+                    "Step {} hashes a node that isn't a leaf or an earlier output",
+                    i
+                )));
+            }
+            if hasher.hash_node(&step.left, &step.right) != step.output {
+                return Err(MerkleTreeError::ProofMismatch(format!(
+                    "Step {} doesn't hash to its recorded output",
+                    i
+                )));
+            }
+            known.insert(step.output);
+        }
+        match (self.steps.last(), self.leaves.as_slice()) {
+            (Some(step), _) => Ok(step.output),
+            (None, [leaf]) => Ok(*leaf),
+            _ => Err(MerkleTreeError::RootNotComputed(
+                "Trace doesn't contain a build".to_string(),
+            )),
+        }
+    }
+}
+
+impl MerkleTree {
+    /// Records every node hashed by subsequent full builds (`get_root`, or `root` on an
+    /// unbuilt tree). Incremental updates aren't recorded.
+    pub fn enable_audit(&mut self) {
+        self.audit_trace = Some(AuditTrace::new(self.hash_domain));
+    }
+
+    /// Returns the trace of the last full build, if audit mode is enabled.
+    pub fn audit_trace(&self) -> Option<&AuditTrace> {
+        self.audit_trace.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeccakHasher;
+    use std::sync::Arc;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_audit_trace() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.enable_audit();
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let root = *merkle_tree.get_root().unwrap();
+
+        let trace = merkle_tree.audit_trace().unwrap().clone();
+        assert_eq!(trace.leaves, merkle_tree.nodes[..SAMPLE.len()]);
+        // 3 + 2 + 1 pairs hashed across the levels
+        assert_eq!(trace.steps.len(), 6);
+        assert_eq!(trace.replay().unwrap(), root);
+
+        let mut forged = trace.clone();
+        forged.steps[0].output = Hash::new_unique();
+        assert!(forged.replay().is_err());
+        let mut forged = trace;
+        forged.leaves[0] = Hash::new_unique();
+        assert!(forged.replay().is_err());
+    }
+
+    #[test]
+    fn test_audit_trace_with_hasher() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.set_hasher(Arc::new(KeccakHasher));
+        merkle_tree.enable_audit();
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let root = *merkle_tree.get_root().unwrap();
+
+        let trace = merkle_tree.audit_trace().unwrap();
+        assert_eq!(trace.replay_with(&KeccakHasher).unwrap(), root);
+        assert_eq!(trace.replay_with(&merkle_tree.tree_hasher()).unwrap(), root);
+        assert!(trace.replay().is_err());
+    }
+}
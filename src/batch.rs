@@ -0,0 +1,281 @@
+use crate::{HashDomain, MerkleTreeError, Opening};
+use solana_program::hash::Hash;
+use std::collections::BTreeMap;
+
+/// Several openings against the same root merged into one proof. Each partner node is
+/// included once and only if it can't be computed from the proven leaves, so verifying
+/// hashes every shared ancestor once: about `k + log n` hashes for `k` nearby leaves.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AggregatedProof {
+    pub root: Hash,
+    /// Path length of the merged openings.
+    pub depth: usize,
+    /// Proven leaf indices, ascending.
+    pub leaf_indices: Vec<usize>,
+    /// Partner nodes that aren't derivable, level by level and by position within a level.
+    pub helpers: Vec<Hash>,
+}
+
+/// Openings bundled for verification, aggregated only when that drops partner nodes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProofBundle {
+    Aggregated(AggregatedProof),
+    Batch(Vec<Opening>),
+}
+
+impl AggregatedProof {
+    /// Merges openings against the same root. Repeated leaf indices are merged.
+    pub fn from_openings(openings: &[Opening]) -> Result<Self, MerkleTreeError> {
+        let Some(first) = openings.first() else {
+            return Err(MerkleTreeError::ProofMismatch(
+                "No openings to aggregate".to_string(),
+            ));
+        };
+        if let Some(opening) = openings
+            .iter()
+            .find(|o| o.root != first.root || o.path.len() != first.path.len())
+        {
+            return Err(MerkleTreeError::ProofMismatch(format!(
+                "Opening for leaf {} has a different root or depth",
+                opening.leaf_index
+            )));
+        }
+
+        // Known positions on the current level, with an opening passing through each
+        let mut known: BTreeMap<usize, &Opening> = openings
+            .iter()
+            .map(|opening| (opening.leaf_index, opening))
+            .collect();
+        let leaf_indices = known.keys().copied().collect();
+        let mut helpers = vec![];
+        for level in 0..first.path.len() {
+            for (position, opening) in &known {
+                if !known.contains_key(&(position ^ 1)) {
+                    helpers.push(opening.path[level]);
+                }
+            }
+            known = known
+                .into_iter()
+                .map(|(position, opening)| (position / 2, opening))
+                .collect();
+        }
+        Ok(Self {
+            root: first.root,
+            depth: first.path.len(),
+            leaf_indices,
+            helpers,
+        })
+    }
+
+    /// Returns true if the leaf hashes, given in `leaf_indices` order, compute to the root.
+    pub fn verify(&self, leaves: &[Hash]) -> bool {
+        self.verify_in(HashDomain::Prefix, leaves).is_some()
+    }
+
+    /// Like `verify`, for trees using the given domain separation. Returns the number of
+    /// node hashes computed, or None if the proof doesn't verify.
+    pub fn verify_in(&self, domain: HashDomain, leaves: &[Hash]) -> Option<usize> {
+        if leaves.len() != self.leaf_indices.len()
+            || self.leaf_indices.windows(2).any(|pair| pair[0] >= pair[1])
+        {
+            return None;
+        }
+        let mut level: Vec<(usize, Hash)> = self
+            .leaf_indices
+            .iter()
+            .copied()
+            .zip(leaves.iter().copied())
+            .collect();
+        let mut helpers = self.helpers.iter();
+        let mut hash_count = 0;
+        for _ in 0..self.depth {
+            let mut next_level = Vec::with_capacity(level.len());
+            let mut i = 0;
+            while i < level.len() {
+                let (position, node) = level[i];
+                let parent = match level.get(i + 1) {
+                    Some((partner_position, partner)) if *partner_position == position ^ 1 => {
+                        i += 1;
+                        domain.hash_node(&node, partner)
+                    }
+                    _ => {
+                        let partner = helpers.next()?;
+                        if position % 2 == 0 {
+                            domain.hash_node(&node, partner)
+                        } else {
+                            domain.hash_node(partner, &node)
+                        }
+                    }
+                };
+                next_level.push((position / 2, parent));
+                hash_count += 1;
+                i += 1;
+            }
+            level = next_level;
+        }
+        (helpers.next().is_none() && level == [(0, self.root)]).then_some(hash_count)
+    }
+}
+
+impl ProofBundle {
+    /// Aggregates the openings unless no partner node can be dropped, e.g. for a single
+    /// opening or leaves far apart, in which case they're kept for `verify_batch`.
+    pub fn new(openings: Vec<Opening>) -> Result<Self, MerkleTreeError> {
+        let aggregated = AggregatedProof::from_openings(&openings)?;
+        let path_nodes: usize = openings.iter().map(|opening| opening.path.len()).sum();
+        if aggregated.helpers.len() < path_nodes {
+            Ok(ProofBundle::Aggregated(aggregated))
+        } else {
+            Ok(ProofBundle::Batch(openings))
+        }
+    }
+
+    /// Returns true if the leaf hashes compute to the root. They're given in ascending leaf
+    /// index order when aggregated, and in opening order otherwise.
+    pub fn verify(&self, root: &Hash, leaves: &[Hash]) -> bool {
+        match self {
+            ProofBundle::Aggregated(proof) => proof.root == *root && proof.verify(leaves),
+            ProofBundle::Batch(openings) => {
+                openings.len() == leaves.len()
+                    && verify_batch(
+                        root,
+                        &openings
+                            .iter()
+                            .cloned()
+                            .zip(leaves.iter().copied())
+                            .collect::<Vec<_>>(),
+                    )
+            }
+        }
+    }
+}
+
+/// Verifies several openings against the same root, hashing each shared ancestor only once.
+/// A path stops as soon as it reaches a node already computed by an earlier opening, which
+/// saves `hashv` calls (and compute units on-chain) when the leaves are close together.
+pub fn verify_batch(root: &Hash, openings: &[(Opening, Hash)]) -> bool {
+    verify_batch_in(HashDomain::Prefix, root, openings).is_some()
+}
+
+/// Like `verify_batch`, for trees using the given domain separation. Returns the number of
+/// node hashes computed, or None if any opening doesn't verify.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(openings = openings.len()))
+)]
+pub fn verify_batch_in(
+    domain: HashDomain,
+    root: &Hash,
+    openings: &[(Opening, Hash)],
+) -> Option<usize> {
+    let depth = openings.first()?.0.path.len();
+    // Nodes computed so far, keyed by (level, position)
+    let mut computed: BTreeMap<(usize, usize), Hash> = BTreeMap::new();
+    let mut hash_count = 0;
+    for (opening, leaf) in openings {
+        if opening.root != *root || opening.path.len() != depth {
+            return None;
+        }
+        let mut position = opening.leaf_index;
+        let mut current = *leaf;
+        let mut level = 0;
+        loop {
+            if let Some(known) = computed.get(&(level, position)) {
+                if *known != current {
+                    return None;
+                }
+                break;
+            }
+            computed.insert((level, position), current);
+            let Some(partner) = opening.path.get(level) else {
+                if current != *root {
+                    return None;
+                }
+                break;
+            };
+            current = if position % 2 == 0 {
+                domain.hash_node(&current, partner)
+            } else {
+                domain.hash_node(partner, &current)
+            };
+            hash_count += 1;
+            position /= 2;
+            level += 1;
+        }
+    }
+    Some(hash_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleTree;
+
+    const SAMPLE: &[&[u8]] = &[
+        b"lorem",
+        b"ipsum",
+        b"dolor",
+        b"sit",
+        b"amet",
+        b"consectetur",
+        b"adipiscing",
+        b"elit",
+    ];
+
+    #[test]
+    fn test_verify_batch() {
+        let merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        let root = *merkle_tree.nodes.last().unwrap();
+        let openings: Vec<(Opening, Hash)> = [0, 1, 2, 3]
+            .iter()
+            .map(|&i| (merkle_tree.get_opening(i).unwrap(), merkle_tree.nodes[i]))
+            .collect();
+        assert!(verify_batch(&root, &openings));
+        // 3 hashes for the first path, then 1, 2 and 1 until each meets a known node
+        assert_eq!(
+            verify_batch_in(HashDomain::Prefix, &root, &openings),
+            Some(7)
+        );
+
+        let mut forged = openings.clone();
+        forged[2].1 = merkle_tree.nodes[5];
+        assert!(!verify_batch(&root, &forged));
+        assert!(!verify_batch(&merkle_tree.nodes[0], &openings));
+        assert!(!verify_batch(&root, &[]));
+    }
+
+    #[test]
+    fn test_aggregated_proof() {
+        let merkle_tree = MerkleTree::from_leaves(&SAMPLE[..7]);
+        let root = *merkle_tree.nodes.last().unwrap();
+        let openings: Vec<Opening> = [3, 0, 1, 2, 6]
+            .iter()
+            .map(|&i| merkle_tree.get_opening(i).unwrap())
+            .collect();
+        let leaves: Vec<Hash> = [0, 1, 2, 3, 6]
+            .iter()
+            .map(|&i| merkle_tree.nodes[i])
+            .collect();
+
+        let bundle = ProofBundle::new(openings).unwrap();
+        let ProofBundle::Aggregated(proof) = &bundle else {
+            panic!("nearby leaves should aggregate");
+        };
+        // Leaf 6 needs its duplicated partner and the parent of leaves 4 and 5
+        assert_eq!(
+            proof.helpers,
+            vec![merkle_tree.nodes[6], merkle_tree.nodes[7 + 2]]
+        );
+        assert_eq!(proof.verify_in(HashDomain::Prefix, &leaves), Some(6));
+        assert!(bundle.verify(&root, &leaves));
+
+        let mut forged = leaves.clone();
+        forged.swap(0, 1);
+        assert!(!bundle.verify(&root, &forged));
+        assert!(!bundle.verify(&merkle_tree.nodes[0], &leaves));
+
+        let single = ProofBundle::new(vec![merkle_tree.get_opening(4).unwrap()]).unwrap();
+        assert!(matches!(single, ProofBundle::Batch(_)));
+        assert!(single.verify(&root, &[merkle_tree.nodes[4]]));
+    }
+}
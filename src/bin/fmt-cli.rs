@@ -0,0 +1,149 @@
+use fast_merkle_tree::{hash_leaf, MerkleProof, MerkleTree, Root, LEAF_PREFIX};
+use solana_program::hash::hashv;
+use std::{fs, path::Path, process::ExitCode, str::FromStr};
+
+const USAGE: &str = "Usage:
+    fmt-cli build <leaf-file> <snapshot>      Build a tree from a newline, CSV or JSON leaf file
+    fmt-cli prove <snapshot> <index>          Print the proof for a leaf as <index>:<node>,<node>,...
+    fmt-cli verify <root> <proof> <leaf>      Check a proof for a raw leaf against a root";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["build", leaf_file, snapshot] => build(leaf_file, snapshot),
+        ["prove", snapshot, index] => prove(snapshot, index),
+        ["verify", root, proof, leaf] => verify(root, proof, leaf),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Reads leaves from a JSON array of strings, one leaf per non-empty CSV field, or one leaf
+/// per non-empty line otherwise.
+fn read_leaves(leaf_file: &str) -> Result<Vec<Vec<u8>>, String> {
+    let contents = fs::read_to_string(leaf_file).map_err(|e| e.to_string())?;
+    match Path::new(leaf_file)
+        .extension()
+        .and_then(|ext| ext.to_str())
+    {
+        Some("json") => {
+            let leaves: Vec<String> = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+            return Ok(leaves.into_iter().map(String::into_bytes).collect());
+        }
+        Some("csv") => return parse_csv(&contents),
+        _ => {}
+    }
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| line.as_bytes().to_vec())
+        .collect())
+}
+
+/// Splits CSV into its fields. Quoted fields may contain commas, line breaks and `""` escapes.
+fn parse_csv(contents: &str) -> Result<Vec<Vec<u8>>, String> {
+    let mut leaves = Vec::new();
+    let mut field = String::new();
+    let mut chars = contents.chars().peekable();
+    let mut line = 1;
+    loop {
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            let start = line;
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => {
+                        if c == '\n' {
+                            line += 1;
+                        }
+                        field.push(c);
+                    }
+                    None => return Err(format!("Unterminated quoted field on line {}", start)),
+                }
+            }
+            if !matches!(chars.peek(), None | Some(',' | '\r' | '\n')) {
+                return Err(format!(
+                    "Unexpected character after quoted field on line {}",
+                    line
+                ));
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                match c {
+                    ',' | '\r' | '\n' => break,
+                    '"' => {
+                        return Err(format!(
+                            "Unexpected quote in unquoted field on line {}",
+                            line
+                        ))
+                    }
+                    _ => field.push(c),
+                }
+                chars.next();
+            }
+        }
+        if !field.is_empty() {
+            leaves.push(std::mem::take(&mut field).into_bytes());
+        }
+        match chars.next() {
+            Some('\r') if chars.peek() == Some(&'\n') => {
+                chars.next();
+                line += 1;
+            }
+            Some('\r' | '\n') => line += 1,
+            Some(_) => {}
+            None => return Ok(leaves),
+        }
+    }
+}
+
+fn build(leaf_file: &str, snapshot: &str) -> Result<String, String> {
+    let leaves = read_leaves(leaf_file)?;
+    let merkle_tree = MerkleTree::from_leaves(&leaves);
+    let root = merkle_tree.nodes.last().ok_or("Leaf file has no leaves")?;
+    merkle_tree.save(snapshot).map_err(|e| e.to_string())?;
+    Ok(format!("leaves: {}\nroot: {}", leaves.len(), root))
+}
+
+fn prove(snapshot: &str, index: &str) -> Result<String, String> {
+    let merkle_tree = MerkleTree::load(snapshot).map_err(|e| e.to_string())?;
+    let leaf_index = usize::from_str(index).map_err(|e| e.to_string())?;
+    let opening = merkle_tree
+        .get_opening(leaf_index)
+        .map_err(|e| e.to_string())?;
+    let proof = MerkleProof::try_from(&opening).map_err(|e| e.to_string())?;
+    Ok(proof.to_string())
+}
+
+fn verify(root: &str, proof: &str, leaf: &str) -> Result<String, String> {
+    let root = Root::from_str(root).map_err(|e| e.to_string())?;
+    let opening = MerkleProof::from_str(proof)
+        .map_err(|e| e.to_string())?
+        .into_opening(root.into());
+    let leaf = leaf.as_bytes();
+    if opening.verify(&hash_leaf!(leaf)) {
+        Ok("valid".to_string())
+    } else {
+        Err("invalid".to_string())
+    }
+}
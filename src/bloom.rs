@@ -0,0 +1,110 @@
+use crate::MerkleTree;
+use solana_program::hash::Hash;
+use std::ops::Range;
+
+/// Bloom filter over leaf hashes. Leaf hashes are already uniformly distributed, so the
+/// bit positions are derived from them directly by double hashing.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct BloomFilter {
+    bits: Vec<u64>,
+    bit_count: u64,
+    hash_count: u32,
+}
+
+impl BloomFilter {
+    fn new(expected_leaves: usize, false_positive_rate: f64) -> Self {
+        let expected_leaves = expected_leaves.max(1) as f64;
+        let false_positive_rate = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bit_count =
+            ((-expected_leaves * false_positive_rate.ln() / (ln2 * ln2)).ceil() as u64).max(64);
+        let hash_count = ((bit_count as f64 / expected_leaves * ln2).round() as u32).max(1);
+        Self {
+            bits: vec![0; ((bit_count + 63) / 64) as usize],
+            bit_count,
+            hash_count,
+        }
+    }
+
+    fn positions(&self, leaf: &Hash) -> impl Iterator<Item = u64> {
+        let bytes = leaf.to_bytes();
+        let h1 = u64::from_le_bytes(bytes[0..8].try_into().unwrap());
+        let h2 = u64::from_le_bytes(bytes[8..16].try_into().unwrap()) | 1;
+        let bit_count = self.bit_count;
+        (0..self.hash_count as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % bit_count)
+    }
+
+    fn insert(&mut self, leaf: &Hash) {
+        for position in self.positions(leaf).collect::<Vec<_>>() {
+            self.bits[(position / 64) as usize] |= 1 << (position % 64);
+        }
+    }
+
+    #[cfg(feature = "zeroize")]
+    pub(crate) fn zeroize(&mut self) {
+        zeroize::Zeroize::zeroize(&mut self.bits);
+    }
+
+    fn contains(&self, leaf: &Hash) -> bool {
+        self.positions(leaf)
+            .all(|position| self.bits[(position / 64) as usize] & (1 << (position % 64)) != 0)
+    }
+}
+
+impl MerkleTree {
+    /// Maintains a bloom filter over the leaf hashes, sized for `expected_leaves` at the given
+    /// false positive rate, and fills it with the current leaves.
+    pub fn enable_bloom_filter(&mut self, expected_leaves: usize, false_positive_rate: f64) {
+        let mut bloom = BloomFilter::new(expected_leaves, false_positive_rate);
+        for leaf in &self.nodes[..self.current_leaf_index] {
+            bloom.insert(leaf);
+        }
+        self.bloom = Some(bloom);
+    }
+
+    /// Returns false if the leaf is definitely not in the tree. Replaced leaves stay in the
+    /// filter, and without a filter this is always true.
+    pub fn maybe_contains<T: AsRef<[u8]>>(&self, leaf: T) -> bool {
+        self.maybe_contains_hash(&self.hash_leaf(leaf.as_ref()))
+    }
+
+    /// Like `maybe_contains`, for a leaf that's already hashed.
+    pub fn maybe_contains_hash(&self, leaf: &Hash) -> bool {
+        self.bloom
+            .as_ref()
+            .map_or(true, |bloom| bloom.contains(leaf))
+    }
+
+    /// Adds the leaves at the given indices to the bloom filter, if enabled.
+    pub(crate) fn record_bloom(&mut self, leaf_indices: Range<usize>) {
+        if let Some(bloom) = self.bloom.as_mut() {
+            for leaf in &self.nodes[leaf_indices] {
+                bloom.insert(leaf);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_bloom_filter() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        assert!(merkle_tree.maybe_contains(b"lorem"));
+        merkle_tree.insert_batch(&SAMPLE[..2]).unwrap();
+        merkle_tree.enable_bloom_filter(SAMPLE.len(), 0.0001);
+        merkle_tree.insert(SAMPLE[2]).unwrap();
+        merkle_tree.update_leaf(3, b"foo").unwrap();
+
+        for leaf in &SAMPLE[..3] {
+            assert!(merkle_tree.maybe_contains(leaf));
+        }
+        assert!(merkle_tree.maybe_contains(b"foo"));
+        assert!(!merkle_tree.maybe_contains(b"amet"));
+        assert!(!merkle_tree.maybe_contains(b"consectetur"));
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{MerkleTree, Opening};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+/// Least recently used cache of openings keyed by leaf index.
+#[derive(Debug, Default)]
+pub(crate) struct ProofCache {
+    capacity: usize,
+    openings: HashMap<usize, Opening>,
+    // Leaf indices ordered from least to most recently used
+    usage: VecDeque<usize>,
+}
+
+impl ProofCache {
+    fn get(&mut self, leaf_index: usize) -> Option<Opening> {
+        let opening = self.openings.get(&leaf_index)?.clone();
+        self.touch(leaf_index);
+        Some(opening)
+    }
+
+    fn insert(&mut self, opening: &Opening) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.openings.len() == self.capacity && !self.openings.contains_key(&opening.leaf_index)
+        {
+            if let Some(evicted) = self.usage.pop_front() {
+                self.openings.remove(&evicted);
+            }
+        }
+        self.openings.insert(opening.leaf_index, opening.clone());
+        self.touch(opening.leaf_index);
+    }
+
+    fn touch(&mut self, leaf_index: usize) {
+        if let Some(position) = self.usage.iter().position(|&i| i == leaf_index) {
+            self.usage.remove(position);
+        }
+        self.usage.push_back(leaf_index);
+    }
+
+    fn clear(&mut self) {
+        self.openings.clear();
+        self.usage.clear();
+    }
+}
+
+impl MerkleTree {
+    /// Caches up to `capacity` recently generated openings. A capacity of 0 disables the cache.
+    pub fn enable_proof_cache(&mut self, capacity: usize) {
+        self.proof_cache = Mutex::new(ProofCache {
+            capacity,
+            ..Default::default()
+        });
+    }
+
+    /// Returns the number of openings currently cached.
+    pub fn cached_openings(&self) -> usize {
+        self.proof_cache.lock().unwrap().openings.len()
+    }
+
+    pub(crate) fn cached_opening(&self, leaf_index: usize) -> Option<Opening> {
+        self.proof_cache.lock().unwrap().get(leaf_index)
+    }
+
+    pub(crate) fn cache_opening(&self, opening: &Opening) {
+        self.proof_cache.lock().unwrap().insert(opening);
+    }
+
+    /// Every opening embeds the root, so any change to the root invalidates all of them.
+    pub(crate) fn invalidate_proof_cache(&mut self) {
+        self.proof_cache.get_mut().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_proof_cache() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.enable_proof_cache(2);
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+
+        let opening = merkle_tree.get_opening(0).unwrap();
+        let _ = merkle_tree.get_opening(1).unwrap();
+        assert_eq!(merkle_tree.get_opening(0).unwrap(), opening);
+        let _ = merkle_tree.get_opening(2).unwrap();
+        assert_eq!(merkle_tree.cached_openings(), 2);
+        assert!(merkle_tree.cached_opening(1).is_none());
+        assert!(merkle_tree.cached_opening(0).is_some());
+
+        merkle_tree.update_leaf(4, b"foo").unwrap();
+        assert_eq!(merkle_tree.cached_openings(), 0);
+        let updated_opening = merkle_tree.get_opening(0).unwrap();
+        assert_ne!(updated_opening.root, opening.root);
+        assert!(merkle_tree.verify_opening(&updated_opening).unwrap());
+    }
+}
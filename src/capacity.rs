@@ -0,0 +1,71 @@
+use crate::{MerkleTree, MerkleTreeError};
+
+/// Default limit on the leaf count of trees sized from untrusted input, 2^24 leaves
+/// (1 GiB of nodes once built). The `_with_limit` variants take a different one.
+pub const DEFAULT_MAX_LEAF_COUNT: usize = 1 << 24;
+
+impl MerkleTree {
+    /// Like `new`, but fails instead of attempting the allocation if the leaf count is above
+    /// `DEFAULT_MAX_LEAF_COUNT` or the node count overflows.
+    pub fn try_new(leaf_count: usize) -> Result<Self, MerkleTreeError> {
+        Self::try_new_with_limit(leaf_count, DEFAULT_MAX_LEAF_COUNT)
+    }
+
+    /// Like `try_new`, with the given limit on the leaf count.
+    pub fn try_new_with_limit(
+        leaf_count: usize,
+        max_leaf_count: usize,
+    ) -> Result<Self, MerkleTreeError> {
+        Self::check_capacity(leaf_count, max_leaf_count)?;
+        Ok(Self::new(leaf_count))
+    }
+
+    /// Returns the node count of a tree of the given leaf count, if it's within the limit.
+    pub(crate) fn check_capacity(
+        leaf_count: usize,
+        max_leaf_count: usize,
+    ) -> Result<usize, MerkleTreeError> {
+        if leaf_count > max_leaf_count {
+            return Err(MerkleTreeError::CapacityExceeded(format!(
+                "Tree of {} leaves exceeds the limit of {}",
+                leaf_count, max_leaf_count
+            )));
+        }
+        Self::checked_max_capacity(leaf_count).ok_or_else(|| {
+            MerkleTreeError::CapacityExceeded(format!(
+                "Node count of a tree of {} leaves overflows usize",
+                leaf_count
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_new() {
+        assert_eq!(MerkleTree::try_new(5).unwrap().nodes.len(), 5);
+        assert!(matches!(
+            MerkleTree::try_new(DEFAULT_MAX_LEAF_COUNT + 1),
+            Err(MerkleTreeError::CapacityExceeded(_))
+        ));
+        assert!(matches!(
+            MerkleTree::try_new_with_limit(5, 4),
+            Err(MerkleTreeError::CapacityExceeded(_))
+        ));
+        assert!(MerkleTree::check_capacity(DEFAULT_MAX_LEAF_COUNT + 1, usize::MAX).is_ok());
+        assert!(MerkleTree::check_capacity(usize::MAX, usize::MAX).is_err());
+
+        let mut checkpoint = vec![];
+        MerkleTree::new(2)
+            .write_checkpoint(&mut checkpoint)
+            .unwrap();
+        checkpoint[4..12].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            MerkleTree::read_checkpoint(checkpoint.as_slice()),
+            Err(MerkleTreeError::CapacityExceeded(_))
+        ));
+    }
+}
@@ -0,0 +1,91 @@
+use crate::MerkleTree;
+use solana_program::hash::{hashv, Hash};
+
+/// Running commitment to every root a tree has published.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct RootChain {
+    pub(crate) chained_root: Hash,
+    pub(crate) last_root: Option<Hash>,
+}
+
+/// Returns `H(prev_chained_root || tree_root)`.
+pub fn chain_root(prev_chained_root: &Hash, tree_root: &Hash) -> Hash {
+    hashv(&[prev_chained_root.as_ref(), tree_root.as_ref()])
+}
+
+/// Returns true if chaining `tree_roots` in order onto `prev_chained_root` gives
+/// `chained_root`. A consumer that remembers the last chained root it saw can use this to
+/// check that a newly published root extends it rather than rolling back.
+pub fn verify_root_chain(
+    prev_chained_root: &Hash,
+    tree_roots: &[Hash],
+    chained_root: &Hash,
+) -> bool {
+    let computed = tree_roots.iter().fold(*prev_chained_root, |chained, root| {
+        chain_root(&chained, root)
+    });
+    computed == *chained_root
+}
+
+impl MerkleTree {
+    /// Starts chaining roots: each distinct new root `r` updates the chained root to
+    /// `H(prev || r)`, starting from `genesis`. The current root, if built, is chained first.
+    pub fn enable_root_chaining(&mut self, genesis: Hash) {
+        self.root_chain = Some(RootChain {
+            chained_root: genesis,
+            last_root: None,
+        });
+        if self.is_built() {
+            self.record_chained_root();
+        }
+    }
+
+    /// Returns the chained root, if chaining is enabled.
+    pub fn chained_root(&self) -> Option<&Hash> {
+        self.root_chain.as_ref().map(|chain| &chain.chained_root)
+    }
+
+    /// Chains the current root if it differs from the last chained one.
+    pub(crate) fn record_chained_root(&mut self) {
+        let (Some(chain), Some(&root)) = (self.root_chain.as_mut(), self.nodes.last()) else {
+            return;
+        };
+        if chain.last_root == Some(root) {
+            return;
+        }
+        chain.chained_root = chain_root(&chain.chained_root, &root);
+        chain.last_root = Some(root);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_root_chaining() {
+        let genesis = Hash::default();
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.enable_root_chaining(genesis);
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let mut roots = vec![*merkle_tree.get_root().unwrap()];
+        let first_chained = *merkle_tree.chained_root().unwrap();
+        assert_eq!(first_chained, chain_root(&genesis, &roots[0]));
+        // Rebuilding the same root doesn't extend the chain
+        let _ = merkle_tree.get_root();
+        assert_eq!(merkle_tree.chained_root(), Some(&first_chained));
+
+        for (leaf_index, leaf) in [(0, b"foo"), (1, b"bar")] {
+            merkle_tree.update_leaf(leaf_index, leaf).unwrap();
+            roots.push(*merkle_tree.root().unwrap());
+        }
+        let chained = *merkle_tree.chained_root().unwrap();
+        assert!(verify_root_chain(&genesis, &roots, &chained));
+        assert!(verify_root_chain(&first_chained, &roots[1..], &chained));
+        assert!(!verify_root_chain(&first_chained, &roots[2..], &chained));
+        // Publishing an older chained root doesn't extend the latest one
+        assert!(!verify_root_chain(&chained, &[], &first_chained));
+    }
+}
@@ -0,0 +1,280 @@
+use crate::{MerkleProof, MerkleTree, MerkleTreeError};
+use solana_program::hash::{Hash, HASH_BYTES};
+
+const APPEND_TAG: u8 = 0;
+const REPLACE_TAG: u8 = 1;
+
+/// Whether a change appended a new leaf or replaced an existing one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Append,
+    Replace,
+}
+
+/// A leaf change emitted by a tree maintained on-chain, with the new nodes on the
+/// leaf's path from the leaf itself (first) up to the root (last).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangeLogEvent {
+    pub kind: ChangeKind,
+    pub leaf_index: u64,
+    pub path: Vec<Hash>,
+}
+
+impl ChangeLogEvent {
+    pub fn leaf(&self) -> Option<&Hash> {
+        self.path.first()
+    }
+
+    pub fn root(&self) -> Option<&Hash> {
+        self.path.last()
+    }
+
+    /// Layout: tag (0 append, 1 replace) || leaf_index (u64 LE) || path_len (u8) || path nodes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(10 + self.path.len() * HASH_BYTES);
+        bytes.push(match self.kind {
+            ChangeKind::Append => APPEND_TAG,
+            ChangeKind::Replace => REPLACE_TAG,
+        });
+        bytes.extend_from_slice(&self.leaf_index.to_le_bytes());
+        bytes.push(self.path.len() as u8);
+        for node in &self.path {
+            bytes.extend_from_slice(node.as_ref());
+        }
+        bytes
+    }
+
+    /// Parses an event written by `to_bytes`, rejecting unknown tags and wrong lengths.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MerkleTreeError> {
+        if bytes.len() < 10 {
+            return Err(MerkleTreeError::InvalidChangeLog(format!(
+                "Event of {} bytes is shorter than its header",
+                bytes.len()
+            )));
+        }
+        let kind = match bytes[0] {
+            APPEND_TAG => ChangeKind::Append,
+            REPLACE_TAG => ChangeKind::Replace,
+            tag => {
+                return Err(MerkleTreeError::InvalidChangeLog(format!(
+                    "Unknown event tag {}",
+                    tag
+                )))
+            }
+        };
+        let mut leaf_index = [0u8; 8];
+        leaf_index.copy_from_slice(&bytes[1..9]);
+        let path_len = bytes[9] as usize;
+        let path_bytes = &bytes[10..];
+        if path_len == 0 || path_bytes.len() != path_len * HASH_BYTES {
+            return Err(MerkleTreeError::InvalidChangeLog(format!(
+                "Event declares {} path nodes but has {} bytes of them",
+                path_len,
+                path_bytes.len()
+            )));
+        }
+        Ok(Self {
+            kind,
+            leaf_index: u64::from_le_bytes(leaf_index),
+            path: path_bytes.chunks_exact(HASH_BYTES).map(Hash::new).collect(),
+        })
+    }
+}
+
+/// Mirrors a fixed-depth tree maintained elsewhere by replaying its changelog events.
+#[derive(Debug)]
+pub struct ChangeLogMirror {
+    pub depth: usize,
+    pub tree: MerkleTree,
+}
+
+impl ChangeLogMirror {
+    /// Starts from an empty tree of `2^depth` default leaves.
+    pub fn new(depth: usize) -> Self {
+        let mut tree = MerkleTree::new(1 << depth);
+        let _ = tree.get_root();
+        Self { depth, tree }
+    }
+
+    /// Applies the event to the local tree, failing if the appended index isn't the next
+    /// one or the recomputed path doesn't match the event's. After a divergence the local
+    /// tree still holds the event's leaf and should be rebuilt from a trusted source.
+    pub fn apply(&mut self, event: &ChangeLogEvent) -> Result<(), MerkleTreeError> {
+        let leaf_index = event.leaf_index as usize;
+        if event.path.len() != self.depth + 1 {
+            return Err(MerkleTreeError::InvalidChangeLog(format!(
+                "Event path has {} nodes but a tree of depth {} needs {}",
+                event.path.len(),
+                self.depth,
+                self.depth + 1
+            )));
+        }
+        if event.kind == ChangeKind::Append && leaf_index != self.tree.current_leaf_index {
+            return Err(MerkleTreeError::RootDivergence(format!(
+                "Append at index {} but the next local index is {}",
+                leaf_index, self.tree.current_leaf_index
+            )));
+        }
+
+        self.tree.set_leaf_hashes(&[(leaf_index, event.path[0])])?;
+        if event.kind == ChangeKind::Append {
+            self.tree.current_leaf_index = leaf_index + 1;
+        }
+
+        let mut level_offset = 0;
+        let mut level_len = self.tree.leaf_count;
+        let mut current_index = leaf_index;
+        for (level, node) in event.path.iter().enumerate() {
+            if self.tree.nodes[level_offset + current_index] != *node {
+                return Err(MerkleTreeError::RootDivergence(format!(
+                    "Leaf {} path diverges at level {}",
+                    leaf_index, level
+                )));
+            }
+            level_offset += level_len;
+            level_len /= 2;
+            current_index /= 2;
+        }
+        Ok(())
+    }
+
+    /// Returns the root of the local tree.
+    pub fn root(&self) -> &Hash {
+        &self.tree.nodes[self.tree.nodes.len() - 1]
+    }
+}
+
+/// Patches a proof made against an older root so it verifies against the root after the
+/// given changes, applied in order. Each change to another leaf replaces the one partner
+/// node where its path meets the proof's, as a concurrent tree's changelog does. Fails if a
+/// change touches the proven leaf itself, since the proof's leaf is then stale too.
+pub fn fast_forward_proof(
+    proof: &MerkleProof,
+    changelog: &[ChangeLogEvent],
+) -> Result<MerkleProof, MerkleTreeError> {
+    let leaf_index = proof.leaf_index as u64;
+    let mut proof = proof.clone();
+    for event in changelog {
+        if event.path.len() != proof.path.len() + 1 {
+            return Err(MerkleTreeError::InvalidChangeLog(format!(
+                "Event path has {} nodes but the proof needs {}",
+                event.path.len(),
+                proof.path.len() + 1
+            )));
+        }
+        if event.leaf_index == leaf_index {
+            return Err(MerkleTreeError::ProofMismatch(format!(
+                "Leaf {} itself changed, its proof can't be fast-forwarded",
+                leaf_index
+            )));
+        }
+        // The paths meet right above the highest bit where the indices differ
+        let level = (u64::BITS - (event.leaf_index ^ leaf_index).leading_zeros() - 1) as usize;
+        if level >= proof.path.len() {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Change at leaf {} is outside a tree of depth {}",
+                event.leaf_index,
+                proof.path.len()
+            )));
+        }
+        proof.path[level] = event.path[level];
+    }
+    Ok(proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleTreeState;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    /// Builds the event a program would emit after changing a leaf of `source`.
+    fn event(source: &mut MerkleTree, kind: ChangeKind, leaf_index: usize) -> ChangeLogEvent {
+        let _ = source.root();
+        let mut path = vec![];
+        let (mut level_offset, mut level_len, mut current_index) =
+            (0, source.leaf_count, leaf_index);
+        while level_len > 0 {
+            path.push(source.nodes[level_offset + current_index]);
+            level_offset += level_len;
+            level_len /= 2;
+            current_index /= 2;
+        }
+        ChangeLogEvent {
+            kind,
+            leaf_index: leaf_index as u64,
+            path,
+        }
+    }
+
+    #[test]
+    fn test_changelog_mirror() {
+        let mut source = MerkleTree::new(8);
+        let _ = source.get_root();
+        let mut state = MerkleTreeState::<3> {
+            discriminator: [0; 8],
+            depth: 0,
+            _padding: [0; 4],
+            next_index: 0,
+            root: [0; 32],
+            filled_subtrees: [[0; 32]; 3],
+        };
+        state.initialize(3).unwrap();
+        let mut mirror = ChangeLogMirror::new(3);
+
+        for (leaf_index, leaf) in SAMPLE.iter().enumerate() {
+            source.update_leaf(leaf_index, leaf).unwrap();
+            state.append(source.nodes[leaf_index].to_bytes()).unwrap();
+            let event = event(&mut source, ChangeKind::Append, leaf_index);
+            let event = ChangeLogEvent::from_bytes(&event.to_bytes()).unwrap();
+            mirror.apply(&event).unwrap();
+            assert_eq!(mirror.root().to_bytes(), state.root);
+        }
+
+        source.update_leaf(1, b"foo").unwrap();
+        mirror
+            .apply(&event(&mut source, ChangeKind::Replace, 1))
+            .unwrap();
+        assert_eq!(mirror.root(), source.nodes.last().unwrap());
+
+        // An event computed against a different tree is caught
+        source.update_leaf(2, b"bar").unwrap();
+        let mut stale = event(&mut source, ChangeKind::Replace, 3);
+        stale.path[0] = Hash::new_unique();
+        assert!(mirror.apply(&stale).is_err());
+        let wrong_index = event(&mut source, ChangeKind::Append, 7);
+        assert!(mirror.apply(&wrong_index).is_err());
+        assert!(ChangeLogEvent::from_bytes(&[2; 10]).is_err());
+    }
+
+    #[test]
+    fn test_fast_forward_proof() {
+        let mut source = MerkleTree::new(8);
+        source.insert_batch(SAMPLE).unwrap();
+        let _ = source.get_root();
+        let proof = MerkleProof::try_from(&source.get_opening(2).unwrap()).unwrap();
+
+        let mut changelog = vec![];
+        for (leaf_index, leaf) in [(5, b"foo"), (3, b"bar"), (0, b"baz")] {
+            source.update_leaf(leaf_index, leaf).unwrap();
+            changelog.push(event(&mut source, ChangeKind::Replace, leaf_index));
+        }
+        source.insert(b"qux").unwrap();
+        changelog.push(event(&mut source, ChangeKind::Append, 5));
+
+        let root = *source.nodes.last().unwrap();
+        let leaf = source.nodes[2];
+        assert!(!proof.clone().into_opening(root).verify(&leaf));
+        let fast_forwarded = fast_forward_proof(&proof, &changelog).unwrap();
+        assert_eq!(
+            fast_forwarded,
+            MerkleProof::try_from(&source.get_opening(2).unwrap()).unwrap()
+        );
+        assert!(fast_forwarded.into_opening(root).verify(&leaf));
+
+        source.update_leaf(2, b"foo").unwrap();
+        changelog.push(event(&mut source, ChangeKind::Replace, 2));
+        assert!(fast_forward_proof(&proof, &changelog).is_err());
+    }
+}
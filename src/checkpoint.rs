@@ -0,0 +1,94 @@
+use crate::{MerkleTree, MerkleTreeError, DEFAULT_MAX_LEAF_COUNT};
+use solana_program::hash::{Hash, HASH_BYTES};
+use std::io::{Read, Write};
+
+/// Identifies a serialized checkpoint of a partially built tree.
+pub const CHECKPOINT_MAGIC: &[u8; 4] = b"FMTC";
+
+// Layout: magic || leaf_count (u64 LE) || current_leaf_index (u64 LE) || inserted leaf hashes
+impl MerkleTree {
+    /// Writes the leaves inserted so far so construction can be resumed with `read_checkpoint`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(leaf_count = self.leaf_count))
+    )]
+    pub fn write_checkpoint<W: Write>(&self, mut writer: W) -> Result<(), MerkleTreeError> {
+        writer.write_all(CHECKPOINT_MAGIC)?;
+        writer.write_all(&(self.leaf_count as u64).to_le_bytes())?;
+        writer.write_all(&(self.current_leaf_index as u64).to_le_bytes())?;
+        for leaf in &self.nodes[0..self.current_leaf_index] {
+            writer.write_all(leaf.as_ref())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Restores a tree written by `write_checkpoint`; further leaves are inserted
+    /// from where the checkpointed tree left off. Trees above `DEFAULT_MAX_LEAF_COUNT`
+    /// leaves are rejected, see `read_checkpoint_with_limit`.
+    pub fn read_checkpoint<R: Read>(reader: R) -> Result<Self, MerkleTreeError> {
+        Self::read_checkpoint_with_limit(reader, DEFAULT_MAX_LEAF_COUNT)
+    }
+
+    /// Like `read_checkpoint`, with the given limit on the leaf count.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn read_checkpoint_with_limit<R: Read>(
+        mut reader: R,
+        max_leaf_count: usize,
+    ) -> Result<Self, MerkleTreeError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != CHECKPOINT_MAGIC {
+            return Err(MerkleTreeError::InvalidCheckpoint(
+                "Checkpoint magic bytes don't match".to_string(),
+            ));
+        }
+
+        let mut word = [0u8; 8];
+        reader.read_exact(&mut word)?;
+        let leaf_count = u64::from_le_bytes(word) as usize;
+        reader.read_exact(&mut word)?;
+        let current_leaf_index = u64::from_le_bytes(word) as usize;
+        if current_leaf_index > leaf_count {
+            return Err(MerkleTreeError::InvalidCheckpoint(format!(
+                "Checkpoint has {} leaves inserted into a tree of {}",
+                current_leaf_index, leaf_count
+            )));
+        }
+
+        let mut merkle_tree = MerkleTree::try_new_with_limit(leaf_count, max_leaf_count)?;
+        let mut leaf = [0u8; HASH_BYTES];
+        for leaf_index in 0..current_leaf_index {
+            reader.read_exact(&mut leaf)?;
+            merkle_tree.nodes[leaf_index] = Hash::new_from_array(leaf);
+        }
+        merkle_tree.current_leaf_index = current_leaf_index;
+        Ok(merkle_tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(&SAMPLE[..3]).unwrap();
+
+        let mut checkpoint = vec![];
+        merkle_tree.write_checkpoint(&mut checkpoint).unwrap();
+        assert_eq!(checkpoint.len(), 4 + 8 + 8 + 3 * HASH_BYTES);
+
+        let mut resumed = MerkleTree::read_checkpoint(checkpoint.as_slice()).unwrap();
+        assert_eq!(resumed.insert_batch(&SAMPLE[3..]).unwrap(), 3..5);
+        merkle_tree.insert_batch(&SAMPLE[3..]).unwrap();
+        assert_eq!(resumed.get_root(), merkle_tree.get_root());
+
+        assert!(MerkleTree::read_checkpoint(&checkpoint[..30]).is_err());
+        checkpoint[0] = b'X';
+        assert!(MerkleTree::read_checkpoint(checkpoint.as_slice()).is_err());
+    }
+}
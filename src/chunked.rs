@@ -0,0 +1,105 @@
+use crate::{MerkleHasher, MerkleTree, MerkleTreeError, LEAF_PREFIX};
+use solana_program::hash::hashv;
+use std::io::{ErrorKind, Read};
+
+impl MerkleTree {
+    /// Splits the reader into `chunk_size` byte chunks (the last one may be shorter),
+    /// hashes each chunk as a leaf and returns the built tree with the chunk count.
+    pub fn from_reader<R: Read>(
+        mut reader: R,
+        chunk_size: usize,
+    ) -> Result<(Self, usize), MerkleTreeError> {
+        if chunk_size == 0 {
+            return Err(MerkleTreeError::InvalidChunkSize(
+                "Chunk size must be greater than 0".to_string(),
+            ));
+        }
+
+        let mut nodes = vec![];
+        let mut chunk = vec![0u8; chunk_size];
+        loop {
+            let chunk_len = read_chunk(&mut reader, &mut chunk)?;
+            if chunk_len == 0 {
+                break;
+            }
+            let leaf = &chunk[..chunk_len];
+            nodes.push(hash_leaf!(leaf));
+        }
+
+        let chunk_count = nodes.len();
+        let mut merkle_tree = Self {
+            leaf_count: chunk_count,
+            nodes,
+            current_leaf_index: chunk_count,
+            ..Default::default()
+        };
+        let _ = merkle_tree.get_root();
+        Ok((merkle_tree, chunk_count))
+    }
+
+    /// Inserts a single leaf read to the end of the reader, e.g. a file, hashing it as it
+    /// streams in. Returns the index it was assigned.
+    pub fn insert_from_reader<R: Read>(&mut self, mut reader: R) -> Result<usize, MerkleTreeError> {
+        self.ensure_not_full()?;
+        let leaf_node = self.tree_hasher().hash_leaf_reader(&mut reader)?;
+        self.insert_leaf_hash(leaf_node)
+    }
+}
+
+/// Fills the buffer from the reader, stopping early only at EOF.
+/// Returns the number of bytes read.
+pub(crate) fn read_chunk<R: Read>(reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_reader() {
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        let (mut merkle_tree, chunk_count) = MerkleTree::from_reader(data.as_slice(), 64).unwrap();
+        assert_eq!(chunk_count, 16);
+
+        let chunks: Vec<&[u8]> = data.chunks(64).collect();
+        assert_eq!(
+            merkle_tree.get_root(),
+            MerkleTree::from_leaves(&chunks).nodes.last()
+        );
+
+        let (empty_tree, chunk_count) = MerkleTree::from_reader(&[][..], 64).unwrap();
+        assert_eq!(chunk_count, 0);
+        assert!(empty_tree.nodes.is_empty());
+        assert!(MerkleTree::from_reader(data.as_slice(), 0).is_err());
+    }
+
+    #[test]
+    fn test_insert_from_reader() {
+        let large_leaf: Vec<u8> = (0..=255u8).cycle().take(50_000).collect();
+        let mut merkle_tree = MerkleTree::new(2);
+        assert_eq!(
+            merkle_tree
+                .insert_from_reader(large_leaf.as_slice())
+                .unwrap(),
+            0
+        );
+        merkle_tree.insert(b"lorem").unwrap();
+        assert!(merkle_tree.insert_from_reader(&b"ipsum"[..]).is_err());
+        assert_eq!(
+            merkle_tree.get_root(),
+            MerkleTree::from_leaves(&[large_leaf.as_slice(), b"lorem"])
+                .nodes
+                .last()
+        );
+    }
+}
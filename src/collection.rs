@@ -0,0 +1,65 @@
+use crate::MerkleTree;
+use solana_program::hash::Hash;
+use std::ops::Index;
+
+impl MerkleTree {
+    /// Returns an iterator over the leaf hashes.
+    pub fn iter(&self) -> std::slice::Iter<'_, Hash> {
+        self.nodes[0..self.leaf_count].iter()
+    }
+}
+
+impl Index<usize> for MerkleTree {
+    type Output = Hash;
+
+    /// Returns the leaf at given index, panicking if it's out of bounds.
+    fn index(&self, leaf_index: usize) -> &Hash {
+        &self.nodes[0..self.leaf_count][leaf_index]
+    }
+}
+
+impl<'a> IntoIterator for &'a MerkleTree {
+    type Item = &'a Hash;
+    type IntoIter = std::slice::Iter<'a, Hash>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T: AsRef<[u8]>> Extend<T> for MerkleTree {
+    /// Inserts every leaf, panicking if the tree runs out of space.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, leaves: I) {
+        for leaf in leaves {
+            if let Err(err) = self.insert_indexed(leaf) {
+                panic!("{}: tree has {} leaves", err, self.leaf_count);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_collection_impls() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.extend(SAMPLE.iter());
+        let _ = merkle_tree.get_root();
+
+        assert_eq!(merkle_tree.iter().count(), SAMPLE.len());
+        for (leaf_index, leaf) in (&merkle_tree).into_iter().enumerate() {
+            assert_eq!(&merkle_tree[leaf_index], leaf);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_extend_past_capacity() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len() - 1);
+        merkle_tree.extend(SAMPLE.iter());
+    }
+}
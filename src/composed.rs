@@ -0,0 +1,72 @@
+use crate::{MerkleTree, MerkleTreeError, Opening, LEAF_PREFIX};
+use solana_program::hash::{hashv, Hash};
+
+/// Proof of a leaf in an inner tree whose root was inserted as a leaf of an outer tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComposedProof {
+    /// Opening of the leaf in the inner tree.
+    pub inner: Opening,
+    /// Opening of the inner root in the outer tree.
+    pub outer: Opening,
+}
+
+impl ComposedProof {
+    /// Generates the openings for both links, checking that the outer leaf
+    /// at `outer_index` really is the root of the inner tree.
+    pub fn generate(
+        inner_tree: &MerkleTree,
+        leaf_index: usize,
+        outer_tree: &MerkleTree,
+        outer_index: usize,
+    ) -> Result<Self, MerkleTreeError> {
+        let inner = inner_tree.get_opening(leaf_index)?;
+        let outer = outer_tree.get_opening(outer_index)?;
+
+        let inner_root = inner.root;
+        if outer_tree.get_value(outer_index) != Some(&hash_leaf!(inner_root)) {
+            return Err(MerkleTreeError::ProofMismatch(format!(
+                "Leaf {} of the outer tree is not the inner root {}",
+                outer_index, inner_root
+            )));
+        }
+        Ok(Self { inner, outer })
+    }
+
+    /// Returns the root of the outer tree.
+    pub fn root(&self) -> &Hash {
+        &self.outer.root
+    }
+
+    /// Returns true if the leaf is included in the inner tree and the inner root
+    /// is included in the outer tree.
+    pub fn verify(&self, leaf: &Hash) -> bool {
+        let inner_root = self.inner.root;
+        self.inner.verify(leaf) && self.outer.verify(&hash_leaf!(inner_root))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(leaves: &[&[u8]]) -> MerkleTree {
+        let mut merkle_tree = MerkleTree::new(leaves.len());
+        merkle_tree.insert_batch(leaves).unwrap();
+        let _ = merkle_tree.get_root();
+        merkle_tree
+    }
+
+    #[test]
+    fn test_composed_proof() {
+        let shard = build(&[b"lorem", b"ipsum", b"dolor"]);
+        let shard_root = shard.nodes[shard.nodes.len() - 1];
+        let epochs = build(&[b"sit", shard_root.as_ref(), b"amet", b"elit"]);
+
+        let proof = ComposedProof::generate(&shard, 2, &epochs, 1).unwrap();
+        assert_eq!(proof.root(), epochs.nodes.last().unwrap());
+        assert!(proof.verify(shard.get_value(2).unwrap()));
+        assert!(!proof.verify(shard.get_value(1).unwrap()));
+
+        assert!(ComposedProof::generate(&shard, 2, &epochs, 0).is_err());
+    }
+}
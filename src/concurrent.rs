@@ -0,0 +1,263 @@
+use crate::{MerkleTree, MerkleTreeError, Opening, SharedMerkleTree};
+use arc_swap::ArcSwap;
+use solana_program::hash::Hash;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+/// Holds the latest finalized tree behind an atomic pointer. Readers never take a lock:
+/// they load a snapshot and keep serving from it while a writer publishes the next version.
+#[derive(Debug)]
+pub struct AtomicMerkleTree {
+    current: ArcSwap<SharedMerkleTree>,
+}
+
+impl AtomicMerkleTree {
+    pub fn new(tree: SharedMerkleTree) -> Self {
+        Self {
+            current: ArcSwap::from_pointee(tree),
+        }
+    }
+
+    /// Returns the current version of the tree. Openings generated from it stay
+    /// consistent with its root even if a newer version is published meanwhile.
+    pub fn load(&self) -> Arc<SharedMerkleTree> {
+        self.current.load_full()
+    }
+
+    /// Returns the current Merkle Root.
+    pub fn root(&self) -> Option<Hash> {
+        self.current.load().root().copied()
+    }
+
+    /// Returns the opening of a leaf against the current root.
+    pub fn get_opening(&self, leaf_index: usize) -> Result<Opening, MerkleTreeError> {
+        self.current.load().get_opening(leaf_index)
+    }
+
+    /// Atomically replaces the current tree, returning the previous one.
+    pub fn publish(&self, tree: SharedMerkleTree) -> Arc<SharedMerkleTree> {
+        self.current.swap(Arc::new(tree))
+    }
+
+    /// Builds the next version from the current one and publishes it. Concurrent writers
+    /// are serialized by retrying `f` against whichever version won the race.
+    pub fn update<F>(&self, mut f: F) -> Result<Arc<SharedMerkleTree>, MerkleTreeError>
+    where
+        F: FnMut(&SharedMerkleTree) -> Result<SharedMerkleTree, MerkleTreeError>,
+    {
+        loop {
+            let current = self.current.load_full();
+            let next = Arc::new(f(&current)?);
+            let previous = self.current.compare_and_swap(&current, Arc::clone(&next));
+            if Arc::ptr_eq(&previous, &current) {
+                return Ok(next);
+            }
+        }
+    }
+}
+
+/// A finalized tree tagged with the epoch that produced it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeGeneration {
+    pub epoch: u64,
+    pub tree: SharedMerkleTree,
+}
+
+/// A write queued for the background builder.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TreeUpdate {
+    Insert(Vec<u8>),
+    Replace(usize, Vec<u8>),
+}
+
+/// Serves proofs under constant writes. A background thread owns the mutable tree, applies
+/// queued updates in batches and publishes each result as a new generation, while readers
+/// keep using whichever generation they loaded. Updates the tree rejects are skipped and
+/// can be collected with `rejected_updates`.
+#[derive(Debug)]
+pub struct EpochMerkleTree {
+    current: Arc<ArcSwap<TreeGeneration>>,
+    sender: Sender<TreeUpdate>,
+    rejected: Receiver<(TreeUpdate, MerkleTreeError)>,
+    builder: JoinHandle<MerkleTree>,
+}
+
+impl EpochMerkleTree {
+    /// Publishes the tree as epoch 0 and starts the background builder.
+    pub fn spawn(mut merkle_tree: MerkleTree) -> Self {
+        let current = Arc::new(ArcSwap::from_pointee(TreeGeneration {
+            epoch: 0,
+            tree: SharedMerkleTree::copy_from(&mut merkle_tree),
+        }));
+        let (sender, receiver) = mpsc::channel();
+        let (rejected_sender, rejected) = mpsc::channel();
+        let published = Arc::clone(&current);
+        let builder = std::thread::spawn(move || {
+            build_epochs(merkle_tree, receiver, &rejected_sender, &published)
+        });
+        Self {
+            current,
+            sender,
+            rejected,
+            builder,
+        }
+    }
+
+    /// Returns the latest published generation.
+    pub fn load(&self) -> Arc<TreeGeneration> {
+        self.current.load_full()
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.current.load().epoch
+    }
+
+    /// Returns the opening of a leaf against the latest published root.
+    pub fn get_opening(&self, leaf_index: usize) -> Result<Opening, MerkleTreeError> {
+        self.current.load().tree.get_opening(leaf_index)
+    }
+
+    /// Queues an update for the next epoch. Returns false if the builder has stopped.
+    pub fn submit(&self, update: TreeUpdate) -> bool {
+        self.sender.send(update).is_ok()
+    }
+
+    /// Drains the updates the builder has skipped so far, with the error each one failed with.
+    pub fn rejected_updates(&self) -> Vec<(TreeUpdate, MerkleTreeError)> {
+        self.rejected.try_iter().collect()
+    }
+
+    /// Waits for all queued updates to be published and returns the writer's tree.
+    pub fn shutdown(self) -> MerkleTree {
+        drop(self.sender);
+        self.builder.join().expect("builder thread panicked")
+    }
+}
+
+fn build_epochs(
+    mut merkle_tree: MerkleTree,
+    receiver: Receiver<TreeUpdate>,
+    rejected: &Sender<(TreeUpdate, MerkleTreeError)>,
+    published: &ArcSwap<TreeGeneration>,
+) -> MerkleTree {
+    merkle_tree.lazy = true;
+    let mut epoch = 0;
+    while let Ok(update) = receiver.recv() {
+        // Coalesce whatever else is queued into the same epoch
+        for update in std::iter::once(update).chain(receiver.try_iter()) {
+            let result = match &update {
+                TreeUpdate::Insert(leaf) => merkle_tree.insert_indexed(leaf).map(|_| ()),
+                TreeUpdate::Replace(index, leaf) => merkle_tree.update_leaf(*index, leaf),
+            };
+            // A failed update is skipped so the rest of the batch still gets published
+            if let Err(err) = result {
+                let _ = rejected.send((update, err));
+            }
+        }
+        epoch += 1;
+        published.store(Arc::new(TreeGeneration {
+            epoch,
+            tree: SharedMerkleTree::copy_from(&mut merkle_tree),
+        }));
+    }
+    merkle_tree.flush();
+    merkle_tree
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleTree;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_atomic_tree() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(&SAMPLE[..1]).unwrap();
+        let atomic_tree = Arc::new(AtomicMerkleTree::new(merkle_tree.into()));
+        let snapshot = atomic_tree.load();
+
+        let writers: Vec<_> = SAMPLE[1..]
+            .iter()
+            .map(|leaf| {
+                let atomic_tree = Arc::clone(&atomic_tree);
+                std::thread::spawn(move || {
+                    atomic_tree
+                        .update(|tree| tree.with_inserted(&[leaf]))
+                        .unwrap();
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let latest = atomic_tree.load();
+        assert_eq!(latest.current_leaf_index, SAMPLE.len());
+        assert_eq!(atomic_tree.root().as_ref(), latest.root());
+        let opening = atomic_tree.get_opening(0).unwrap();
+        assert!(opening.verify(latest.get_value(0).unwrap()));
+
+        // Readers holding the old version still get openings against its root
+        let stale_opening = snapshot.get_opening(0).unwrap();
+        assert_eq!(Some(&stale_opening.root), snapshot.root());
+        assert_ne!(stale_opening.root, opening.root);
+    }
+
+    #[test]
+    fn test_epoch_tree() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(&SAMPLE[..2]).unwrap();
+        let epoch_tree = EpochMerkleTree::spawn(merkle_tree);
+        let first = epoch_tree.load();
+        assert_eq!(first.epoch, 0);
+
+        for leaf in &SAMPLE[2..] {
+            assert!(epoch_tree.submit(TreeUpdate::Insert(leaf.to_vec())));
+        }
+        assert!(epoch_tree.submit(TreeUpdate::Replace(0, b"foo".to_vec())));
+
+        // The generation loaded earlier is unaffected by the builder
+        let opening = first.tree.get_opening(1).unwrap();
+        assert_eq!(Some(&opening.root), first.tree.root());
+
+        let merkle_tree = epoch_tree.shutdown();
+        let mut expected = MerkleTree::from_leaves(SAMPLE);
+        expected.update_leaf(0, b"foo").unwrap();
+        assert_eq!(merkle_tree.nodes, expected.nodes);
+    }
+
+    #[test]
+    fn test_epoch_tree_skips_rejected_updates() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(&SAMPLE[..4]).unwrap();
+        let epoch_tree = EpochMerkleTree::spawn(merkle_tree);
+
+        assert!(epoch_tree.submit(TreeUpdate::Replace(SAMPLE.len(), b"foo".to_vec())));
+        assert!(epoch_tree.submit(TreeUpdate::Insert(SAMPLE[4].to_vec())));
+        while epoch_tree.load().tree.current_leaf_index < SAMPLE.len() {
+            std::thread::yield_now();
+        }
+        assert_eq!(
+            epoch_tree.load().tree.root(),
+            MerkleTree::from_leaves(SAMPLE).nodes.last()
+        );
+        let rejected = epoch_tree.rejected_updates();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(
+            rejected[0].0,
+            TreeUpdate::Replace(SAMPLE.len(), b"foo".to_vec())
+        );
+        assert!(matches!(
+            rejected[0].1,
+            MerkleTreeError::LeafIndexOutOfBounds(_)
+        ));
+
+        // The builder is still running after the rejected update
+        assert!(epoch_tree.submit(TreeUpdate::Insert(b"foo".to_vec())));
+        let merkle_tree = epoch_tree.shutdown();
+        assert_eq!(merkle_tree.current_leaf_index, SAMPLE.len());
+    }
+}
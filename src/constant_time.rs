@@ -0,0 +1,64 @@
+use crate::{HashDomain, Opening};
+use solana_program::hash::{Hash, HASH_BYTES};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+
+/// Compares two hashes in constant time with `subtle`.
+pub fn ct_eq(a: &Hash, b: &Hash) -> bool {
+    a.as_ref().ct_eq(b.as_ref()).into()
+}
+
+/// Returns `b` if `choice` is set and `a` otherwise, without branching on `choice`.
+fn ct_select(choice: Choice, a: &Hash, b: &Hash) -> Hash {
+    let mut selected = [0u8; HASH_BYTES];
+    for (byte, (x, y)) in selected.iter_mut().zip(a.as_ref().iter().zip(b.as_ref())) {
+        *byte = u8::conditional_select(x, y, choice);
+    }
+    Hash::new_from_array(selected)
+}
+
+impl Opening {
+    /// Like `verify`, but hashes every level and picks each side with masks instead of
+    /// branches, then compares roots in constant time. Use it where the leaf or path is
+    /// secret and timing could leak it.
+    pub fn verify_constant_time(&self, leaf: &Hash) -> bool {
+        self.verify_constant_time_in(HashDomain::Prefix, leaf)
+    }
+
+    /// Like `verify_constant_time`, for trees using the given domain separation.
+    pub fn verify_constant_time_in(&self, domain: HashDomain, leaf: &Hash) -> bool {
+        let mut current_index = self.leaf_index;
+        let mut computed_root = *leaf;
+        for partner in &self.path {
+            let is_right = Choice::from((current_index & 1) as u8);
+            let lnode = ct_select(is_right, &computed_root, partner);
+            let rnode = ct_select(is_right, partner, &computed_root);
+            computed_root = domain.hash_node(&lnode, &rnode);
+            current_index >>= 1;
+        }
+        ct_eq(&computed_root, &self.root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleTree;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_constant_time_verification() {
+        let merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        for leaf_index in 0..SAMPLE.len() {
+            let opening = merkle_tree.get_opening(leaf_index).unwrap();
+            let leaf = merkle_tree.get_value(leaf_index).unwrap();
+            assert!(opening.verify_constant_time(leaf));
+            let other = merkle_tree
+                .get_value((leaf_index + 1) % SAMPLE.len())
+                .unwrap();
+            assert!(!opening.verify_constant_time(other));
+        }
+        assert!(ct_eq(&Hash::default(), &Hash::default()));
+        assert!(!ct_eq(&Hash::default(), &Hash::new_unique()));
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{HashDomain, COMPACT_PROOF_HEADER_LEN};
+use solana_program::hash::HASH_BYTES;
+
+/// Compute units charged per call of the sha256 syscall.
+pub const SOLANA_SHA256_BASE_COST: u64 = 85;
+/// Compute units charged per 2 bytes hashed by the sha256 syscall.
+pub const SOLANA_SHA256_BYTE_COST: u64 = 1;
+/// Minimum compute units charged per slice passed to the sha256 syscall.
+pub const SOLANA_MEM_OP_BASE_COST: u64 = 10;
+/// Gas charged per keccak256 call.
+pub const EVM_KECCAK256_GAS: u64 = 30;
+/// Gas charged per 32-byte word hashed by keccak256.
+pub const EVM_KECCAK256_WORD_GAS: u64 = 6;
+/// Gas charged per non-zero byte of calldata.
+pub const EVM_CALLDATA_BYTE_GAS: u64 = 16;
+/// Gas charged for reading a storage slot for the first time in a transaction.
+pub const EVM_COLD_SLOAD_GAS: u64 = 2100;
+
+/// Where a proof gets verified.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerificationBackend {
+    /// A Solana program hashing with the sha256 syscall under the given domain separation.
+    Solana(HashDomain),
+    /// An EVM contract hashing `0x01 || left || right` with keccak256, as a port of the
+    /// prefix domain would.
+    Evm,
+}
+
+/// Modeled cost of verifying one proof. Only hashing and proof transfer are counted, not
+/// the leaf hash or the surrounding program logic, so treat it as a lower bound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CostEstimate {
+    /// Node hashes computed up the path.
+    pub hashes: usize,
+    /// Compute units on Solana, gas on the EVM.
+    pub cost: u64,
+    /// Bytes of compact proof the verifier receives as instruction data or calldata.
+    pub proof_bytes: usize,
+}
+
+impl CostEstimate {
+    /// Adjusts the estimate for the top `canopy_depth` levels of the path being read from
+    /// on-chain storage instead of being sent with the proof. The hashes are unchanged.
+    pub fn with_canopy(self, backend: VerificationBackend, canopy_depth: usize) -> Self {
+        let canopy_depth = canopy_depth.min(self.hashes);
+        let canopy_bytes = canopy_depth * HASH_BYTES;
+        let cost = match backend {
+            // Account data is already loaded, reading it costs no compute units
+            VerificationBackend::Solana(_) => self.cost,
+            VerificationBackend::Evm => {
+                self.cost - canopy_bytes as u64 * EVM_CALLDATA_BYTE_GAS
+                    + canopy_depth as u64 * EVM_COLD_SLOAD_GAS
+            }
+        };
+        Self {
+            cost,
+            proof_bytes: self.proof_bytes - canopy_bytes,
+            ..self
+        }
+    }
+}
+
+/// Returns the compute units of one sha256 syscall over slices of the given lengths.
+fn solana_sha256_cost(slice_lens: &[usize]) -> u64 {
+    SOLANA_SHA256_BASE_COST
+        + slice_lens
+            .iter()
+            .map(|len| (SOLANA_SHA256_BYTE_COST * (*len as u64 / 2)).max(SOLANA_MEM_OP_BASE_COST))
+            .sum::<u64>()
+}
+
+/// Estimates the cost of verifying a proof of the given depth, to help pick the tree depth
+/// and canopy size before deployment.
+pub fn estimate_verification_cost(depth: usize, backend: VerificationBackend) -> CostEstimate {
+    let proof_bytes = COMPACT_PROOF_HEADER_LEN + depth * HASH_BYTES;
+    let hash_cost = match backend {
+        VerificationBackend::Solana(HashDomain::Prefix) => {
+            solana_sha256_cost(&[1, HASH_BYTES, HASH_BYTES])
+        }
+        VerificationBackend::Solana(HashDomain::Tagged) => solana_sha256_cost(&[HASH_BYTES; 4]),
+        VerificationBackend::Evm => {
+            let words = (1 + 2 * HASH_BYTES as u64 + 31) / 32;
+            EVM_KECCAK256_GAS + words * EVM_KECCAK256_WORD_GAS
+        }
+    };
+    let transfer_cost = match backend {
+        VerificationBackend::Solana(_) => 0,
+        VerificationBackend::Evm => proof_bytes as u64 * EVM_CALLDATA_BYTE_GAS,
+    };
+    CostEstimate {
+        hashes: depth,
+        cost: depth as u64 * hash_cost + transfer_cost,
+        proof_bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_verification_cost() {
+        let prefix =
+            estimate_verification_cost(20, VerificationBackend::Solana(HashDomain::Prefix));
+        assert_eq!(prefix.hashes, 20);
+        assert_eq!(prefix.cost, 20 * (85 + 10 + 16 + 16));
+        assert_eq!(prefix.proof_bytes, 5 + 20 * 32);
+        let tagged =
+            estimate_verification_cost(20, VerificationBackend::Solana(HashDomain::Tagged));
+        assert!(tagged.cost > prefix.cost);
+
+        let with_canopy = prefix.with_canopy(VerificationBackend::Solana(HashDomain::Prefix), 10);
+        assert_eq!(with_canopy.cost, prefix.cost);
+        assert_eq!(with_canopy.proof_bytes, 5 + 10 * 32);
+
+        let evm = estimate_verification_cost(20, VerificationBackend::Evm);
+        assert_eq!(evm.cost, 20 * (30 + 3 * 6) + (5 + 20 * 32) * 16);
+        let evm_canopy = evm.with_canopy(VerificationBackend::Evm, 2);
+        assert_eq!(evm_canopy.cost, evm.cost - 2 * 32 * 16 + 2 * 2100);
+        assert_eq!(evm.with_canopy(VerificationBackend::Evm, 30).proof_bytes, 5);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::{MerkleTree, MerkleTreeError};
+use solana_program::hash::Hash;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// What inserting a leaf whose value is already in the tree does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Inserts the duplicate at the next index.
+    #[default]
+    Allow,
+    /// Fails with `MerkleTreeError::DuplicateLeaf`.
+    Reject,
+    /// Inserts nothing and returns the index of the first occurrence.
+    Dedupe,
+}
+
+/// Index from leaf hash to every index holding it, kept while the policy isn't `Allow`.
+/// All indices are kept so the next occurrence takes over when the first is replaced.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DuplicateIndex {
+    policy: DuplicatePolicy,
+    indices: HashMap<Hash, BTreeSet<usize>>,
+}
+
+impl DuplicateIndex {
+    fn first_index(&self, leaf: &Hash) -> Option<usize> {
+        self.indices
+            .get(leaf)
+            .and_then(|indices| indices.first().copied())
+    }
+}
+
+impl MerkleTree {
+    /// Sets how inserts handle leaves already in the tree. Anything other than `Allow`
+    /// indexes the inserted leaves, starting with the current ones. Updates to existing
+    /// leaves keep the index current but are never rejected.
+    pub fn set_duplicate_policy(&mut self, policy: DuplicatePolicy) {
+        if policy == DuplicatePolicy::Allow {
+            self.duplicates = None;
+            return;
+        }
+        let mut indices: HashMap<Hash, BTreeSet<usize>> = HashMap::new();
+        for (leaf_index, leaf) in self.nodes[..self.current_leaf_index].iter().enumerate() {
+            indices.entry(*leaf).or_default().insert(leaf_index);
+        }
+        self.duplicates = Some(DuplicateIndex { policy, indices });
+    }
+
+    /// Returns the policy for duplicate leaves.
+    pub fn duplicate_policy(&self) -> DuplicatePolicy {
+        self.duplicates
+            .as_ref()
+            .map_or(DuplicatePolicy::Allow, |duplicates| duplicates.policy)
+    }
+
+    /// Returns the indices of the inserted leaves whose hash matches the predicate, in order.
+    pub fn find_leaves<'a, F>(&'a self, mut predicate: F) -> impl Iterator<Item = usize> + 'a
+    where
+        F: FnMut(&Hash) -> bool + 'a,
+    {
+        self.nodes[..self.current_leaf_index]
+            .iter()
+            .enumerate()
+            .filter(move |(_, leaf)| predicate(leaf))
+            .map(|(leaf_index, _)| leaf_index)
+    }
+
+    /// Returns the first index holding each of the leaf hashes, or None for those not in the
+    /// tree. Uses the duplicate index when a policy other than `Allow` keeps one, otherwise
+    /// indexes the leaves once for the whole lookup.
+    pub fn positions_of(&self, leaves: &[Hash]) -> Vec<Option<usize>> {
+        if let Some(duplicates) = self.duplicates.as_ref() {
+            return leaves
+                .iter()
+                .map(|leaf| duplicates.first_index(leaf))
+                .collect();
+        }
+        let wanted: HashSet<&Hash> = leaves.iter().collect();
+        let mut first_index = HashMap::with_capacity(wanted.len());
+        for (leaf_index, leaf) in self.nodes[..self.current_leaf_index].iter().enumerate() {
+            if wanted.contains(leaf) {
+                first_index.entry(*leaf).or_insert(leaf_index);
+            }
+        }
+        leaves
+            .iter()
+            .map(|leaf| first_index.get(leaf).copied())
+            .collect()
+    }
+
+    /// Returns the first index of a leaf hash about to be inserted if the policy dedupes it,
+    /// or an error if the policy rejects it.
+    pub(crate) fn check_duplicate(&self, leaf: &Hash) -> Result<Option<usize>, MerkleTreeError> {
+        let Some(duplicates) = self.duplicates.as_ref() else {
+            return Ok(None);
+        };
+        match (duplicates.policy, duplicates.first_index(leaf)) {
+            (DuplicatePolicy::Reject, Some(leaf_index)) => {
+                Err(MerkleTreeError::DuplicateLeaf(format!(
+                    "Leaf {} is already in the tree at index {}",
+                    leaf, leaf_index
+                )))
+            }
+            (DuplicatePolicy::Dedupe, Some(leaf_index)) => Ok(Some(leaf_index)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Drops from the batch the leaf hashes the policy dedupes, or fails if it rejects any.
+    /// Duplicates within the batch are treated like duplicates of leaves in the tree.
+    pub(crate) fn filter_duplicates(
+        &self,
+        leaves: Vec<Hash>,
+    ) -> Result<Vec<Hash>, MerkleTreeError> {
+        if self.duplicates.is_none() {
+            return Ok(leaves);
+        }
+        let mut seen = HashSet::new();
+        let mut unique = Vec::with_capacity(leaves.len());
+        for leaf in leaves {
+            if self.check_duplicate(&leaf)?.is_some() {
+                continue;
+            }
+            if seen.insert(leaf) {
+                unique.push(leaf);
+            } else if self.duplicate_policy() == DuplicatePolicy::Reject {
+                return Err(MerkleTreeError::DuplicateLeaf(format!(
+                    "Leaf {} appears more than once in the batch",
+                    leaf
+                )));
+            }
+        }
+        Ok(unique)
+    }
+
+    /// Indexes the leaves at the given indices after they were written, replacing `previous`.
+    pub(crate) fn record_duplicates(&mut self, leaf_indices: &[usize], previous: &[Hash]) {
+        let Some(duplicates) = self.duplicates.as_mut() else {
+            return;
+        };
+        for (leaf_index, previous) in leaf_indices.iter().zip(previous) {
+            if let Some(indices) = duplicates.indices.get_mut(previous) {
+                indices.remove(leaf_index);
+                if indices.is_empty() {
+                    duplicates.indices.remove(previous);
+                }
+            }
+        }
+        for leaf_index in leaf_indices {
+            duplicates
+                .indices
+                .entry(self.nodes[*leaf_index])
+                .or_default()
+                .insert(*leaf_index);
+        }
+    }
+
+    /// Forgets leaves at or past `start` after they were rolled back.
+    pub(crate) fn forget_duplicates_from(&mut self, start: usize) {
+        if let Some(duplicates) = self.duplicates.as_mut() {
+            duplicates.indices.retain(|_, indices| {
+                indices.split_off(&start);
+                !indices.is_empty()
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_duplicate_policy() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert(SAMPLE[0]).unwrap();
+        merkle_tree.insert(SAMPLE[0]).unwrap();
+        assert_eq!(merkle_tree.current_leaf_index, 2);
+
+        merkle_tree.set_duplicate_policy(DuplicatePolicy::Reject);
+        assert!(matches!(
+            merkle_tree.insert(SAMPLE[0]),
+            Err(MerkleTreeError::DuplicateLeaf(_))
+        ));
+        assert!(merkle_tree.insert_batch(&[SAMPLE[1], SAMPLE[1]]).is_err());
+        assert_eq!(merkle_tree.current_leaf_index, 2);
+
+        merkle_tree.set_duplicate_policy(DuplicatePolicy::Dedupe);
+        assert_eq!(merkle_tree.insert_indexed(SAMPLE[1]).unwrap(), 2);
+        assert_eq!(merkle_tree.insert_indexed(SAMPLE[0]).unwrap(), 0);
+        assert_eq!(
+            merkle_tree
+                .insert_batch(&[SAMPLE[1], SAMPLE[2], SAMPLE[2]])
+                .unwrap(),
+            3..4
+        );
+
+        // Replaced leaves no longer count as duplicates
+        merkle_tree.update_leaf(2, b"foo").unwrap();
+        assert_eq!(merkle_tree.insert_indexed(SAMPLE[1]).unwrap(), 4);
+        assert_eq!(merkle_tree.insert_indexed(b"foo").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_leaf_lookup() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len() + 1);
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        merkle_tree.insert(SAMPLE[1]).unwrap();
+        let ipsum = merkle_tree.hash_leaf(SAMPLE[1]);
+        let missing = merkle_tree.hash_leaf(b"foo");
+
+        assert_eq!(
+            merkle_tree
+                .find_leaves(|leaf| *leaf == ipsum)
+                .collect::<Vec<_>>(),
+            [1, 5]
+        );
+        let expected = [Some(1), None, Some(4)];
+        let lookup = [ipsum, missing, merkle_tree.nodes[4]];
+        assert_eq!(merkle_tree.positions_of(&lookup), expected);
+        merkle_tree.set_duplicate_policy(DuplicatePolicy::Dedupe);
+        assert_eq!(merkle_tree.positions_of(&lookup), expected);
+    }
+
+    #[test]
+    fn test_replaced_first_occurrence() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(&[SAMPLE[0]; 3]).unwrap();
+        let lorem = merkle_tree.hash_leaf(SAMPLE[0]);
+        merkle_tree.set_duplicate_policy(DuplicatePolicy::Dedupe);
+
+        // The next copy takes over as the first occurrence
+        merkle_tree.update_leaf(0, b"foo").unwrap();
+        assert_eq!(merkle_tree.positions_of(&[lorem]), [Some(1)]);
+        assert_eq!(merkle_tree.insert_indexed(SAMPLE[0]).unwrap(), 1);
+        merkle_tree.update_leaf(1, b"bar").unwrap();
+        assert_eq!(merkle_tree.positions_of(&[lorem]), [Some(2)]);
+
+        merkle_tree.set_duplicate_policy(DuplicatePolicy::Reject);
+        merkle_tree.update_leaf(0, SAMPLE[0]).unwrap();
+        merkle_tree.update_leaf(0, b"foo").unwrap();
+        assert!(matches!(
+            merkle_tree.insert(SAMPLE[0]),
+            Err(MerkleTreeError::DuplicateLeaf(_))
+        ));
+        merkle_tree.update_leaf(2, b"baz").unwrap();
+        assert_eq!(merkle_tree.positions_of(&[lorem]), [None]);
+        assert_eq!(merkle_tree.insert_indexed(SAMPLE[0]).unwrap(), 3);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{MerkleHasher, LEAF_PREFIX, NODE_PREFIX};
+use digest::Digest;
+use solana_program::hash::{Hash, HASH_BYTES};
+use std::marker::PhantomData;
+
+/// Adapts any RustCrypto `Digest` (SHA3, Blake2, Ripemd, ...) into a `MerkleHasher` using the
+/// same 0x00/0x01 prefixes as `HashDomain::Prefix`. Outputs shorter than 32 bytes are padded
+/// with zeros and longer ones truncated.
+pub struct DigestHasher<D>(PhantomData<fn() -> D>);
+
+impl<D> DigestHasher<D> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<D> Default for DigestHasher<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D> Clone for DigestHasher<D> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<D> std::fmt::Debug for DigestHasher<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DigestHasher")
+    }
+}
+
+fn to_hash(output: &[u8]) -> Hash {
+    let mut bytes = [0u8; HASH_BYTES];
+    let len = output.len().min(HASH_BYTES);
+    bytes[..len].copy_from_slice(&output[..len]);
+    Hash::new_from_array(bytes)
+}
+
+impl<D: Digest> MerkleHasher for DigestHasher<D> {
+    fn hash_leaf(&self, leaf: &[u8]) -> Hash {
+        to_hash(
+            &D::new()
+                .chain_update(LEAF_PREFIX)
+                .chain_update(leaf)
+                .finalize(),
+        )
+    }
+
+    fn hash_node(&self, lnode: &Hash, rnode: &Hash) -> Hash {
+        to_hash(
+            &D::new()
+                .chain_update(NODE_PREFIX)
+                .chain_update(lnode)
+                .chain_update(rnode)
+                .finalize(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleTree;
+    use std::sync::Arc;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    fn build(hasher: Arc<dyn MerkleHasher>) -> MerkleTree {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.set_hasher(hasher);
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+        merkle_tree
+    }
+
+    #[test]
+    fn test_digest_hasher() {
+        // SHA-256 through the adapter matches the built-in prefix domain
+        let sha256 = build(Arc::new(DigestHasher::<sha2::Sha256>::new()));
+        assert_eq!(sha256, MerkleTree::from_leaves(SAMPLE));
+
+        let hasher = Arc::new(DigestHasher::<sha3::Sha3_256>::new());
+        let mut sha3 = build(hasher.clone());
+        assert_ne!(sha3.nodes.last(), sha256.nodes.last());
+        let opening = sha3.get_opening(3).unwrap();
+        assert!(opening.verify_with(hasher.as_ref(), &hasher.hash_leaf(SAMPLE[3])));
+        assert!(sha3.verify_opening(&opening).unwrap());
+
+        sha3.update_leaf(3, b"foo").unwrap();
+        assert!(!sha3.verify_opening(&opening).unwrap());
+        assert!(sha3.maybe_contains(b"foo"));
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{MerkleTree, MerkleTreeError, Opening};
+use solana_program::pubkey::Pubkey;
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read},
+    str::FromStr,
+};
+
+/// Merkle distributor over `(address, amount)` claims. Each leaf is the recipient's
+/// address followed by the amount as a little-endian u64.
+#[derive(Debug)]
+pub struct Distributor {
+    pub tree: MerkleTree,
+    pub recipients: Vec<(Pubkey, u64)>,
+    /// Sum of all amounts, i.e. what the distributor must be funded with.
+    pub total_amount: u64,
+    indices: HashMap<Pubkey, usize>,
+}
+
+impl Distributor {
+    /// Returns the leaf committed for a claim.
+    pub fn leaf(address: &Pubkey, amount: u64) -> [u8; 40] {
+        let mut leaf = [0u8; 40];
+        leaf[..32].copy_from_slice(address.as_ref());
+        leaf[32..].copy_from_slice(&amount.to_le_bytes());
+        leaf
+    }
+
+    /// Builds the tree, rejecting duplicate addresses and totals that overflow a u64.
+    pub fn new(recipients: Vec<(Pubkey, u64)>) -> Result<Self, MerkleTreeError> {
+        if recipients.is_empty() {
+            return Err(MerkleTreeError::InvalidRecipients(
+                "No recipients given".to_string(),
+            ));
+        }
+        let mut indices = HashMap::with_capacity(recipients.len());
+        let mut total_amount: u64 = 0;
+        for (index, (address, amount)) in recipients.iter().enumerate() {
+            if indices.insert(*address, index).is_some() {
+                return Err(MerkleTreeError::InvalidRecipients(format!(
+                    "Duplicate recipient {}",
+                    address
+                )));
+            }
+            total_amount = total_amount.checked_add(*amount).ok_or_else(|| {
+                MerkleTreeError::InvalidRecipients(format!(
+                    "Total amount overflows at recipient {}",
+                    address
+                ))
+            })?;
+        }
+        let leaves: Vec<[u8; 40]> = recipients
+            .iter()
+            .map(|(address, amount)| Self::leaf(address, *amount))
+            .collect();
+        Ok(Self {
+            tree: MerkleTree::from_leaves(&leaves),
+            recipients,
+            total_amount,
+            indices,
+        })
+    }
+
+    /// Reads `address,amount` rows, with an optional header row. Addresses are base58.
+    pub fn from_csv<R: Read>(reader: R) -> Result<Self, MerkleTreeError> {
+        let mut recipients = vec![];
+        for (line_number, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || (line_number == 0 && line.eq_ignore_ascii_case("address,amount"))
+            {
+                continue;
+            }
+            let Some((address, amount)) = line.split_once(',') else {
+                return Err(MerkleTreeError::InvalidRecipients(format!(
+                    "Line {} isn't an address,amount row",
+                    line_number + 1
+                )));
+            };
+            recipients.push(parse_recipient(
+                address.trim(),
+                amount.trim(),
+                line_number + 1,
+            )?);
+        }
+        Self::new(recipients)
+    }
+
+    /// Reads an array of `{"address": "...", "amount": ...}` objects. Amounts may be numbers
+    /// or strings, since JSON numbers above 2^53 aren't portable.
+    #[cfg(feature = "json")]
+    pub fn from_json<R: Read>(reader: R) -> Result<Self, MerkleTreeError> {
+        let rows: Vec<serde_json::Value> = serde_json::from_reader(reader).map_err(|e| {
+            MerkleTreeError::InvalidRecipients(format!("Invalid recipients JSON: {}", e))
+        })?;
+        let mut recipients = Vec::with_capacity(rows.len());
+        for (row_number, row) in rows.iter().enumerate() {
+            let address = row["address"].as_str().unwrap_or_default();
+            let amount = match &row["amount"] {
+                serde_json::Value::String(amount) => amount.clone(),
+                amount => amount.to_string(),
+            };
+            recipients.push(parse_recipient(address, &amount, row_number + 1)?);
+        }
+        Self::new(recipients)
+    }
+
+    /// Returns the index of the recipient's leaf.
+    pub fn index_of(&self, address: &Pubkey) -> Option<usize> {
+        self.indices.get(address).copied()
+    }
+
+    /// Returns the proof of the recipient's claim.
+    pub fn proof(&self, address: &Pubkey) -> Result<Opening, MerkleTreeError> {
+        let index = self.index_of(address).ok_or_else(|| {
+            MerkleTreeError::InvalidRecipients(format!("{} isn't a recipient", address))
+        })?;
+        self.tree.get_opening(index)
+    }
+
+    /// Returns the proofs of every claim, in recipient order.
+    pub fn proofs(&self) -> Result<Vec<Opening>, MerkleTreeError> {
+        (0..self.recipients.len())
+            .map(|index| self.tree.get_opening(index))
+            .collect()
+    }
+}
+
+fn parse_recipient(
+    address: &str,
+    amount: &str,
+    row: usize,
+) -> Result<(Pubkey, u64), MerkleTreeError> {
+    let address = Pubkey::from_str(address).map_err(|_| {
+        MerkleTreeError::InvalidRecipients(format!("Row {} has an invalid address", row))
+    })?;
+    let amount = amount.parse::<u64>().map_err(|_| {
+        MerkleTreeError::InvalidRecipients(format!(
+            "Row {} has an amount that isn't a u64: {}",
+            row, amount
+        ))
+    })?;
+    Ok((address, amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LEAF_PREFIX;
+    use solana_program::hash::hashv;
+
+    #[test]
+    fn test_distributor_from_csv() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let csv = format!("address,amount\n{},100\n{},250\n", alice, bob);
+        let distributor = Distributor::from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(distributor.total_amount, 350);
+
+        let proof = distributor.proof(&bob).unwrap();
+        let leaf = Distributor::leaf(&bob, 250);
+        assert!(proof.verify(&hash_leaf!(leaf)));
+        assert_eq!(distributor.proofs().unwrap()[1], proof);
+        assert!(distributor.proof(&Pubkey::new_unique()).is_err());
+
+        let duplicate = format!("{},1\n{},2\n", alice, alice);
+        assert!(Distributor::from_csv(duplicate.as_bytes()).is_err());
+        let overflow = format!("{},{}\n{},1\n", alice, u64::MAX, bob);
+        assert!(Distributor::from_csv(overflow.as_bytes()).is_err());
+        let negative = format!("{},-1\n", alice);
+        assert!(Distributor::from_csv(negative.as_bytes()).is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_distributor_from_json() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let json = format!(
+            r#"[{{"address": "{}", "amount": 100}}, {{"address": "{}", "amount": "18446744073709551515"}}]"#,
+            alice, bob
+        );
+        let distributor = Distributor::from_json(json.as_bytes()).unwrap();
+        assert_eq!(distributor.recipients[1], (bob, u64::MAX - 100));
+        assert_eq!(distributor.total_amount, u64::MAX);
+        assert!(Distributor::from_json(&b"[{\"address\": \"x\", \"amount\": 1}]"[..]).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{LEAF_PREFIX, NODE_PREFIX};
+use solana_program::hash::{hash, hashv, Hash, Hasher};
+use std::{
+    io::{ErrorKind, Read},
+    sync::OnceLock,
+};
+
+/// Tag for leaf hashes under `HashDomain::Tagged`.
+pub const LEAF_TAG: &[u8] = b"FastMerkleTree/leaf";
+/// Tag for interior node hashes under `HashDomain::Tagged`.
+pub const NODE_TAG: &[u8] = b"FastMerkleTree/node";
+
+/// How leaf and node hashes are kept apart to prevent second pre-image attacks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum HashDomain {
+    /// Prepends 0x00 to leaves and 0x01 to nodes.
+    #[default]
+    Prefix,
+    /// BIP-340 style tagged hashes: `SHA256(SHA256(tag) || SHA256(tag) || data)`.
+    Tagged,
+}
+
+impl HashDomain {
+    /// Hashes raw leaf data.
+    pub fn hash_leaf(&self, leaf: &[u8]) -> Hash {
+        match self {
+            HashDomain::Prefix => hash_leaf!(leaf),
+            HashDomain::Tagged => tagged_hash(leaf_tag(), &[leaf]),
+        }
+    }
+
+    /// Like `hash_leaf`, streaming the leaf from the reader in fixed-size blocks so large
+    /// leaves never have to be held in memory at once.
+    pub fn hash_leaf_reader<R: Read>(&self, mut reader: R) -> std::io::Result<Hash> {
+        let mut hasher = Hasher::default();
+        match self {
+            HashDomain::Prefix => hasher.hash(LEAF_PREFIX),
+            HashDomain::Tagged => hasher.hashv(&[leaf_tag().as_ref(), leaf_tag().as_ref()]),
+        }
+        let mut block = [0u8; 8192];
+        loop {
+            match reader.read(&mut block) {
+                Ok(0) => return Ok(hasher.result()),
+                Ok(read) => hasher.hash(&block[..read]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Hashes a pair of child nodes into their parent.
+    pub fn hash_node(&self, lnode: &Hash, rnode: &Hash) -> Hash {
+        match self {
+            HashDomain::Prefix => hash_node!(lnode, rnode),
+            HashDomain::Tagged => tagged_hash(node_tag(), &[lnode.as_ref(), rnode.as_ref()]),
+        }
+    }
+}
+
+/// Returns `SHA256(tag_hash || tag_hash || data)` where `tag_hash` is `SHA256(tag)`.
+pub fn tagged_hash(tag_hash: &Hash, data: &[&[u8]]) -> Hash {
+    let mut vals: Vec<&[u8]> = Vec::with_capacity(data.len() + 2);
+    vals.push(tag_hash.as_ref());
+    vals.push(tag_hash.as_ref());
+    vals.extend_from_slice(data);
+    hashv(&vals)
+}
+
+fn leaf_tag() -> &'static Hash {
+    static LEAF_TAG_HASH: OnceLock<Hash> = OnceLock::new();
+    LEAF_TAG_HASH.get_or_init(|| hash(LEAF_TAG))
+}
+
+fn node_tag() -> &'static Hash {
+    static NODE_TAG_HASH: OnceLock<Hash> = OnceLock::new();
+    NODE_TAG_HASH.get_or_init(|| hash(NODE_TAG))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleTree;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_tagged_tree() {
+        let tag_hash = hash(LEAF_TAG);
+        assert_eq!(
+            HashDomain::Tagged.hash_leaf(b"lorem"),
+            hashv(&[tag_hash.as_ref(), tag_hash.as_ref(), b"lorem"])
+        );
+
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.hash_domain = HashDomain::Tagged;
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let root = *merkle_tree.get_root().unwrap();
+        assert_ne!(&root, MerkleTree::from_leaves(SAMPLE).nodes.last().unwrap());
+
+        merkle_tree.update_leaf(2, b"foo").unwrap();
+        let opening = merkle_tree.get_opening(2).unwrap();
+        let leaf = HashDomain::Tagged.hash_leaf(b"foo");
+        assert!(opening.verify_in(HashDomain::Tagged, &leaf));
+        assert!(!opening.verify(&leaf));
+        assert!(merkle_tree.verify_opening(&opening).unwrap());
+
+        for domain in [HashDomain::Prefix, HashDomain::Tagged] {
+            let large_leaf = vec![7u8; 20_000];
+            assert_eq!(
+                domain.hash_leaf_reader(&large_leaf[..]).unwrap(),
+                domain.hash_leaf(&large_leaf)
+            );
+        }
+    }
+}
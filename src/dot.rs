@@ -0,0 +1,87 @@
+use crate::{pretty::abbreviate_hash, MerkleTree, MerkleTreeError};
+use std::fmt::Write;
+
+impl MerkleTree {
+    /// Renders the top `max_depth + 1` levels of a built tree as a Graphviz digraph, labelling
+    /// each node with its level, index and abbreviated hash. Nodes whose children were cut off
+    /// are dashed. Render it with e.g. `dot -Tsvg tree.dot -o tree.svg`.
+    pub fn to_dot(&self, max_depth: usize) -> Result<String, MerkleTreeError> {
+        if !self.is_built() {
+            return Err(MerkleTreeError::RootNotComputed(
+                "Tree must be built before it can be rendered".to_string(),
+            ));
+        }
+        let layout = self.layout();
+        let levels = layout.levels();
+        let height = levels.len() - 1;
+        let lowest_level = height.saturating_sub(max_depth);
+
+        let mut dot =
+            String::from("digraph merkle_tree {\n    node [shape=box, fontname=\"monospace\"];\n");
+        for level in (lowest_level..=height).rev() {
+            for (index, node) in self.nodes[levels[level].clone()].iter().enumerate() {
+                let style = if level == lowest_level && level > 0 {
+                    ", style=dashed"
+                } else {
+                    ""
+                };
+                // Writing to a String can't fail
+                let _ = writeln!(
+                    dot,
+                    "    n{}_{} [label=\"L{} #{}\\n{}\"{}];",
+                    level,
+                    index,
+                    level,
+                    index,
+                    abbreviate_hash(node),
+                    style
+                );
+                if level > lowest_level {
+                    let children = levels[level - 1].len();
+                    for child in [2 * index, 2 * index + 1] {
+                        if child < children {
+                            let _ = writeln!(
+                                dot,
+                                "    n{}_{} -> n{}_{};",
+                                level,
+                                index,
+                                level - 1,
+                                child
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        dot.push_str("}\n");
+        Ok(dot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_to_dot() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        assert!(merkle_tree.to_dot(1).is_err());
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+
+        let dot = merkle_tree.to_dot(usize::MAX).unwrap();
+        assert!(dot.starts_with("digraph merkle_tree {"));
+        assert_eq!(dot.matches("[label=").count(), merkle_tree.nodes.len());
+        assert_eq!(dot.matches(" -> ").count(), merkle_tree.nodes.len() - 1);
+        // The duplicated last node of odd levels gets a single edge
+        assert!(dot.contains("n1_2 -> n0_4;"));
+        assert!(!dot.contains("n0_5"));
+        assert!(!dot.contains("dashed"));
+
+        let truncated = merkle_tree.to_dot(1).unwrap();
+        assert_eq!(truncated.matches("[label=").count(), 3);
+        assert_eq!(truncated.matches("dashed").count(), 2);
+    }
+}
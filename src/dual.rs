@@ -0,0 +1,123 @@
+use crate::{MerkleHasher, MerkleTree, MerkleTreeError, Opening, LEAF_PREFIX, NODE_PREFIX};
+use solana_program::{hash::Hash, keccak};
+use std::{ops::Range, sync::Arc};
+
+/// Keccak-256 with the crate's 0x00 leaf and 0x01 node prefixes, for roots verified by
+/// EVM contracts.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KeccakHasher;
+
+impl MerkleHasher for KeccakHasher {
+    fn hash_leaf(&self, leaf: &[u8]) -> Hash {
+        Hash::new_from_array(keccak::hashv(&[LEAF_PREFIX, leaf]).to_bytes())
+    }
+
+    fn hash_node(&self, lnode: &Hash, rnode: &Hash) -> Hash {
+        Hash::new_from_array(
+            keccak::hashv(&[NODE_PREFIX, lnode.as_ref(), rnode.as_ref()]).to_bytes(),
+        )
+    }
+}
+
+/// Two trees over the same leaves fed by a single insert stream: `primary` hashes with the
+/// default SHA-256 domain and `secondary` with another backend, e.g. `KeccakHasher` for an
+/// EVM bridge. Each keeps its own leaf hashes since the backends hash leaves differently.
+#[derive(Clone, Debug)]
+pub struct DualRootTree {
+    pub primary: MerkleTree,
+    pub secondary: MerkleTree,
+}
+
+impl DualRootTree {
+    pub fn new(leaf_count: usize, secondary_hasher: Arc<dyn MerkleHasher>) -> Self {
+        let mut secondary = MerkleTree::new(leaf_count);
+        secondary.set_hasher(secondary_hasher);
+        Self {
+            primary: MerkleTree::new(leaf_count),
+            secondary,
+        }
+    }
+
+    /// Inserts the leaf into both trees and returns the index it was assigned.
+    pub fn insert<T: AsRef<[u8]>>(&mut self, leaf: T) -> Result<usize, MerkleTreeError> {
+        let leaf_index = self.primary.insert_indexed(leaf.as_ref())?;
+        self.secondary.insert_indexed(leaf)?;
+        Ok(leaf_index)
+    }
+
+    /// Inserts the leaves into both trees and returns the range of indices they were given.
+    pub fn insert_batch<T: AsRef<[u8]>>(
+        &mut self,
+        leaves: &[T],
+    ) -> Result<Range<usize>, MerkleTreeError> {
+        let range = self.primary.insert_batch(leaves)?;
+        self.secondary.insert_batch(leaves)?;
+        Ok(range)
+    }
+
+    /// Replaces the leaf at the given index in both trees.
+    pub fn update_leaf<T: AsRef<[u8]>>(
+        &mut self,
+        leaf_index: usize,
+        leaf: T,
+    ) -> Result<(), MerkleTreeError> {
+        self.primary.update_leaf(leaf_index, leaf.as_ref())?;
+        self.secondary.update_leaf(leaf_index, leaf)
+    }
+
+    /// Builds both trees and returns their roots as `(primary, secondary)`.
+    pub fn roots(&mut self) -> Result<(Hash, Hash), MerkleTreeError> {
+        let primary = self.primary.get_root().copied();
+        let secondary = self.secondary.get_root().copied();
+        match (primary, secondary) {
+            (Some(primary), Some(secondary)) => Ok((primary, secondary)),
+            _ => Err(MerkleTreeError::RootNotComputed(
+                "Tree has no leaves to compute roots from".to_string(),
+            )),
+        }
+    }
+
+    /// Returns the openings of the leaf in both trees as `(primary, secondary)`.
+    pub fn get_openings(&self, leaf_index: usize) -> Result<(Opening, Opening), MerkleTreeError> {
+        Ok((
+            self.primary.get_opening(leaf_index)?,
+            self.secondary.get_opening(leaf_index)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_dual_root_tree() {
+        let mut dual_tree = DualRootTree::new(SAMPLE.len(), Arc::new(KeccakHasher));
+        dual_tree.insert(SAMPLE[0]).unwrap();
+        assert_eq!(dual_tree.insert_batch(&SAMPLE[1..]).unwrap(), 1..5);
+        let (primary, secondary) = dual_tree.roots().unwrap();
+        assert_eq!(
+            &primary,
+            MerkleTree::from_leaves(SAMPLE).nodes.last().unwrap()
+        );
+        assert_ne!(primary, secondary);
+
+        let (_, opening) = dual_tree.get_openings(3).unwrap();
+        assert_eq!(opening.root, secondary);
+        assert!(opening.verify_with(&KeccakHasher, &KeccakHasher.hash_leaf(SAMPLE[3])));
+
+        dual_tree.update_leaf(3, b"foo").unwrap();
+        let (_, updated) = dual_tree.roots().unwrap();
+        assert_eq!(
+            dual_tree
+                .secondary
+                .get_opening(3)
+                .unwrap()
+                .compute_root_with(&KeccakHasher, &KeccakHasher.hash_leaf(b"foo")),
+            updated
+        );
+        assert_ne!(updated, secondary);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{DEFAULT_LEAF, NODE_PREFIX};
+use solana_program::hash::{hashv, Hash};
+
+/// Height of the tallest all-default subtree in `EMPTY_SUBTREE_ROOTS`.
+pub const MAX_EMPTY_SUBTREE_HEIGHT: usize = 32;
+
+/// Roots of subtrees whose leaves are all `DEFAULT_LEAF`, indexed by height. Entry 0 is
+/// `DEFAULT_LEAF` itself and entry `h + 1` hashes entry `h` with itself.
+pub const EMPTY_SUBTREE_ROOTS: [[u8; 32]; MAX_EMPTY_SUBTREE_HEIGHT + 1] = [
+    DEFAULT_LEAF,
+    [
+        254, 67, 214, 106, 250, 74, 154, 92, 79, 156, 157, 168, 159, 79, 251, 82, 99, 92, 143, 52,
+        46, 127, 251, 115, 29, 104, 227, 108, 89, 130, 7, 42,
+    ],
+    [
+        222, 184, 46, 21, 89, 84, 214, 190, 20, 89, 44, 102, 204, 247, 161, 236, 225, 147, 238,
+        235, 205, 171, 175, 116, 123, 145, 244, 69, 25, 240, 159, 71,
+    ],
+    [
+        41, 96, 4, 76, 98, 242, 53, 78, 148, 94, 141, 120, 253, 210, 32, 160, 95, 44, 8, 121, 242,
+        77, 246, 241, 30, 245, 204, 38, 181, 39, 10, 14,
+    ],
+    [
+        76, 250, 188, 72, 198, 137, 138, 48, 177, 181, 209, 45, 218, 142, 9, 169, 110, 158, 161,
+        126, 128, 244, 178, 160, 80, 184, 168, 180, 128, 63, 189, 67,
+    ],
+    [
+        113, 98, 237, 132, 143, 25, 116, 14, 83, 118, 108, 224, 26, 192, 153, 82, 59, 9, 157, 89,
+        62, 7, 130, 221, 188, 82, 150, 238, 206, 80, 236, 80,
+    ],
+    [
+        43, 227, 207, 5, 81, 204, 105, 54, 212, 97, 227, 220, 67, 243, 196, 191, 80, 203, 238, 27,
+        192, 145, 146, 82, 84, 232, 121, 244, 231, 102, 94, 148,
+    ],
+    [
+        18, 219, 82, 98, 165, 80, 13, 37, 22, 184, 248, 35, 98, 210, 168, 114, 120, 210, 15, 113,
+        47, 241, 252, 226, 1, 157, 66, 236, 186, 23, 36, 29,
+    ],
+    [
+        26, 26, 146, 101, 248, 105, 103, 108, 32, 104, 36, 170, 123, 252, 47, 232, 199, 254, 52,
+        105, 29, 221, 251, 53, 121, 123, 106, 50, 31, 151, 125, 252,
+    ],
+    [
+        110, 11, 184, 36, 62, 38, 139, 227, 210, 250, 60, 232, 50, 52, 178, 248, 80, 200, 81, 98,
+        189, 15, 206, 211, 14, 145, 158, 6, 155, 213, 45, 247,
+    ],
+    [
+        1, 98, 137, 47, 166, 105, 181, 85, 104, 45, 76, 86, 102, 244, 44, 152, 242, 48, 231, 100,
+        6, 214, 70, 230, 219, 188, 239, 181, 211, 17, 224, 71,
+    ],
+    [
+        253, 85, 147, 240, 191, 222, 8, 202, 164, 23, 69, 168, 166, 178, 213, 220, 174, 160, 58,
+        88, 103, 232, 67, 42, 153, 91, 234, 58, 31, 212, 223, 86,
+    ],
+    [
+        123, 188, 210, 122, 224, 184, 245, 215, 192, 19, 220, 109, 19, 162, 229, 134, 181, 143,
+        131, 234, 198, 42, 166, 42, 165, 111, 51, 34, 136, 173, 139, 244,
+    ],
+    [
+        214, 200, 47, 144, 227, 65, 204, 54, 170, 15, 181, 248, 208, 59, 187, 62, 109, 81, 72, 235,
+        86, 252, 247, 158, 180, 21, 87, 74, 238, 127, 169, 154,
+    ],
+    [
+        226, 182, 73, 196, 250, 112, 60, 50, 63, 194, 201, 41, 173, 38, 157, 253, 209, 80, 189,
+        230, 134, 45, 155, 206, 190, 150, 98, 68, 185, 131, 242, 15,
+    ],
+    [
+        72, 193, 42, 141, 214, 117, 233, 220, 211, 198, 49, 65, 251, 253, 230, 209, 16, 86, 195,
+        146, 180, 55, 156, 59, 189, 199, 154, 133, 17, 208, 230, 91,
+    ],
+    [
+        216, 51, 137, 172, 154, 32, 127, 183, 219, 220, 73, 47, 187, 86, 185, 72, 47, 25, 23, 6,
+        153, 226, 36, 190, 100, 105, 76, 200, 133, 163, 162, 162,
+    ],
+    [
+        237, 204, 145, 168, 180, 153, 49, 112, 213, 245, 93, 113, 212, 35, 79, 233, 229, 155, 124,
+        0, 67, 64, 18, 205, 2, 63, 60, 186, 134, 10, 224, 51,
+    ],
+    [
+        254, 232, 98, 46, 186, 77, 99, 155, 243, 225, 56, 84, 167, 122, 120, 53, 6, 8, 158, 242,
+        196, 139, 132, 214, 239, 122, 210, 84, 252, 149, 92, 74,
+    ],
+    [
+        226, 209, 17, 204, 185, 170, 51, 178, 161, 27, 138, 210, 127, 38, 82, 35, 19, 16, 192, 50,
+        232, 114, 92, 238, 187, 212, 28, 72, 26, 228, 203, 231,
+    ],
+    [
+        16, 214, 196, 35, 8, 36, 130, 94, 114, 150, 164, 41, 123, 67, 222, 155, 179, 223, 159, 66,
+        180, 185, 205, 101, 10, 57, 180, 79, 171, 178, 42, 251,
+    ],
+    [
+        40, 104, 244, 115, 54, 120, 14, 156, 200, 4, 110, 212, 195, 48, 207, 121, 251, 12, 97, 151,
+        18, 32, 143, 103, 192, 46, 72, 56, 129, 65, 226, 237,
+    ],
+    [
+        88, 77, 185, 38, 55, 56, 176, 208, 149, 101, 21, 174, 48, 129, 242, 149, 233, 148, 223,
+        198, 231, 167, 231, 164, 210, 209, 193, 181, 77, 96, 194, 47,
+    ],
+    [
+        185, 84, 143, 58, 40, 125, 191, 66, 91, 163, 45, 247, 8, 9, 40, 237, 193, 155, 27, 225,
+        130, 235, 96, 186, 38, 37, 126, 83, 0, 113, 228, 34,
+    ],
+    [
+        141, 100, 70, 212, 198, 78, 231, 235, 177, 34, 31, 237, 103, 233, 91, 5, 64, 54, 250, 32,
+        118, 227, 17, 66, 99, 139, 115, 72, 232, 117, 173, 199,
+    ],
+    [
+        112, 61, 245, 42, 111, 79, 112, 223, 199, 15, 218, 140, 31, 24, 61, 94, 48, 192, 242, 193,
+        147, 6, 73, 232, 195, 147, 20, 161, 183, 32, 126, 186,
+    ],
+    [
+        158, 71, 206, 94, 174, 127, 192, 104, 93, 196, 88, 19, 88, 66, 199, 130, 171, 121, 236, 47,
+        175, 75, 68, 245, 45, 131, 203, 39, 76, 128, 93, 3,
+    ],
+    [
+        248, 151, 1, 61, 235, 119, 43, 163, 203, 119, 128, 192, 0, 44, 56, 2, 103, 36, 191, 178,
+        54, 225, 94, 68, 226, 219, 146, 95, 20, 254, 93, 187,
+    ],
+    [
+        43, 158, 170, 20, 129, 70, 248, 61, 206, 99, 75, 54, 29, 95, 95, 217, 33, 91, 72, 235, 152,
+        241, 12, 214, 241, 224, 34, 189, 158, 10, 127, 7,
+    ],
+    [
+        222, 61, 99, 28, 0, 71, 141, 126, 250, 58, 229, 240, 95, 217, 46, 251, 168, 219, 140, 148,
+        199, 82, 27, 242, 51, 77, 110, 200, 33, 37, 67, 17,
+    ],
+    [
+        73, 246, 182, 140, 7, 25, 149, 247, 100, 8, 252, 6, 58, 7, 23, 49, 136, 196, 38, 156, 243,
+        119, 198, 174, 46, 187, 111, 250, 148, 76, 77, 166,
+    ],
+    [
+        200, 2, 66, 42, 245, 54, 209, 251, 168, 78, 2, 166, 168, 245, 105, 55, 120, 222, 121, 15,
+        139, 203, 81, 223, 226, 32, 194, 130, 12, 17, 39, 159,
+    ],
+    [
+        165, 223, 168, 50, 54, 78, 110, 117, 224, 95, 212, 128, 247, 86, 30, 73, 229, 147, 94, 184,
+        87, 54, 205, 216, 105, 221, 25, 235, 171, 17, 185, 18,
+    ],
+];
+
+/// Returns the root of an all-default subtree of the given height, hashing past the table
+/// for heights above `MAX_EMPTY_SUBTREE_HEIGHT`.
+pub fn empty_subtree_root(height: usize) -> Hash {
+    let mut root = EMPTY_SUBTREE_ROOTS[height.min(MAX_EMPTY_SUBTREE_HEIGHT)];
+    for _ in MAX_EMPTY_SUBTREE_HEIGHT..height {
+        root = hash_node!(root, root).to_bytes();
+    }
+    Hash::new_from_array(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleTree;
+
+    #[test]
+    fn test_empty_subtree_roots() {
+        let mut zero = DEFAULT_LEAF;
+        for empty_root in EMPTY_SUBTREE_ROOTS {
+            assert_eq!(empty_root, zero);
+            zero = hash_node!(zero, zero).to_bytes();
+        }
+        assert_eq!(
+            empty_subtree_root(MAX_EMPTY_SUBTREE_HEIGHT + 1).to_bytes(),
+            zero
+        );
+
+        for height in 0..5 {
+            let mut merkle_tree = MerkleTree::new(1 << height);
+            assert_eq!(merkle_tree.get_root(), Some(&empty_subtree_root(height)));
+        }
+    }
+}
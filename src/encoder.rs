@@ -0,0 +1,172 @@
+use crate::{MerkleTree, MerkleTreeError};
+use borsh::BorshSerialize;
+use solana_program::pubkey::Pubkey;
+
+/// Converts typed leaves into the canonical bytes that get hashed into the tree.
+pub trait LeafEncoder<T: ?Sized> {
+    fn encode(&self, value: &T) -> Result<Vec<u8>, MerkleTreeError>;
+}
+
+/// Encodes leaves with borsh, the default for `insert_typed`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BorshEncoder;
+
+impl<T: BorshSerialize + ?Sized> LeafEncoder<T> for BorshEncoder {
+    fn encode(&self, value: &T) -> Result<Vec<u8>, MerkleTreeError> {
+        Ok(value.try_to_vec()?)
+    }
+}
+
+/// Byte order of integers in the fixed-layout encoders.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Endianness {
+    /// Matches borsh and on-chain `u64::to_le_bytes`.
+    #[default]
+    Little,
+    Big,
+}
+
+/// Airdrop claims as `pubkey (32) || amount (u64)`, the layout `Distributor` commits to
+/// when little-endian.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PubkeyAmountEncoder(pub Endianness);
+
+/// Allow-list entries as `pubkey (32) || id (u64)`, e.g. an asset id or mint index.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PubkeyIdEncoder(pub Endianness);
+
+/// Leaves that are already 32 bytes, like a pubkey or a precomputed hash, taken as is.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RawEncoder;
+
+fn encode_pubkey_u64(pubkey: &Pubkey, value: u64, endianness: Endianness) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(40);
+    bytes.extend_from_slice(pubkey.as_ref());
+    match endianness {
+        Endianness::Little => bytes.extend_from_slice(&value.to_le_bytes()),
+        Endianness::Big => bytes.extend_from_slice(&value.to_be_bytes()),
+    }
+    bytes
+}
+
+impl LeafEncoder<(Pubkey, u64)> for PubkeyAmountEncoder {
+    fn encode(&self, (pubkey, amount): &(Pubkey, u64)) -> Result<Vec<u8>, MerkleTreeError> {
+        Ok(encode_pubkey_u64(pubkey, *amount, self.0))
+    }
+}
+
+impl LeafEncoder<(Pubkey, u64)> for PubkeyIdEncoder {
+    fn encode(&self, (pubkey, id): &(Pubkey, u64)) -> Result<Vec<u8>, MerkleTreeError> {
+        Ok(encode_pubkey_u64(pubkey, *id, self.0))
+    }
+}
+
+impl LeafEncoder<[u8; 32]> for RawEncoder {
+    fn encode(&self, value: &[u8; 32]) -> Result<Vec<u8>, MerkleTreeError> {
+        Ok(value.to_vec())
+    }
+}
+
+impl LeafEncoder<Pubkey> for RawEncoder {
+    fn encode(&self, value: &Pubkey) -> Result<Vec<u8>, MerkleTreeError> {
+        Ok(value.to_bytes().to_vec())
+    }
+}
+
+impl MerkleTree {
+    /// Inserts a typed leaf encoded with borsh and returns the index it was assigned.
+    pub fn insert_typed<T: BorshSerialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<usize, MerkleTreeError> {
+        self.insert_encoded(&BorshEncoder, value)
+    }
+
+    /// Inserts a typed leaf encoded with the given encoder and returns the index it was assigned.
+    pub fn insert_encoded<T: ?Sized, E: LeafEncoder<T>>(
+        &mut self,
+        encoder: &E,
+        value: &T,
+    ) -> Result<usize, MerkleTreeError> {
+        let leaf = encoder.encode(value)?;
+        self.insert_indexed(leaf)
+    }
+
+    /// Replaces the leaf at the given index with a typed leaf encoded with borsh.
+    pub fn update_typed<T: BorshSerialize + ?Sized>(
+        &mut self,
+        leaf_index: usize,
+        value: &T,
+    ) -> Result<(), MerkleTreeError> {
+        let leaf = BorshEncoder.encode(value)?;
+        self.update_leaf(leaf_index, &leaf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Distributor;
+
+    #[derive(BorshSerialize)]
+    struct Claim {
+        recipient: Pubkey,
+        amount: u64,
+    }
+
+    /// Little-endian amount followed by the address, unlike borsh's field order.
+    struct AmountFirst;
+
+    impl LeafEncoder<Claim> for AmountFirst {
+        fn encode(&self, value: &Claim) -> Result<Vec<u8>, MerkleTreeError> {
+            let mut bytes = value.amount.to_le_bytes().to_vec();
+            bytes.extend_from_slice(value.recipient.as_ref());
+            Ok(bytes)
+        }
+    }
+
+    #[test]
+    fn test_insert_typed() {
+        let claims: Vec<Claim> = (0..3)
+            .map(|amount| Claim {
+                recipient: Pubkey::new_unique(),
+                amount,
+            })
+            .collect();
+        let mut merkle_tree = MerkleTree::new(claims.len() + 1);
+        for (leaf_index, claim) in claims.iter().enumerate() {
+            assert_eq!(merkle_tree.insert_typed(claim).unwrap(), leaf_index);
+        }
+        merkle_tree
+            .insert_encoded(&AmountFirst, &claims[0])
+            .unwrap();
+
+        let leaves: Vec<Vec<u8>> = claims
+            .iter()
+            .map(|claim| claim.try_to_vec().unwrap())
+            .chain([AmountFirst.encode(&claims[0]).unwrap()])
+            .collect();
+        assert_eq!(leaves[0].len(), 40);
+        assert_eq!(
+            merkle_tree.get_root(),
+            MerkleTree::from_leaves(&leaves).nodes.last()
+        );
+
+        merkle_tree.update_typed(3, &claims[1]).unwrap();
+        assert_eq!(merkle_tree.nodes[3], merkle_tree.nodes[1]);
+    }
+
+    #[test]
+    fn test_claim_encoders() {
+        let address = Pubkey::new_unique();
+        let claim = (address, 0x0102);
+        assert_eq!(
+            PubkeyAmountEncoder::default().encode(&claim).unwrap(),
+            Distributor::leaf(&address, 0x0102)
+        );
+        let big_endian = PubkeyIdEncoder(Endianness::Big).encode(&claim).unwrap();
+        assert_eq!(&big_endian[32..], &[0, 0, 0, 0, 0, 0, 1, 2]);
+        assert_eq!(RawEncoder.encode(&address).unwrap(), address.as_ref());
+        assert_eq!(RawEncoder.encode(&[7u8; 32]).unwrap(), [7u8; 32]);
+    }
+}
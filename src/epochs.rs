@@ -0,0 +1,96 @@
+use crate::{ComposedProof, MerkleTree, MerkleTreeError};
+use solana_program::hash::Hash;
+
+/// A sequence of fixed trees, one per epoch, whose roots are committed to by a parent tree.
+/// Proofs compose a leaf's opening in its epoch with the epoch root's opening in the parent.
+#[derive(Debug, Default)]
+pub struct EpochTree {
+    epochs: Vec<MerkleTree>,
+    parent: Option<MerkleTree>,
+}
+
+impl EpochTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seals the tree as the next epoch and commits its root to the parent. Returns the
+    /// epoch number it was assigned.
+    pub fn push_epoch(&mut self, mut epoch_tree: MerkleTree) -> Result<usize, MerkleTreeError> {
+        if epoch_tree.root().is_none() {
+            return Err(MerkleTreeError::RootNotComputed(
+                "Epoch tree has no leaves".to_string(),
+            ));
+        }
+        self.epochs.push(epoch_tree);
+        // The parent is append-only, but rebuilding it is cheap next to the epochs themselves
+        let epoch_roots: Vec<Hash> = self
+            .epochs
+            .iter()
+            .map(|epoch_tree| epoch_tree.nodes[epoch_tree.nodes.len() - 1])
+            .collect();
+        self.parent = Some(MerkleTree::from_leaves(&epoch_roots));
+        Ok(self.epochs.len() - 1)
+    }
+
+    /// Returns the sealed tree of the given epoch.
+    pub fn epoch(&self, epoch: usize) -> Option<&MerkleTree> {
+        self.epochs.get(epoch)
+    }
+
+    pub fn epoch_count(&self) -> usize {
+        self.epochs.len()
+    }
+
+    /// Returns the root of the parent tree, committing to every epoch.
+    pub fn root(&self) -> Option<&Hash> {
+        self.parent.as_ref().and_then(|parent| parent.nodes.last())
+    }
+
+    /// Returns the proof of the leaf at `leaf_index` in `epoch` against the parent root.
+    pub fn prove(&self, epoch: usize, leaf_index: usize) -> Result<ComposedProof, MerkleTreeError> {
+        let (Some(epoch_tree), Some(parent)) = (self.epochs.get(epoch), self.parent.as_ref())
+        else {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "There are {} epochs but epoch given was {}",
+                self.epochs.len(),
+                epoch
+            )));
+        };
+        ComposedProof::generate(epoch_tree, leaf_index, parent, epoch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_epoch_tree() {
+        let mut epoch_tree = EpochTree::new();
+        assert!(epoch_tree.root().is_none());
+        assert!(epoch_tree.prove(0, 0).is_err());
+
+        let days: [&[&[u8]]; 3] = [
+            &[b"lorem", b"ipsum"],
+            &[b"dolor", b"sit", b"amet"],
+            &[b"elit"],
+        ];
+        for (day, leaves) in days.iter().enumerate() {
+            assert_eq!(
+                epoch_tree
+                    .push_epoch(MerkleTree::from_leaves(leaves))
+                    .unwrap(),
+                day
+            );
+        }
+        assert!(epoch_tree.push_epoch(MerkleTree::new(0)).is_err());
+
+        let proof = epoch_tree.prove(1, 2).unwrap();
+        assert_eq!(Some(proof.root()), epoch_tree.root());
+        let leaf = *epoch_tree.epoch(1).unwrap().get_value(2).unwrap();
+        assert!(proof.verify(&leaf));
+        assert!(epoch_tree.prove(1, 3).is_err());
+        assert!(epoch_tree.prove(3, 0).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use crate::MerkleTree;
+use solana_program::hash::Hash;
+use std::{hash::Hasher, sync::Mutex};
+
+impl MerkleTree {
+    /// Returns the root if the tree is built and has no pending updates.
+    pub fn finalized_root(&self) -> Option<&Hash> {
+        if self.is_built() && self.dirty_leaves.is_empty() {
+            self.nodes.last()
+        } else {
+            None
+        }
+    }
+
+    /// Builds or flushes both trees as needed and compares their leaf counts and roots.
+    pub fn roots_equal(&mut self, other: &mut Self) -> bool {
+        self.leaf_count == other.leaf_count && self.root().copied() == other.root().copied()
+    }
+}
+
+/// Clones the tree with its settings and history. The proof cache starts out empty.
+impl Clone for MerkleTree {
+    fn clone(&self) -> Self {
+        Self {
+            leaf_count: self.leaf_count,
+            nodes: self.nodes.clone(),
+            current_leaf_index: self.current_leaf_index,
+            lazy: self.lazy,
+            memoize_subtrees: self.memoize_subtrees,
+            hash_domain: self.hash_domain,
+            dirty_leaves: self.dirty_leaves.clone(),
+            root_history: self.root_history.clone(),
+            root_history_capacity: self.root_history_capacity,
+            root_version: self.root_version,
+            proof_cache: Default::default(),
+            layout: Mutex::new(self.layout.lock().unwrap().clone()),
+            undo_log: self.undo_log.clone(),
+            undo_capacity: self.undo_capacity,
+            bloom: self.bloom.clone(),
+            audit_trace: self.audit_trace.clone(),
+            metrics: self.metrics.clone(),
+            root_chain: self.root_chain,
+            duplicates: self.duplicates.clone(),
+            hasher: self.hasher.clone(),
+            progress: self.progress.clone(),
+        }
+    }
+}
+
+/// Trees are equal if they have the same leaf count and finalized root. Trees that aren't
+/// finalized only equal each other, when their leaves match; use `roots_equal` to compare
+/// a tree with pending updates against a built one.
+impl PartialEq for MerkleTree {
+    fn eq(&self, other: &Self) -> bool {
+        if self.leaf_count != other.leaf_count {
+            return false;
+        }
+        match (self.finalized_root(), other.finalized_root()) {
+            (Some(root), Some(other_root)) => root == other_root,
+            (None, None) => {
+                self.hash_domain == other.hash_domain
+                    && self.nodes[..self.leaf_count.min(self.nodes.len())]
+                        == other.nodes[..other.leaf_count.min(other.nodes.len())]
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for MerkleTree {}
+
+impl std::hash::Hash for MerkleTree {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.leaf_count.hash(state);
+        self.finalized_root().hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    // The proof cache is interior mutable but isn't part of the hash
+    #[allow(clippy::mutable_key_type)]
+    fn test_tree_equality() {
+        let built = MerkleTree::from_leaves(SAMPLE);
+        let mut unbuilt = MerkleTree::new(SAMPLE.len());
+        unbuilt.insert_batch(SAMPLE).unwrap();
+        assert_ne!(built, unbuilt);
+        assert_eq!(unbuilt, unbuilt.clone());
+
+        let mut lazy = built.clone();
+        lazy.lazy = true;
+        lazy.update_leaf(0, b"foo").unwrap();
+        assert_ne!(lazy, built);
+        lazy.update_leaf(0, SAMPLE[0]).unwrap();
+        assert!(lazy.roots_equal(&mut unbuilt));
+        assert_eq!(lazy, built);
+        assert_eq!(unbuilt, built);
+
+        let trees: HashSet<MerkleTree> = [
+            built.clone(),
+            unbuilt,
+            MerkleTree::from_leaves(&SAMPLE[..4]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(trees.len(), 2);
+        assert!(trees.contains(&built));
+    }
+}
@@ -0,0 +1,230 @@
+//! `extern "C"` bindings so non-Rust services can reuse the exact hashing rules.
+//! Build the C library with `cargo rustc --lib --release --features ffi --crate-type cdylib`
+//! (or `staticlib`) and generate the header with
+//! `cbindgen --config cbindgen.toml --output fast_merkle_tree.h`.
+use crate::{MerkleTree, MerkleTreeError, Opening, LEAF_PREFIX};
+use solana_program::hash::{hashv, Hash, HASH_BYTES};
+use std::slice;
+
+pub const FMT_OK: i32 = 0;
+pub const FMT_ERR_NULL_POINTER: i32 = -1;
+pub const FMT_ERR_OUT_OF_BOUNDS: i32 = -2;
+pub const FMT_ERR_ROOT_NOT_COMPUTED: i32 = -3;
+pub const FMT_ERR_BUFFER_TOO_SMALL: i32 = -4;
+
+/// Creates a tree with the given number of leaves. Free it with `fmt_tree_free`.
+/// Returns null if the leaf count is above `DEFAULT_MAX_LEAF_COUNT`.
+#[no_mangle]
+pub extern "C" fn fmt_tree_new(leaf_count: usize) -> *mut MerkleTree {
+    match MerkleTree::try_new(leaf_count) {
+        Ok(merkle_tree) => Box::into_raw(Box::new(merkle_tree)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Frees a tree created by `fmt_tree_new`.
+///
+/// # Safety
+/// `tree` must come from `fmt_tree_new` and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn fmt_tree_free(tree: *mut MerkleTree) {
+    if !tree.is_null() {
+        drop(Box::from_raw(tree));
+    }
+}
+
+/// Inserts `len` bytes at `leaf` as the next leaf and writes its index to `leaf_index`.
+///
+/// # Safety
+/// `tree` must be a valid tree, `leaf` must point to `len` readable bytes and
+/// `leaf_index` must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn fmt_tree_insert(
+    tree: *mut MerkleTree,
+    leaf: *const u8,
+    len: usize,
+    leaf_index: *mut usize,
+) -> i32 {
+    let Some(tree) = tree.as_mut() else {
+        return FMT_ERR_NULL_POINTER;
+    };
+    if leaf.is_null() && len > 0 {
+        return FMT_ERR_NULL_POINTER;
+    }
+    let leaf = if len > 0 {
+        slice::from_raw_parts(leaf, len)
+    } else {
+        &[]
+    };
+    match tree.insert_indexed(leaf) {
+        Ok(index) => {
+            if !leaf_index.is_null() {
+                *leaf_index = index;
+            }
+            FMT_OK
+        }
+        Err(_) => FMT_ERR_OUT_OF_BOUNDS,
+    }
+}
+
+/// Computes the root and writes its 32 bytes to `root`.
+///
+/// # Safety
+/// `tree` must be a valid tree and `root` must point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn fmt_tree_root(tree: *mut MerkleTree, root: *mut u8) -> i32 {
+    let Some(tree) = tree.as_mut() else {
+        return FMT_ERR_NULL_POINTER;
+    };
+    if root.is_null() {
+        return FMT_ERR_NULL_POINTER;
+    }
+    match tree.root() {
+        Some(hash) => {
+            slice::from_raw_parts_mut(root, HASH_BYTES).copy_from_slice(hash.as_ref());
+            FMT_OK
+        }
+        None => FMT_ERR_ROOT_NOT_COMPUTED,
+    }
+}
+
+/// Writes the opening path of a leaf as consecutive 32 byte nodes into `path`, which can
+/// hold `path_capacity` nodes, and the number of nodes written to `path_len`. Returns
+/// `FMT_ERR_ROOT_NOT_COMPUTED` until `fmt_tree_root` has built the tree.
+///
+/// # Safety
+/// `tree` must be a valid tree, `path` must point to `path_capacity * 32` writable bytes
+/// and `path_len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn fmt_tree_get_opening(
+    tree: *const MerkleTree,
+    leaf_index: usize,
+    path: *mut u8,
+    path_capacity: usize,
+    path_len: *mut usize,
+) -> i32 {
+    let Some(tree) = tree.as_ref() else {
+        return FMT_ERR_NULL_POINTER;
+    };
+    if path.is_null() || path_len.is_null() {
+        return FMT_ERR_NULL_POINTER;
+    }
+    let opening = match tree.get_opening(leaf_index) {
+        Ok(opening) => opening,
+        Err(MerkleTreeError::RootNotComputed(_)) => return FMT_ERR_ROOT_NOT_COMPUTED,
+        Err(_) => return FMT_ERR_OUT_OF_BOUNDS,
+    };
+    if opening.path.len() > path_capacity {
+        return FMT_ERR_BUFFER_TOO_SMALL;
+    }
+    let path = slice::from_raw_parts_mut(path, opening.path.len() * HASH_BYTES);
+    for (out, partner) in path.chunks_exact_mut(HASH_BYTES).zip(&opening.path) {
+        out.copy_from_slice(partner.as_ref());
+    }
+    *path_len = opening.path.len();
+    FMT_OK
+}
+
+/// Returns true if the raw leaf bytes and opening path compute to the 32 byte root.
+///
+/// # Safety
+/// `root` must point to 32 readable bytes, `path` to `path_len * 32` readable bytes and
+/// `leaf` to `leaf_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn fmt_verify_opening(
+    root: *const u8,
+    leaf_index: usize,
+    path: *const u8,
+    path_len: usize,
+    leaf: *const u8,
+    leaf_len: usize,
+) -> bool {
+    if root.is_null() || (path.is_null() && path_len > 0) || (leaf.is_null() && leaf_len > 0) {
+        return false;
+    }
+    let path = if path_len > 0 {
+        slice::from_raw_parts(path, path_len * HASH_BYTES)
+    } else {
+        &[]
+    };
+    let leaf = if leaf_len > 0 {
+        slice::from_raw_parts(leaf, leaf_len)
+    } else {
+        &[]
+    };
+    let opening = Opening {
+        leaf_index,
+        path: path.chunks_exact(HASH_BYTES).map(Hash::new).collect(),
+        root: Hash::new(slice::from_raw_parts(root, HASH_BYTES)),
+    };
+    opening.verify(&hash_leaf!(leaf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_ffi_roundtrip() {
+        unsafe {
+            let tree = fmt_tree_new(SAMPLE.len());
+            let mut leaf_index = usize::MAX;
+            for (i, leaf) in SAMPLE.iter().enumerate() {
+                assert_eq!(
+                    fmt_tree_insert(tree, leaf.as_ptr(), leaf.len(), &mut leaf_index),
+                    FMT_OK
+                );
+                assert_eq!(leaf_index, i);
+            }
+            assert_eq!(
+                fmt_tree_insert(tree, SAMPLE[0].as_ptr(), 5, &mut leaf_index),
+                FMT_ERR_OUT_OF_BOUNDS
+            );
+
+            let mut path = [0u8; 8 * HASH_BYTES];
+            let mut path_len = 0;
+            assert_eq!(
+                fmt_tree_get_opening(tree, 3, path.as_mut_ptr(), 8, &mut path_len),
+                FMT_ERR_ROOT_NOT_COMPUTED
+            );
+
+            let mut root = [0u8; HASH_BYTES];
+            assert_eq!(fmt_tree_root(tree, root.as_mut_ptr()), FMT_OK);
+
+            assert_eq!(
+                fmt_tree_get_opening(tree, 3, path.as_mut_ptr(), 1, &mut path_len),
+                FMT_ERR_BUFFER_TOO_SMALL
+            );
+            assert_eq!(
+                fmt_tree_get_opening(tree, 3, path.as_mut_ptr(), 8, &mut path_len),
+                FMT_OK
+            );
+            assert_eq!(path_len, 3);
+            assert_eq!(
+                fmt_tree_get_opening(tree, 5, path.as_mut_ptr(), 8, &mut path_len),
+                FMT_ERR_OUT_OF_BOUNDS
+            );
+
+            let leaf = SAMPLE[3];
+            assert!(fmt_verify_opening(
+                root.as_ptr(),
+                3,
+                path.as_ptr(),
+                path_len,
+                leaf.as_ptr(),
+                leaf.len()
+            ));
+            assert!(!fmt_verify_opening(
+                root.as_ptr(),
+                2,
+                path.as_ptr(),
+                path_len,
+                leaf.as_ptr(),
+                leaf.len()
+            ));
+            fmt_tree_free(tree);
+        }
+    }
+}
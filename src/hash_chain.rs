@@ -0,0 +1,144 @@
+use crate::{HashDomain, MerkleTree, MerkleTreeError};
+use solana_program::hash::Hash;
+use std::ops::Range;
+
+/// Sequential log commitment where each head is `hash_node(prev_head, hash_leaf(entry))`
+/// under the chain's `HashDomain`, starting from the default hash. Entry hashes are kept so
+/// any segment can be turned into a tree for random-access proofs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HashChain {
+    pub hash_domain: HashDomain,
+    entries: Vec<Hash>,
+    heads: Vec<Hash>,
+}
+
+impl HashChain {
+    pub fn new(hash_domain: HashDomain) -> Self {
+        Self {
+            hash_domain,
+            ..Default::default()
+        }
+    }
+
+    /// Appends an entry and returns the new head.
+    pub fn append<T: AsRef<[u8]>>(&mut self, entry: T) -> Hash {
+        let entry = self.hash_domain.hash_leaf(entry.as_ref());
+        self.append_hash(entry)
+    }
+
+    /// Appends an entry that's already leaf hashed and returns the new head.
+    pub fn append_hash(&mut self, entry: Hash) -> Hash {
+        let head = self.hash_domain.hash_node(&self.head(), &entry);
+        self.entries.push(entry);
+        self.heads.push(head);
+        head
+    }
+
+    /// Head committing to every entry, or the default hash if there are none.
+    pub fn head(&self) -> Hash {
+        self.heads.last().copied().unwrap_or_default()
+    }
+
+    /// Head right after the entry at `index` was appended.
+    pub fn head_at(&self, index: usize) -> Option<Hash> {
+        self.heads.get(index).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Leaf hashes of the entries, in order.
+    pub fn entries(&self) -> &[Hash] {
+        &self.entries
+    }
+
+    /// Builds a tree over the entries in `range` with the chain's domain, so the tree's
+    /// leaf `i` is entry `range.start + i`.
+    pub fn to_tree(&self, range: Range<usize>) -> Result<MerkleTree, MerkleTreeError> {
+        if range.start > range.end || range.end > self.entries.len() {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Chain has {} entries but range given was {:?}",
+                self.entries.len(),
+                range
+            )));
+        }
+        let nodes = self.entries[range].to_vec();
+        let leaf_count = nodes.len();
+        let mut merkle_tree = MerkleTree {
+            leaf_count,
+            nodes,
+            current_leaf_index: leaf_count,
+            hash_domain: self.hash_domain,
+            ..Default::default()
+        };
+        let _ = merkle_tree.get_root();
+        Ok(merkle_tree)
+    }
+}
+
+/// Returns true if chaining the leaf hashed `entries` onto `prev_head` gives `head`.
+pub fn verify_hash_chain(
+    hash_domain: HashDomain,
+    prev_head: &Hash,
+    entries: &[Hash],
+    head: &Hash,
+) -> bool {
+    let computed = entries.iter().fold(*prev_head, |head, entry| {
+        hash_domain.hash_node(&head, entry)
+    });
+    computed == *head
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_hash_chain() {
+        let mut chain = HashChain::new(HashDomain::Prefix);
+        for entry in SAMPLE {
+            chain.append(entry);
+        }
+        assert_eq!(chain.len(), SAMPLE.len());
+        assert!(verify_hash_chain(
+            chain.hash_domain,
+            &chain.head_at(1).unwrap(),
+            &chain.entries()[2..],
+            &chain.head()
+        ));
+        assert!(!verify_hash_chain(
+            chain.hash_domain,
+            &Hash::default(),
+            &chain.entries()[1..],
+            &chain.head()
+        ));
+
+        // The whole chain commits to the same leaves as a tree over the stream
+        let merkle_tree = chain.to_tree(0..SAMPLE.len()).unwrap();
+        assert_eq!(merkle_tree, MerkleTree::from_leaves(SAMPLE));
+
+        let segment = chain.to_tree(2..5).unwrap();
+        let opening = segment.get_opening(1).unwrap();
+        assert!(opening.verify(&chain.entries()[3]));
+        assert!(chain.to_tree(3..6).is_err());
+    }
+
+    #[test]
+    fn test_tagged_hash_chain() {
+        let mut chain = HashChain::new(HashDomain::Tagged);
+        let head = chain.append(SAMPLE[0]);
+        assert_eq!(
+            head,
+            HashDomain::Tagged
+                .hash_node(&Hash::default(), &HashDomain::Tagged.hash_leaf(SAMPLE[0]))
+        );
+        assert_eq!(chain.to_tree(0..1).unwrap().hash_domain, HashDomain::Tagged);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{HashDomain, MerkleTree};
+use solana_program::hash::Hash;
+use std::{
+    io::{self, Read},
+    sync::Arc,
+};
+
+/// Hash function for leaves and interior nodes, for trees that don't hash with one of the
+/// `HashDomain` schemes.
+pub trait MerkleHasher: Send + Sync {
+    /// Hashes raw leaf data.
+    fn hash_leaf(&self, leaf: &[u8]) -> Hash;
+
+    /// Hashes a pair of child nodes into their parent.
+    fn hash_node(&self, lnode: &Hash, rnode: &Hash) -> Hash;
+
+    /// Hashes a leaf read to the end of the reader. Reads it into memory by default.
+    fn hash_leaf_reader(&self, reader: &mut dyn Read) -> io::Result<Hash> {
+        let mut leaf = vec![];
+        reader.read_to_end(&mut leaf)?;
+        Ok(self.hash_leaf(&leaf))
+    }
+}
+
+impl MerkleHasher for HashDomain {
+    fn hash_leaf(&self, leaf: &[u8]) -> Hash {
+        HashDomain::hash_leaf(self, leaf)
+    }
+
+    fn hash_node(&self, lnode: &Hash, rnode: &Hash) -> Hash {
+        HashDomain::hash_node(self, lnode, rnode)
+    }
+
+    fn hash_leaf_reader(&self, reader: &mut dyn Read) -> io::Result<Hash> {
+        HashDomain::hash_leaf_reader(self, reader)
+    }
+}
+
+/// The hasher a tree uses: its `hash_domain`, or a custom one set with `set_hasher`.
+#[derive(Clone)]
+pub(crate) enum TreeHasher {
+    Domain(HashDomain),
+    Custom(Arc<dyn MerkleHasher>),
+}
+
+impl TreeHasher {
+    /// The custom hasher if there is one, otherwise the domain.
+    pub(crate) fn new(hash_domain: HashDomain, hasher: Option<&Arc<dyn MerkleHasher>>) -> Self {
+        match hasher {
+            Some(hasher) => TreeHasher::Custom(hasher.clone()),
+            None => TreeHasher::Domain(hash_domain),
+        }
+    }
+}
+
+/// Returns true if both are the same custom hasher, or both are absent. Hashers are
+/// compared by identity since they can't be compared by value.
+pub(crate) fn same_hasher(
+    hasher: Option<&Arc<dyn MerkleHasher>>,
+    other: Option<&Arc<dyn MerkleHasher>>,
+) -> bool {
+    match (hasher, other) {
+        (Some(hasher), Some(other)) => std::ptr::eq(
+            Arc::as_ptr(hasher) as *const u8,
+            Arc::as_ptr(other) as *const u8,
+        ),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+impl MerkleHasher for TreeHasher {
+    fn hash_leaf(&self, leaf: &[u8]) -> Hash {
+        match self {
+            TreeHasher::Domain(domain) => domain.hash_leaf(leaf),
+            TreeHasher::Custom(hasher) => hasher.hash_leaf(leaf),
+        }
+    }
+
+    fn hash_node(&self, lnode: &Hash, rnode: &Hash) -> Hash {
+        match self {
+            TreeHasher::Domain(domain) => domain.hash_node(lnode, rnode),
+            TreeHasher::Custom(hasher) => hasher.hash_node(lnode, rnode),
+        }
+    }
+
+    fn hash_leaf_reader(&self, reader: &mut dyn Read) -> io::Result<Hash> {
+        match self {
+            TreeHasher::Domain(domain) => domain.hash_leaf_reader(reader),
+            TreeHasher::Custom(hasher) => hasher.hash_leaf_reader(reader),
+        }
+    }
+}
+
+/// Hashes leaves with a closure and nodes with the hasher it replaced.
+struct LeafFnHasher<F> {
+    leaf_fn: F,
+    node_hasher: TreeHasher,
+}
+
+impl<F: Fn(&[u8]) -> Hash + Send + Sync> MerkleHasher for LeafFnHasher<F> {
+    fn hash_leaf(&self, leaf: &[u8]) -> Hash {
+        (self.leaf_fn)(leaf)
+    }
+
+    fn hash_node(&self, lnode: &Hash, rnode: &Hash) -> Hash {
+        self.node_hasher.hash_node(lnode, rnode)
+    }
+}
+
+impl MerkleTree {
+    /// Hashes inserted leaves with the closure, e.g. to hash a struct's canonical fields or
+    /// double hash, keeping the tree's node hashing. Like `set_hasher`, use it before
+    /// inserting any leaves.
+    pub fn with_leaf_hasher<F>(mut self, leaf_fn: F) -> Self
+    where
+        F: Fn(&[u8]) -> Hash + Send + Sync + 'static,
+    {
+        let node_hasher = self.tree_hasher();
+        self.set_hasher(Arc::new(LeafFnHasher {
+            leaf_fn,
+            node_hasher,
+        }));
+        self
+    }
+
+    /// Hashes leaves and nodes with the given hasher instead of `hash_domain`. Set it before
+    /// inserting any leaves. Helpers that take a `HashDomain`, like `Opening::verify_in`,
+    /// don't know about it; use `Opening::verify_with` instead.
+    pub fn set_hasher(&mut self, hasher: Arc<dyn MerkleHasher>) {
+        self.hasher = Some(hasher);
+    }
+
+    /// Returns the hasher the tree builds with.
+    pub(crate) fn tree_hasher(&self) -> TreeHasher {
+        TreeHasher::new(self.hash_domain, self.hasher.as_ref())
+    }
+
+    /// Hashes raw leaf data the way inserts do, with the tree's hasher or `hash_domain`.
+    pub fn hash_leaf(&self, leaf: &[u8]) -> Hash {
+        match &self.hasher {
+            Some(hasher) => hasher.hash_leaf(leaf),
+            None => self.hash_domain.hash_leaf(leaf),
+        }
+    }
+
+    /// Hashes a pair of child nodes the way the tree builds its interior levels.
+    pub fn hash_node(&self, lnode: &Hash, rnode: &Hash) -> Hash {
+        match &self.hasher {
+            Some(hasher) => hasher.hash_node(lnode, rnode),
+            None => self.hash_domain.hash_node(lnode, rnode),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::hash::hashv;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_with_leaf_hasher() {
+        let double_hash = |leaf: &[u8]| hashv(&[hashv(&[leaf]).as_ref()]);
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len()).with_leaf_hasher(double_hash);
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+        assert_eq!(merkle_tree.nodes[1], double_hash(SAMPLE[1]));
+        assert_eq!(
+            merkle_tree.nodes[SAMPLE.len()],
+            HashDomain::Prefix.hash_node(&merkle_tree.nodes[0], &merkle_tree.nodes[1])
+        );
+
+        let opening = merkle_tree.get_opening(3).unwrap();
+        assert!(merkle_tree.verify_opening(&opening).unwrap());
+        assert!(opening.verify(&double_hash(SAMPLE[3])));
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{MerkleTree, Opening};
+use solana_program::hash::Hash;
+
+/// A root the tree has had, tagged with the version it was computed at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecentRoot {
+    pub version: u64,
+    pub root: Hash,
+}
+
+impl MerkleTree {
+    /// Keeps the last `capacity` distinct roots, dropping the oldest ones if needed.
+    /// A capacity of 0 (the default) disables the history.
+    pub fn keep_recent_roots(&mut self, capacity: usize) {
+        self.root_history_capacity = capacity;
+        while self.root_history.len() > capacity {
+            self.root_history.pop_front();
+        }
+    }
+
+    /// Returns the recorded roots, oldest first.
+    pub fn recent_roots(&self) -> impl Iterator<Item = &RecentRoot> {
+        self.root_history.iter()
+    }
+
+    /// Returns the version of the most recent root the opening verifies against, if any,
+    /// hashing with the tree's hasher.
+    pub fn verify_against_recent(&self, opening: &Opening, leaf: &Hash) -> Option<u64> {
+        let computed_root = opening.compute_root_with(&self.tree_hasher(), leaf);
+        self.root_history
+            .iter()
+            .rev()
+            .find(|recent| recent.root == computed_root)
+            .map(|recent| recent.version)
+    }
+
+    /// Records the current root if it differs from the last recorded one.
+    pub(crate) fn record_root(&mut self) {
+        let Some(&root) = self.nodes.last() else {
+            return;
+        };
+        if self.root_history.back().map(|recent| recent.root) == Some(root) {
+            return;
+        }
+        self.root_version += 1;
+        if self.root_history_capacity == 0 {
+            return;
+        }
+        if self.root_history.len() == self.root_history_capacity {
+            self.root_history.pop_front();
+        }
+        self.root_history.push_back(RecentRoot {
+            version: self.root_version,
+            root,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HashDomain;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_recent_roots() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.keep_recent_roots(2);
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+        let first_opening = merkle_tree.get_opening(0).unwrap();
+        let _ = merkle_tree.get_root();
+
+        merkle_tree.update_leaf(4, b"foo").unwrap();
+        let second_opening = merkle_tree.get_opening(0).unwrap();
+        merkle_tree.update_leaf(4, b"bar").unwrap();
+
+        let versions: Vec<u64> = merkle_tree.recent_roots().map(|r| r.version).collect();
+        assert_eq!(versions, vec![2, 3]);
+
+        let leaf = merkle_tree.nodes[0];
+        assert_eq!(
+            merkle_tree.verify_against_recent(&second_opening, &leaf),
+            Some(2)
+        );
+        assert_eq!(
+            merkle_tree.verify_against_recent(&first_opening, &leaf),
+            None
+        );
+    }
+
+    #[test]
+    fn test_recent_roots_tagged() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.hash_domain = HashDomain::Tagged;
+        merkle_tree.keep_recent_roots(2);
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+        let opening = merkle_tree.get_opening(1).unwrap();
+        merkle_tree.update_leaf(4, b"foo").unwrap();
+        let _ = merkle_tree.get_root();
+
+        let leaf = merkle_tree.nodes[1];
+        assert_eq!(merkle_tree.verify_against_recent(&opening, &leaf), Some(1));
+        assert_eq!(
+            merkle_tree.verify_against_recent(&merkle_tree.get_opening(1).unwrap(), &leaf),
+            Some(2)
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{ChangeLogEvent, ChangeLogMirror, MerkleTreeError};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_program::hash::Hash;
+
+/// Prefix of the log lines written by `sol_log_data`.
+pub const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+/// Source of changelog events, e.g. an adapter over a Geyser plugin stream.
+pub trait ChangeLogSource {
+    /// Returns the next event, or None once the source is exhausted.
+    fn next_event(&mut self) -> Option<Result<ChangeLogEvent, MerkleTreeError>>;
+}
+
+/// Parses a program log line. Returns None for lines that don't carry program data.
+pub fn parse_log_line(line: &str) -> Option<Result<ChangeLogEvent, MerkleTreeError>> {
+    let data = line.strip_prefix(PROGRAM_DATA_PREFIX)?;
+    // sol_log_data writes each field as its own base64 string, the event is the first one
+    let encoded = data.split_whitespace().next()?;
+    Some(
+        STANDARD
+            .decode(encoded)
+            .map_err(|e| {
+                MerkleTreeError::InvalidChangeLog(format!("Invalid base64 in program data: {}", e))
+            })
+            .and_then(|bytes| ChangeLogEvent::from_bytes(&bytes)),
+    )
+}
+
+/// Reads events out of transaction log lines, skipping lines without program data.
+#[derive(Debug)]
+pub struct LogEventSource<I> {
+    lines: I,
+}
+
+impl<I> LogEventSource<I> {
+    pub fn new(lines: I) -> Self {
+        Self { lines }
+    }
+}
+
+impl<I, S> ChangeLogSource for LogEventSource<I>
+where
+    I: Iterator<Item = S>,
+    S: AsRef<str>,
+{
+    fn next_event(&mut self) -> Option<Result<ChangeLogEvent, MerkleTreeError>> {
+        self.lines
+            .by_ref()
+            .find_map(|line| parse_log_line(line.as_ref()))
+    }
+}
+
+/// Reconstructs a tree maintained on-chain by replaying its changelog events.
+#[derive(Debug)]
+pub struct TreeIndexer {
+    pub mirror: ChangeLogMirror,
+    /// Number of events applied so far.
+    pub applied: u64,
+}
+
+impl TreeIndexer {
+    /// Starts from an empty tree of `2^depth` default leaves.
+    pub fn new(depth: usize) -> Self {
+        Self {
+            mirror: ChangeLogMirror::new(depth),
+            applied: 0,
+        }
+    }
+
+    /// Applies every event from the source, stopping at the first malformed event or
+    /// divergence. Returns the number of events applied.
+    pub fn sync<S: ChangeLogSource>(&mut self, source: &mut S) -> Result<u64, MerkleTreeError> {
+        let mut applied = 0;
+        while let Some(event) = source.next_event() {
+            self.mirror.apply(&event?)?;
+            self.applied += 1;
+            applied += 1;
+        }
+        Ok(applied)
+    }
+
+    /// Checks the local root against the root currently stored on-chain.
+    pub fn check_root(&self, chain_root: &Hash) -> Result<(), MerkleTreeError> {
+        if self.mirror.root() != chain_root {
+            return Err(MerkleTreeError::RootDivergence(format!(
+                "Local root {} doesn't match chain root {} after {} events",
+                self.mirror.root(),
+                chain_root,
+                self.applied
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn root(&self) -> &Hash {
+        self.mirror.root()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChangeKind, MerkleTree};
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor"];
+
+    fn log_line(source: &mut MerkleTree, leaf_index: usize) -> String {
+        let _ = source.root();
+        let mut path = vec![];
+        let (mut level_offset, mut level_len, mut current_index) =
+            (0, source.leaf_count, leaf_index);
+        while level_len > 0 {
+            path.push(source.nodes[level_offset + current_index]);
+            level_offset += level_len;
+            level_len /= 2;
+            current_index /= 2;
+        }
+        let event = ChangeLogEvent {
+            kind: ChangeKind::Append,
+            leaf_index: leaf_index as u64,
+            path,
+        };
+        format!(
+            "{}{}",
+            PROGRAM_DATA_PREFIX,
+            STANDARD.encode(event.to_bytes())
+        )
+    }
+
+    #[test]
+    fn test_indexer_from_logs() {
+        let mut source = MerkleTree::new(4);
+        let _ = source.get_root();
+        let mut logs = vec!["Program log: Instruction: Append".to_string()];
+        for (leaf_index, leaf) in SAMPLE.iter().enumerate() {
+            source.update_leaf(leaf_index, leaf).unwrap();
+            logs.push(log_line(&mut source, leaf_index));
+        }
+
+        let mut indexer = TreeIndexer::new(2);
+        let applied = indexer.sync(&mut LogEventSource::new(logs.iter())).unwrap();
+        assert_eq!(applied, SAMPLE.len() as u64);
+        indexer.check_root(source.nodes.last().unwrap()).unwrap();
+        assert!(indexer.check_root(&Hash::default()).is_err());
+
+        // Replaying an old append diverges from the local tree
+        let stale = [logs[1].clone()];
+        assert!(indexer
+            .sync(&mut LogEventSource::new(stale.iter()))
+            .is_err());
+        assert!(parse_log_line("Program data: !!!").unwrap().is_err());
+    }
+}
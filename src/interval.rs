@@ -0,0 +1,307 @@
+use crate::{MerkleTree, MerkleTreeError, LEAF_PREFIX, NODE_PREFIX};
+use solana_program::hash::{hashv, Hash};
+
+/// A node of an `IntervalTree`, committing to the smallest and largest key below it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IntervalNode {
+    pub hash: Hash,
+    pub min: u64,
+    pub max: u64,
+}
+
+impl IntervalNode {
+    /// Hashes a key and its value into a leaf.
+    pub fn leaf<T: AsRef<[u8]>>(key: u64, value: T) -> Self {
+        Self {
+            hash: hashv(&[LEAF_PREFIX, &key.to_le_bytes(), value.as_ref()]),
+            min: key,
+            max: key,
+        }
+    }
+
+    /// Hashes two children, including their key ranges, into their parent.
+    pub fn parent(lnode: &Self, rnode: &Self) -> Self {
+        Self {
+            hash: hashv(&[
+                NODE_PREFIX,
+                &lnode.min.to_le_bytes(),
+                &lnode.max.to_le_bytes(),
+                lnode.hash.as_ref(),
+                &rnode.min.to_le_bytes(),
+                &rnode.max.to_le_bytes(),
+                rnode.hash.as_ref(),
+            ]),
+            min: lnode.min,
+            max: rnode.max,
+        }
+    }
+}
+
+/// Merkle tree over leaves sorted by a `u64` key, where every node commits to the key
+/// range below it. Besides inclusion, it can prove that no key falls within a range.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntervalTree {
+    pub leaf_count: usize,
+    pub nodes: Vec<IntervalNode>,
+}
+
+/// Opening of a single keyed leaf in an `IntervalTree`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntervalOpening {
+    pub leaf_index: usize,
+    pub leaf: IntervalNode,
+    /// Partner nodes from the leaf level up to (but excluding) the root.
+    pub path: Vec<IntervalNode>,
+}
+
+/// Proof that no key lies within a range, made of the openings of the closest keys on
+/// either side. A missing side means the range lies beyond the first or last key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AbsenceProof {
+    pub below: Option<IntervalOpening>,
+    pub above: Option<IntervalOpening>,
+}
+
+/// Result of looking a key up in an `IntervalTree`: the opening of its leaf, or the
+/// openings of its neighbours proving it isn't there.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyProof {
+    Present(IntervalOpening),
+    Absent(AbsenceProof),
+}
+
+impl KeyProof {
+    /// Returns true if the proof shows under `root` that `key` is present, for `Present`,
+    /// or absent, for `Absent`.
+    pub fn verify(&self, root: &Hash, key: u64) -> bool {
+        match self {
+            KeyProof::Present(opening) => opening.leaf.min == key && opening.verify(root),
+            KeyProof::Absent(proof) => proof.verify(root, key, key),
+        }
+    }
+}
+
+impl IntervalTree {
+    /// Builds the tree from `(key, value)` entries whose keys are strictly increasing.
+    pub fn new<T: AsRef<[u8]>>(entries: &[(u64, T)]) -> Result<Self, MerkleTreeError> {
+        if let Some(pair) = entries.windows(2).find(|pair| pair[0].0 >= pair[1].0) {
+            return Err(MerkleTreeError::InvalidKeyOrder(format!(
+                "Key {} is followed by {}",
+                pair[0].0, pair[1].0
+            )));
+        }
+        let leaf_count = entries.len();
+        let mut nodes = Vec::with_capacity(MerkleTree::calculate_max_capacity(leaf_count));
+        nodes.extend(
+            entries
+                .iter()
+                .map(|(key, value)| IntervalNode::leaf(*key, value)),
+        );
+
+        let mut level_offset = 0;
+        let mut level_len = leaf_count;
+        while level_len > 1 {
+            for index in (0..level_len).step_by(2) {
+                let lnode = nodes[level_offset + index];
+                let rnode = nodes[level_offset + (index + 1).min(level_len - 1)];
+                nodes.push(IntervalNode::parent(&lnode, &rnode));
+            }
+            level_offset += level_len;
+            level_len = MerkleTree::calculate_next_level_len(level_len);
+        }
+        Ok(Self { leaf_count, nodes })
+    }
+
+    /// Returns the root node, whose `min` and `max` are the smallest and largest keys.
+    pub fn root(&self) -> Option<&IntervalNode> {
+        self.nodes.last()
+    }
+
+    /// Returns the index of the leaf with the given key.
+    pub fn find(&self, key: u64) -> Option<usize> {
+        self.nodes[..self.leaf_count]
+            .binary_search_by_key(&key, |leaf| leaf.min)
+            .ok()
+    }
+
+    /// Binary searches for the key and returns the opening of its leaf, or the absence
+    /// proof bracketing it if it isn't in the tree.
+    pub fn find_and_prove(&self, key: u64) -> Result<KeyProof, MerkleTreeError> {
+        match self.find(key) {
+            Some(leaf_index) => Ok(KeyProof::Present(self.get_opening(leaf_index)?)),
+            None => Ok(KeyProof::Absent(self.prove_absence(key, key)?)),
+        }
+    }
+
+    /// Returns the opening for the leaf at given index.
+    pub fn get_opening(&self, leaf_index: usize) -> Result<IntervalOpening, MerkleTreeError> {
+        if leaf_index >= self.leaf_count {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Tree has {} leaves but index given was {}",
+                self.leaf_count, leaf_index
+            )));
+        }
+        let mut path = vec![];
+        let mut position = leaf_index;
+        let mut level_offset = 0;
+        let mut level_len = self.leaf_count;
+        while level_len > 1 {
+            let partner = (position ^ 1).min(level_len - 1);
+            path.push(self.nodes[level_offset + partner]);
+            position /= 2;
+            level_offset += level_len;
+            level_len = MerkleTree::calculate_next_level_len(level_len);
+        }
+        Ok(IntervalOpening {
+            leaf_index,
+            leaf: self.nodes[leaf_index],
+            path,
+        })
+    }
+
+    /// Returns the proof that no key lies within `[start, end]`, or an error if one does.
+    pub fn prove_absence(&self, start: u64, end: u64) -> Result<AbsenceProof, MerkleTreeError> {
+        if self.leaf_count == 0 || start > end {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Can't prove absence of [{}, {}] in a tree of {} leaves",
+                start, end, self.leaf_count
+            )));
+        }
+        let leaves = &self.nodes[..self.leaf_count];
+        let first_above = leaves.partition_point(|leaf| leaf.min < start);
+        if first_above < self.leaf_count && leaves[first_above].min <= end {
+            return Err(MerkleTreeError::ProofMismatch(format!(
+                "Key {} lies within [{}, {}]",
+                leaves[first_above].min, start, end
+            )));
+        }
+        Ok(AbsenceProof {
+            below: first_above
+                .checked_sub(1)
+                .map(|leaf_index| self.get_opening(leaf_index))
+                .transpose()?,
+            above: (first_above < self.leaf_count)
+                .then(|| self.get_opening(first_above))
+                .transpose()?,
+        })
+    }
+}
+
+impl IntervalOpening {
+    /// Hashes the leaf up the path and returns the resulting root node.
+    pub fn compute_root(&self) -> IntervalNode {
+        let mut current_index = self.leaf_index;
+        let mut computed_root = self.leaf;
+        for partner in &self.path {
+            computed_root = if current_index % 2 == 0 {
+                IntervalNode::parent(&computed_root, partner)
+            } else {
+                IntervalNode::parent(partner, &computed_root)
+            };
+            current_index /= 2;
+        }
+        computed_root
+    }
+
+    /// Returns true if the opening computes to the given root hash.
+    pub fn verify(&self, root: &Hash) -> bool {
+        self.compute_root().hash == *root
+    }
+}
+
+impl AbsenceProof {
+    /// Returns true if the proof shows that no key under `root` lies within `[start, end]`.
+    pub fn verify(&self, root: &Hash, start: u64, end: u64) -> bool {
+        if start > end {
+            return false;
+        }
+        let below = self
+            .below
+            .as_ref()
+            .map(|opening| (opening, opening.compute_root()));
+        let above = self
+            .above
+            .as_ref()
+            .map(|opening| (opening, opening.compute_root()));
+        match (below, above) {
+            (Some((below, below_root)), Some((above, above_root))) => {
+                below_root.hash == *root
+                    && above_root.hash == *root
+                    && below.leaf_index + 1 == above.leaf_index
+                    && below.leaf.max < start
+                    && above.leaf.min > end
+            }
+            // The root's key range shows there's nothing past the one neighbour
+            (Some((below, below_root)), None) => {
+                below_root.hash == *root
+                    && below.leaf.max == below_root.max
+                    && below.leaf.max < start
+            }
+            (None, Some((above, above_root))) => {
+                above_root.hash == *root && above.leaf.min == above_root.min && above.leaf.min > end
+            }
+            (None, None) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRIES: &[(u64, &[u8])] = &[
+        (10, b"lorem"),
+        (20, b"ipsum"),
+        (30, b"dolor"),
+        (40, b"sit"),
+        (50, b"amet"),
+    ];
+
+    #[test]
+    fn test_interval_tree() {
+        let interval_tree = IntervalTree::new(ENTRIES).unwrap();
+        let root = interval_tree.root().unwrap();
+        assert_eq!((root.min, root.max), (10, 50));
+
+        let leaf_index = interval_tree.find(40).unwrap();
+        let opening = interval_tree.get_opening(leaf_index).unwrap();
+        assert!(opening.verify(&root.hash));
+        assert_eq!(opening.leaf, IntervalNode::leaf(40, b"sit"));
+
+        let proof = interval_tree.prove_absence(21, 29).unwrap();
+        assert!(proof.verify(&root.hash, 21, 29));
+        assert!(!proof.verify(&root.hash, 21, 30));
+        let proof = interval_tree.prove_absence(0, 9).unwrap();
+        assert!(proof.below.is_none());
+        assert!(proof.verify(&root.hash, 0, 9));
+        let proof = interval_tree.prove_absence(51, u64::MAX).unwrap();
+        assert!(proof.verify(&root.hash, 51, u64::MAX));
+
+        assert!(interval_tree.prove_absence(25, 35).is_err());
+        // Openings of non-adjacent neighbours don't prove anything
+        let gap = AbsenceProof {
+            below: Some(interval_tree.get_opening(0).unwrap()),
+            above: Some(interval_tree.get_opening(2).unwrap()),
+        };
+        assert!(!gap.verify(&root.hash, 11, 19));
+
+        assert!(IntervalTree::new(&[(2u64, b"a"), (1, b"b")]).is_err());
+    }
+
+    #[test]
+    fn test_find_and_prove() {
+        let interval_tree = IntervalTree::new(ENTRIES).unwrap();
+        let root = interval_tree.root().unwrap().hash;
+
+        let proof = interval_tree.find_and_prove(30).unwrap();
+        assert!(matches!(proof, KeyProof::Present(ref opening) if opening.leaf_index == 2));
+        assert!(proof.verify(&root, 30));
+        assert!(!proof.verify(&root, 31));
+
+        let proof = interval_tree.find_and_prove(35).unwrap();
+        assert!(matches!(proof, KeyProof::Absent(_)));
+        assert!(proof.verify(&root, 35));
+        assert!(!proof.verify(&root, 40));
+        assert!(interval_tree.find_and_prove(60).unwrap().verify(&root, 60));
+    }
+}
@@ -0,0 +1,149 @@
+use crate::MerkleTree;
+use std::{ops::Range, sync::Arc};
+
+/// Where each level of a built tree lives in `nodes`: the leaves first, then each level
+/// above them, with the root last. Computed once from the leaf count.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeLayout {
+    levels: Vec<Range<usize>>,
+}
+
+impl TreeLayout {
+    pub fn new(leaf_count: usize) -> Self {
+        let mut levels = vec![];
+        let (mut level_offset, mut level_len) = (0, leaf_count);
+        while level_len > 0 {
+            levels.push(level_offset..level_offset + level_len);
+            level_offset += level_len;
+            level_len = MerkleTree::calculate_next_level_len(level_len);
+        }
+        Self { levels }
+    }
+
+    /// Ranges of `nodes` holding each level, leaves first.
+    pub fn levels(&self) -> &[Range<usize>] {
+        &self.levels
+    }
+
+    /// Number of leaves the layout was computed for.
+    pub fn leaf_count(&self) -> usize {
+        self.levels.first().map_or(0, |leaves| leaves.len())
+    }
+
+    /// Number of levels including the leaves and the root, 0 for an empty tree.
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Number of nodes a built tree holds.
+    pub fn node_count(&self) -> usize {
+        self.levels.last().map_or(0, |root| root.end)
+    }
+
+    /// Range of `nodes` holding the level.
+    pub fn level_range(&self, level: usize) -> Option<Range<usize>> {
+        self.levels.get(level).cloned()
+    }
+
+    /// Index in `nodes` of the first node of the level.
+    pub fn offset_of_level(&self, level: usize) -> Option<usize> {
+        self.levels.get(level).map(|range| range.start)
+    }
+
+    /// Number of nodes on the level.
+    pub fn len_of_level(&self, level: usize) -> Option<usize> {
+        self.levels.get(level).map(|range| range.len())
+    }
+
+    /// Index in `nodes` of the node at `index` on `level`.
+    pub fn node_index(&self, level: usize, index: usize) -> Option<usize> {
+        let range = self.levels.get(level)?;
+        (index < range.len()).then_some(range.start + index)
+    }
+
+    /// Index on the level above of the node's parent, or `None` for the root.
+    pub fn parent(&self, level: usize, index: usize) -> Option<usize> {
+        self.node_index(level + 1, index / 2)?;
+        self.node_index(level, index).map(|_| index / 2)
+    }
+
+    /// Index on the same level of the node it's hashed with. The last node of an odd level
+    /// is paired with itself. `None` for the root.
+    pub fn sibling(&self, level: usize, index: usize) -> Option<usize> {
+        self.parent(level, index)?;
+        let sibling = index ^ 1;
+        Some(if sibling < self.levels[level].len() {
+            sibling
+        } else {
+            index
+        })
+    }
+}
+
+impl MerkleTree {
+    /// Returns the layout of the tree's nodes once built. It's computed on first use and
+    /// cached until the leaf count changes.
+    pub fn layout(&self) -> Arc<TreeLayout> {
+        let mut cached = self.layout.lock().unwrap();
+        match cached.as_ref() {
+            Some(layout) if layout.leaf_count() == self.leaf_count => Arc::clone(layout),
+            _ => {
+                let layout = Arc::new(TreeLayout::new(self.leaf_count));
+                *cached = Some(Arc::clone(&layout));
+                layout
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_tree_layout() {
+        let merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        let layout = merkle_tree.layout();
+        assert_eq!(layout.level_count(), 4);
+        assert_eq!(layout.node_count(), merkle_tree.nodes.len());
+        assert_eq!(layout.offset_of_level(1), Some(5));
+        assert_eq!(layout.len_of_level(1), Some(3));
+        assert_eq!(layout.level_range(3), Some(10..11));
+        assert_eq!(layout.len_of_level(4), None);
+
+        assert_eq!(layout.parent(0, 4), Some(2));
+        assert_eq!(layout.sibling(0, 4), Some(4));
+        assert_eq!(layout.sibling(1, 1), Some(0));
+        assert_eq!(layout.parent(3, 0), None);
+        assert_eq!(layout.sibling(3, 0), None);
+        assert_eq!(layout.parent(0, 5), None);
+
+        // Every node hashes with its sibling into its parent
+        let opening = merkle_tree.get_opening(4).unwrap();
+        let mut index = 4;
+        for (level, partner) in opening.path.iter().enumerate() {
+            let sibling = layout.sibling(level, index).unwrap();
+            assert_eq!(
+                merkle_tree.nodes[layout.node_index(level, sibling).unwrap()],
+                *partner
+            );
+            index = layout.parent(level, index).unwrap();
+        }
+        assert_eq!(TreeLayout::new(0).node_count(), 0);
+    }
+
+    #[test]
+    fn test_cached_layout() {
+        let mut merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        let layout = merkle_tree.layout();
+        assert!(Arc::ptr_eq(&layout, &merkle_tree.layout()));
+        assert!(Arc::ptr_eq(&layout, &merkle_tree.clone().layout()));
+
+        merkle_tree.leaf_count = 3;
+        let resized = merkle_tree.layout();
+        assert!(!Arc::ptr_eq(&layout, &resized));
+        assert_eq!(*resized, TreeLayout::new(3));
+    }
+}
@@ -1,2 +1,147 @@
+#[macro_use]
 mod merkle;
+mod acceleration;
+mod account_proof;
+mod audit;
+mod batch;
+mod bloom;
+mod cache;
+mod capacity;
+mod chain;
+mod changelog;
+mod checkpoint;
+mod chunked;
+mod collection;
+mod composed;
+mod concurrent;
+mod constant_time;
+mod cost;
+mod dedupe;
+#[cfg(feature = "digest")]
+mod digest_hasher;
+mod distributor;
+mod domain;
+mod dot;
+mod dual;
+mod empty;
+mod encoder;
+mod epochs;
+mod equality;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod hash_chain;
+mod hasher;
+mod history;
+mod indexer;
+mod interval;
+mod layout;
+mod manifest;
+mod merge;
+mod metrics;
+mod migrate;
+#[cfg(all(feature = "mmap", not(target_arch = "wasm32")))]
+mod mmap;
+mod opening;
+mod opening_ref;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod partial;
+mod pretty;
+mod progress;
+mod proof;
+#[cfg(feature = "proto")]
+pub mod proto;
+mod raw;
+mod reconcile;
+mod rollback;
+mod root;
+mod rs_compat;
+#[cfg(feature = "sanity-check")]
+mod sanity;
+mod selection;
+#[cfg(feature = "zeroize")]
+mod sensitive;
+mod shard;
+mod shared;
+mod snapshot;
+#[cfg(feature = "solana-merkle-tree")]
+mod solana;
+#[cfg(not(target_arch = "wasm32"))]
+mod spill;
+mod state;
+mod stream;
+#[cfg(feature = "tokio")]
+mod stream_ingest;
+mod subtree;
+pub mod test_vectors;
+mod tombstone;
+mod versioned;
+mod view;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod weighted;
+mod window;
+pub use acceleration::*;
+pub use account_proof::*;
+pub use audit::*;
+pub use batch::*;
+pub use capacity::*;
+pub use chain::*;
+pub use changelog::*;
+pub use checkpoint::*;
+pub use composed::*;
+pub use concurrent::*;
+pub use constant_time::*;
+pub use cost::*;
+pub use dedupe::*;
+#[cfg(feature = "digest")]
+pub use digest_hasher::*;
+pub use distributor::*;
+pub use domain::*;
+pub use dual::*;
+pub use empty::*;
+pub use encoder::*;
+pub use epochs::*;
+pub use hash_chain::*;
+pub use hasher::*;
+pub use history::*;
+pub use indexer::*;
+pub use interval::*;
+pub use layout::*;
+pub use manifest::*;
+pub use merge::*;
 pub use merkle::*;
+pub use metrics::*;
+pub use migrate::*;
+#[cfg(all(feature = "mmap", not(target_arch = "wasm32")))]
+pub use mmap::*;
+pub use opening::*;
+pub use opening_ref::*;
+#[cfg(feature = "rayon")]
+pub use parallel::*;
+pub use partial::*;
+pub use pretty::*;
+pub use progress::*;
+pub use proof::*;
+pub use raw::*;
+pub use reconcile::*;
+pub use root::*;
+pub use rs_compat::*;
+pub use selection::*;
+pub use shard::*;
+pub use shared::*;
+pub use snapshot::*;
+#[cfg(feature = "solana-merkle-tree")]
+pub use solana::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use spill::*;
+pub use state::*;
+pub use stream::*;
+pub use subtree::*;
+pub use tombstone::*;
+pub use versioned::*;
+pub use view::*;
+#[cfg(feature = "wasm")]
+pub use wasm::*;
+pub use weighted::*;
+pub use window::*;
@@ -0,0 +1,123 @@
+use crate::{HashDomain, MerkleTree, MerkleTreeError};
+use std::io::Write;
+
+/// Output format of `MerkleTree::export_manifest`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ManifestFormat {
+    /// A single object with no whitespace and keys in a fixed order:
+    /// `{"root","hash_domain","leaf_count","populated","leaves":[{"index","leaf_hash","proof"}]}`.
+    #[default]
+    Json,
+    /// `#`-prefixed lines with the root and tree parameters, then `index,leaf_hash,proof` rows
+    /// with the proof nodes separated by `;`.
+    Csv,
+}
+
+impl MerkleTree {
+    /// Writes the root, tree parameters and the hash and proof of every populated leaf,
+    /// with all hashes in base58. Fails if the tree isn't built or has pending updates.
+    pub fn export_manifest<W: Write>(
+        &self,
+        mut writer: W,
+        format: ManifestFormat,
+    ) -> Result<(), MerkleTreeError> {
+        let root = self.try_root()?;
+        let hash_domain = match (&self.hasher, self.hash_domain) {
+            (Some(_), _) => "custom",
+            (None, HashDomain::Prefix) => "prefix",
+            (None, HashDomain::Tagged) => "tagged",
+        };
+        match format {
+            ManifestFormat::Json => write!(
+                writer,
+                r#"{{"root":"{}","hash_domain":"{}","leaf_count":{},"populated":{},"leaves":["#,
+                root, hash_domain, self.leaf_count, self.current_leaf_index
+            )?,
+            ManifestFormat::Csv => write!(
+                writer,
+                "# root: {}\n# hash_domain: {}\n# leaf_count: {}\n# populated: {}\nindex,leaf_hash,proof\n",
+                root, hash_domain, self.leaf_count, self.current_leaf_index
+            )?,
+        }
+
+        for leaf_index in 0..self.current_leaf_index {
+            let opening = self.get_opening(leaf_index)?;
+            let proof: Vec<String> = opening.path.iter().map(|node| node.to_string()).collect();
+            let leaf_hash = self.nodes[leaf_index];
+            match format {
+                ManifestFormat::Json => write!(
+                    writer,
+                    r#"{}{{"index":{},"leaf_hash":"{}","proof":[{}]}}"#,
+                    if leaf_index > 0 { "," } else { "" },
+                    leaf_index,
+                    leaf_hash,
+                    proof
+                        .iter()
+                        .map(|node| format!("\"{}\"", node))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                )?,
+                ManifestFormat::Csv => {
+                    writeln!(writer, "{},{},{}", leaf_index, leaf_hash, proof.join(";"))?
+                }
+            }
+        }
+
+        if format == ManifestFormat::Json {
+            writeln!(writer, "]}}")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{root::parse_hash, Opening};
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_export_manifest() {
+        let mut merkle_tree = MerkleTree::new(8);
+        assert!(merkle_tree
+            .export_manifest(vec![], ManifestFormat::Json)
+            .is_err());
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let root = *merkle_tree.get_root().unwrap();
+
+        let mut csv = vec![];
+        merkle_tree
+            .export_manifest(&mut csv, ManifestFormat::Csv)
+            .unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with(&format!("# root: {}\n", root)));
+        let rows: Vec<&str> = csv.lines().filter(|line| !line.starts_with('#')).collect();
+        assert_eq!(rows.len(), SAMPLE.len() + 1);
+        for (leaf_index, row) in rows[1..].iter().enumerate() {
+            let columns: Vec<&str> = row.split(',').collect();
+            assert_eq!(columns[0], leaf_index.to_string());
+            let opening = Opening {
+                leaf_index,
+                root,
+                path: columns[2]
+                    .split(';')
+                    .map(|node| parse_hash(node).unwrap())
+                    .collect(),
+            };
+            assert!(opening.verify(&parse_hash(columns[1]).unwrap()));
+        }
+
+        let mut json = vec![];
+        merkle_tree
+            .export_manifest(&mut json, ManifestFormat::Json)
+            .unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.starts_with(&format!(
+            r#"{{"root":"{}","hash_domain":"prefix","leaf_count":8,"populated":5,"leaves":[{{"index":0,"#,
+            root
+        )));
+        assert_eq!(json.matches("leaf_hash").count(), SAMPLE.len());
+    }
+}
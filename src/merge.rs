@@ -0,0 +1,174 @@
+use crate::{hasher::same_hasher, MerkleTree, MerkleTreeError, DEFAULT_LEAF};
+use solana_program::hash::Hash;
+
+/// Which leaf `MerkleTree::merge` keeps where the two trees differ.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergePolicy<'a> {
+    /// Keeps this tree's leaf.
+    PreferSelf,
+    /// Keeps the other tree's leaf.
+    PreferOther,
+    /// Keeps whichever leaf has been set, i.e. isn't the default leaf, preferring this
+    /// tree's if both have.
+    PreferNonDefault,
+    /// Keeps the leaf with the higher version, given a version per leaf of each tree,
+    /// preferring this tree's on ties.
+    PreferNewer {
+        versions: &'a [u64],
+        other_versions: &'a [u64],
+    },
+}
+
+/// Outcome of a merge.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    /// Indices where both trees had set different leaves.
+    pub conflicts: Vec<usize>,
+    /// Indices where the merged tree took the other tree's leaf.
+    pub taken_from_other: Vec<usize>,
+}
+
+impl MerkleTree {
+    /// Combines this tree with another over the same index space, picking each differing
+    /// leaf by the policy, and returns the built merged tree with a report of the conflicts.
+    /// Both trees must hash the same way, with the same domain or the same custom hasher.
+    pub fn merge(
+        &self,
+        other: &MerkleTree,
+        policy: MergePolicy<'_>,
+    ) -> Result<(MerkleTree, MergeReport), MerkleTreeError> {
+        if self.leaf_count != other.leaf_count
+            || self.hash_domain != other.hash_domain
+            || !same_hasher(self.hasher.as_ref(), other.hasher.as_ref())
+        {
+            return Err(MerkleTreeError::ProofMismatch(format!(
+                "Can't merge a tree of {} {:?} leaves with one of {} {:?} leaves, or trees \
+                 with different hashers",
+                self.leaf_count, self.hash_domain, other.leaf_count, other.hash_domain
+            )));
+        }
+        if let MergePolicy::PreferNewer {
+            versions,
+            other_versions,
+        } = policy
+        {
+            if versions.len() != self.leaf_count || other_versions.len() != self.leaf_count {
+                return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                    "Got {} and {} versions for {} leaves",
+                    versions.len(),
+                    other_versions.len(),
+                    self.leaf_count
+                )));
+            }
+        }
+
+        let default_leaf = Hash::new_from_array(DEFAULT_LEAF);
+        let mut report = MergeReport::default();
+        let mut nodes = self.nodes[..self.leaf_count].to_vec();
+        for (leaf_index, (leaf, other_leaf)) in nodes
+            .iter_mut()
+            .zip(&other.nodes[..other.leaf_count])
+            .enumerate()
+        {
+            if leaf == other_leaf {
+                continue;
+            }
+            if *leaf != default_leaf && *other_leaf != default_leaf {
+                report.conflicts.push(leaf_index);
+            }
+            let take_other = match policy {
+                MergePolicy::PreferSelf => false,
+                MergePolicy::PreferOther => true,
+                MergePolicy::PreferNonDefault => *leaf == default_leaf,
+                MergePolicy::PreferNewer {
+                    versions,
+                    other_versions,
+                } => other_versions[leaf_index] > versions[leaf_index],
+            };
+            if take_other {
+                *leaf = *other_leaf;
+                report.taken_from_other.push(leaf_index);
+            }
+        }
+
+        let mut merged = MerkleTree {
+            leaf_count: self.leaf_count,
+            nodes,
+            current_leaf_index: self.current_leaf_index.max(other.current_leaf_index),
+            hash_domain: self.hash_domain,
+            hasher: self.hasher.clone(),
+            ..Default::default()
+        };
+        let _ = merged.get_root();
+        Ok((merged, report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeccakHasher, MerkleHasher};
+    use std::sync::Arc;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_merge() {
+        let mut left = MerkleTree::new(4);
+        left.insert_batch(&SAMPLE[..2]).unwrap();
+        let mut right = MerkleTree::new(4);
+        right
+            .insert_batch(&[SAMPLE[0], SAMPLE[2], SAMPLE[3]])
+            .unwrap();
+
+        let (mut merged, report) = left.merge(&right, MergePolicy::PreferNonDefault).unwrap();
+        assert_eq!(report.conflicts, vec![1]);
+        assert_eq!(report.taken_from_other, vec![2]);
+        let mut expected = MerkleTree::new(4);
+        expected
+            .insert_batch(&[SAMPLE[0], SAMPLE[1], SAMPLE[3]])
+            .unwrap();
+        assert_eq!(merged.get_root(), expected.get_root());
+        assert_eq!(merged.current_leaf_index, 3);
+
+        let versions = [0, 1, 0, 0];
+        let (merged, report) = left
+            .merge(
+                &right,
+                MergePolicy::PreferNewer {
+                    versions: &versions,
+                    other_versions: &[0, 2, 0, 0],
+                },
+            )
+            .unwrap();
+        assert_eq!(report.taken_from_other, vec![1]);
+        assert_eq!(
+            merged.nodes[..4],
+            [right.nodes[0], right.nodes[1], left.nodes[2], left.nodes[3]]
+        );
+        assert!(left
+            .merge(&MerkleTree::new(5), MergePolicy::PreferSelf)
+            .is_err());
+    }
+
+    #[test]
+    fn test_merge_with_hasher() {
+        let hasher: Arc<dyn MerkleHasher> = Arc::new(KeccakHasher);
+        let keccak_tree = |leaves: &[&[u8]]| {
+            let mut merkle_tree = MerkleTree::new(4);
+            merkle_tree.set_hasher(hasher.clone());
+            merkle_tree.insert_batch(leaves).unwrap();
+            merkle_tree
+        };
+        let left = keccak_tree(&SAMPLE[..2]);
+        let right = keccak_tree(&[SAMPLE[0], SAMPLE[1], SAMPLE[2]]);
+
+        let (mut merged, _) = left.merge(&right, MergePolicy::PreferNonDefault).unwrap();
+        assert_eq!(merged.get_root(), right.clone().get_root());
+        let mut default_hashed = MerkleTree::new(4);
+        default_hashed.insert_batch(&SAMPLE[..3]).unwrap();
+        assert!(left
+            .merge(&default_hashed, MergePolicy::PreferSelf)
+            .is_err());
+    }
+}
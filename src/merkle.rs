@@ -1,5 +1,15 @@
 // use rayon::prelude::*;
+use crate::{
+    bloom::BloomFilter, cache::ProofCache, chain::RootChain, dedupe::DuplicateIndex,
+    hasher::TreeHasher, metrics::MetricsHandle, AuditTrace, HashDomain, MerkleHasher,
+    MerkleTreeView, Opening, ProgressFn, RecentRoot, TreeLayout,
+};
 use solana_program::hash::{hashv, Hash};
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::Range,
+    sync::{Arc, Mutex},
+};
 use thiserror::Error;
 pub const DEFAULT_LEAF: [u8; 32] = [
     110, 52, 11, 156, 255, 179, 122, 152, 156, 165, 68, 230, 187, 120, 10, 44, 120, 144, 29, 63,
@@ -9,6 +19,9 @@ pub const DEFAULT_LEAF: [u8; 32] = [
 pub const LEAF_PREFIX: &[u8] = &[0];
 pub const NODE_PREFIX: &[u8] = &[1];
 
+/// Maximum number of distinct child pairs remembered when `memoize_subtrees` is set.
+pub const SUBTREE_MEMO_LIMIT: usize = 1024;
+
 // hash_leaf and hash_node prepend a prefix 0x0 and 0x1 to prevent second pre-image attacks
 // Refer: https://en.wikipedia.org/wiki/Merkle_tree#Second_preimage_attack
 #[macro_export]
@@ -24,24 +37,57 @@ macro_rules! hash_node {
     };
 }
 
-#[derive(Default, Debug)]
+/// Function form of `hash_leaf!` that doesn't need `hashv` and `LEAF_PREFIX` in scope.
+/// Use `MerkleTree::hash_leaf` to hash the way a tree with another domain or hasher does.
+pub fn hash_leaf(leaf: &[u8]) -> Hash {
+    hash_leaf!(leaf)
+}
+
+/// Function form of `hash_node!`, see `hash_leaf`.
+pub fn hash_node(lnode: &Hash, rnode: &Hash) -> Hash {
+    hash_node!(lnode, rnode)
+}
+
+#[derive(Default)]
 pub struct MerkleTree {
     pub leaf_count: usize,
     pub nodes: Vec<Hash>,
     pub current_leaf_index: usize,
+    /// Defers rehashing of interior nodes after mutations until `root()` or `flush()`.
+    pub lazy: bool,
+    /// Reuses the hash of identical child pairs while building, which pays off when
+    /// large regions of the tree hold the same leaf (e.g. unfilled default leaves).
+    pub memoize_subtrees: bool,
+    /// Domain separation used for leaf and node hashes. Set before inserting any leaves.
+    pub hash_domain: HashDomain,
+    pub(crate) dirty_leaves: Vec<usize>,
+    pub(crate) root_history: VecDeque<RecentRoot>,
+    pub(crate) root_history_capacity: usize,
+    pub(crate) root_version: u64,
+    pub(crate) proof_cache: Mutex<ProofCache>,
+    pub(crate) layout: Mutex<Option<Arc<TreeLayout>>>,
+    pub(crate) undo_log: VecDeque<Hash>,
+    pub(crate) undo_capacity: usize,
+    pub(crate) bloom: Option<BloomFilter>,
+    pub(crate) audit_trace: Option<AuditTrace>,
+    pub(crate) metrics: Option<MetricsHandle>,
+    pub(crate) root_chain: Option<RootChain>,
+    pub(crate) duplicates: Option<DuplicateIndex>,
+    pub(crate) hasher: Option<Arc<dyn MerkleHasher>>,
+    pub(crate) progress: Option<Arc<ProgressFn>>,
 }
 
 impl MerkleTree {
     /// Calculates the height of a tree with n leaves (n = 2^h).
-    pub fn calculate_height(leaf_count: usize) -> usize {
+    pub const fn calculate_height(leaf_count: usize) -> usize {
         if leaf_count > 0 {
-            fast_math::log2(leaf_count as f32).ceil() as usize
+            (usize::BITS - (leaf_count - 1).leading_zeros()) as usize
         } else {
             0
         }
     }
     /// Returns the length of array for the next level of the tree.
-    pub fn calculate_next_level_len(current_level_len: usize) -> usize {
+    pub const fn calculate_next_level_len(current_level_len: usize) -> usize {
         if current_level_len > 1 {
             if current_level_len % 2 == 0 {
                 current_level_len / 2
@@ -53,23 +99,26 @@ impl MerkleTree {
         }
     }
     /// Returns the vector capacity required for a tree of given leaf count.
-    pub fn calculate_max_capacity(leaf_count: usize) -> usize {
-        if leaf_count > 0 {
-            let mut level_leaf_count = leaf_count as usize;
-            let mut node_count = level_leaf_count;
-            while level_leaf_count > 1 {
-                level_leaf_count = if level_leaf_count % 2 == 0 {
-                    level_leaf_count / 2
-                } else {
-                    (level_leaf_count + 1) / 2
-                };
-                node_count += level_leaf_count;
-            }
-            node_count
-        } else {
-            0
+    /// Panics if it overflows a usize, see `checked_max_capacity`.
+    pub const fn calculate_max_capacity(leaf_count: usize) -> usize {
+        match Self::checked_max_capacity(leaf_count) {
+            Some(node_count) => node_count,
+            None => panic!("Node count of the tree overflows usize"),
         }
     }
+    /// Like `calculate_max_capacity`, returning None if the node count overflows a usize.
+    pub const fn checked_max_capacity(leaf_count: usize) -> Option<usize> {
+        let mut level_leaf_count = leaf_count;
+        let mut node_count = level_leaf_count;
+        while level_leaf_count > 1 {
+            level_leaf_count = level_leaf_count / 2 + level_leaf_count % 2;
+            node_count = match node_count.checked_add(level_leaf_count) {
+                Some(node_count) => node_count,
+                None => return None,
+            };
+        }
+        Some(node_count)
+    }
     /// Construct a new instance of the Merkle Tree.
     pub fn new(leaf_count: usize) -> Self {
         let max_capacity = MerkleTree::calculate_max_capacity(leaf_count);
@@ -81,148 +130,427 @@ impl MerkleTree {
         Self {
             leaf_count,
             nodes,
-            current_leaf_index: 0,
+            ..Default::default()
         }
     }
 
+    /// Construct a tree from the given leaves and compute its root.
+    /// Leaf hashes are written straight into the reserved capacity, skipping the default fill.
+    pub fn from_leaves<T: AsRef<[u8]>>(leaves: &[T]) -> Self {
+        let leaf_count = leaves.len();
+        let mut nodes = Vec::with_capacity(MerkleTree::calculate_max_capacity(leaf_count));
+        nodes.extend(leaves.iter().map(|leaf| hash_leaf!(leaf)));
+
+        let mut merkle_tree = Self {
+            leaf_count,
+            nodes,
+            current_leaf_index: leaf_count,
+            ..Default::default()
+        };
+        let _ = merkle_tree.get_root();
+        merkle_tree
+    }
+
+    /// Construct a tree from leaves that are already hashed and compute its root.
+    pub(crate) fn from_leaf_hashes(nodes: Vec<Hash>) -> Self {
+        let leaf_count = nodes.len();
+        let mut merkle_tree = Self {
+            leaf_count,
+            nodes,
+            current_leaf_index: leaf_count,
+            ..Default::default()
+        };
+        let _ = merkle_tree.get_root();
+        merkle_tree
+    }
+
     /// Inserts a single leaf into the tree.
-    pub fn insert<T: AsRef<[u8]>>(self: &mut Self, leaf: T) -> Result<&mut Self, MerkleTreeError> {
+    pub fn insert<T: AsRef<[u8]>>(&mut self, leaf: T) -> Result<&mut Self, MerkleTreeError> {
+        self.insert_indexed(leaf)?;
+        Ok(self)
+    }
+
+    /// Inserts a single leaf into the tree and returns the index it was assigned.
+    pub fn insert_indexed<T: AsRef<[u8]>>(&mut self, leaf: T) -> Result<usize, MerkleTreeError> {
+        let leaf_node = self.hash_leaf(leaf.as_ref());
+        self.insert_leaf_hash(leaf_node)
+    }
+
+    pub(crate) fn ensure_not_full(&self) -> Result<(), MerkleTreeError> {
         if self.current_leaf_index == self.leaf_count {
             return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
                 "New leaf exceeds size of tree: {}",
                 self.leaf_count,
             )));
         }
+        Ok(())
+    }
 
-        let leaf_node = hash_leaf!(leaf);
-
-        if self.current_leaf_index == 0 {
-            self.nodes[0] = leaf_node;
-        } else {
-            self.nodes[self.current_leaf_index] = leaf_node;
+    /// Inserts a leaf that's already hashed and returns the index it was assigned, or the
+    /// index of its first occurrence if the duplicate policy dedupes it.
+    pub(crate) fn insert_leaf_hash(&mut self, leaf_node: Hash) -> Result<usize, MerkleTreeError> {
+        if let Some(leaf_index) = self.check_duplicate(&leaf_node)? {
+            return Ok(leaf_index);
         }
+        self.ensure_not_full()?;
+        let leaf_index = self.current_leaf_index;
+
+        self.record_undo(leaf_index..leaf_index + 1);
+        let previous = std::mem::replace(&mut self.nodes[leaf_index], leaf_node);
         self.current_leaf_index += 1;
-        Ok(self)
+        self.record_bloom(leaf_index..leaf_index + 1);
+        self.record_duplicates(&[leaf_index], &[previous]);
+        if let Some(metrics) = self.metrics() {
+            metrics.leaves_inserted(1);
+        }
+        self.mark_dirty(leaf_index..leaf_index + 1);
+        Ok(leaf_index)
+    }
+
+    /// Inserts a batch of leaves and returns the range of indices they were assigned.
+    /// Nothing is inserted if the batch doesn't fit in the remaining space or the duplicate
+    /// policy rejects one of its leaves. Leaves the policy dedupes are skipped and aren't
+    /// part of the returned range.
+    pub fn insert_batch<T: AsRef<[u8]>>(
+        &mut self,
+        leaves: &[T],
+    ) -> Result<Range<usize>, MerkleTreeError> {
+        let hasher = self.tree_hasher();
+        let leaf_nodes = leaves
+            .iter()
+            .map(|leaf| hasher.hash_leaf(leaf.as_ref()))
+            .collect();
+        let leaf_nodes = self.filter_duplicates(leaf_nodes)?;
+        let start = self.current_leaf_index;
+        if leaf_nodes.len() > self.leaf_count - start {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Batch of {} leaves exceeds size of tree: {}",
+                leaf_nodes.len(),
+                self.leaf_count,
+            )));
+        }
+        let end = start + leaf_nodes.len();
+
+        self.record_undo(start..end);
+        let previous: Vec<Hash> = self.nodes[start..end].to_vec();
+        self.nodes[start..end].copy_from_slice(&leaf_nodes);
+        self.current_leaf_index = end;
+        self.record_bloom(start..end);
+        if self.duplicates.is_some() {
+            self.record_duplicates(&(start..end).collect::<Vec<_>>(), &previous);
+        }
+        if let Some(metrics) = self.metrics() {
+            metrics.leaves_inserted(leaf_nodes.len());
+        }
+        self.mark_dirty(start..end);
+        Ok(start..end)
     }
 
     /// Returns the leaf at given index.
-    pub fn get_value(self: &Self, leaf_index: usize) -> Option<&Hash> {
+    pub fn get_value(&self, leaf_index: usize) -> Option<&Hash> {
         self.nodes[0..self.leaf_count].get(leaf_index)
     }
 
-    /// Returns the Merkle Root of the tree.
-    pub fn get_root(self: &mut Self) -> Option<&Hash> {
-        let height = Self::calculate_height(self.leaf_count);
-        let mut current_level: usize = height;
-
-        let mut prev_level_len: usize = 0;
-        let mut current_level_len: usize = self.leaf_count;
+    /// Returns the leaves in the given range.
+    pub fn get_values(&self, range: Range<usize>) -> Option<&[Hash]> {
+        self.nodes[0..self.leaf_count].get(range)
+    }
 
-        // This cache exists to avoid taking multiple mutable borrows on self.nodes
-        let mut level_cache = Vec::with_capacity(current_level_len);
+    /// Returns an iterator over all the leaves in pages of at most `page_size` leaves.
+    pub fn leaf_pages(&self, page_size: usize) -> std::slice::Chunks<'_, Hash> {
+        self.nodes[0..self.leaf_count].chunks(page_size)
+    }
 
-        let mut pairs = self.nodes.chunks(2);
+    /// Returns the Merkle Root of the tree.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(leaf_count = self.leaf_count))
+    )]
+    pub fn get_root(&mut self) -> Option<&Hash> {
+        // Only read the clock when someone is listening, it isn't available on every target
+        let started =
+            (self.metrics.is_some() || self.progress.is_some()).then(std::time::Instant::now);
+        self.build(started)
+    }
 
-        while current_level > 0 {
-            let pair = pairs.next();
-            match pair {
-                Some([lnode, rnode]) => {
-                    let inter_node = hash_node!(lnode, rnode);
-                    level_cache.push(inter_node);
-                }
-                Some([lnode]) => {
-                    let inter_node = hash_node!(lnode, lnode);
-                    level_cache.push(inter_node);
-                }
-                _ => {
-                    self.nodes.append(&mut level_cache);
-                    current_level -= 1;
-
-                    prev_level_len += current_level_len;
-                    current_level_len = Self::calculate_next_level_len(current_level_len);
-                    level_cache = Vec::with_capacity(current_level_len);
-                    pairs = self.nodes[(prev_level_len)..(prev_level_len + current_level_len)]
-                        .chunks(2);
+    /// Computes the interior levels and root, timing the build from `started`.
+    pub(crate) fn build(&mut self, started: Option<std::time::Instant>) -> Option<&Hash> {
+        // Drop interior levels from a previous build so they aren't hashed as leaves
+        self.nodes.truncate(self.leaf_count);
+        self.dirty_leaves.clear();
+        let layout = self.layout();
+
+        let domain = self.hash_domain;
+        let hasher = self.tree_hasher();
+        let memoize = self.memoize_subtrees;
+        let mut memo = HashMap::new();
+
+        let mut audit_trace = self.audit_trace.take().map(|_| AuditTrace {
+            leaves: self.nodes.clone(),
+            ..AuditTrace::new(domain)
+        });
+
+        for level in 1..layout.level_count() {
+            // This cache exists to avoid taking multiple mutable borrows on self.nodes
+            let mut level_cache = Vec::with_capacity(layout.len_of_level(level).unwrap_or(0));
+            let children = layout.level_range(level - 1).unwrap_or_default();
+            for pair in self.nodes[children].chunks(2) {
+                // The last node of an odd level is paired with itself
+                let lnode = &pair[0];
+                let rnode = pair.last().unwrap_or(lnode);
+                let inter_node = if memoize {
+                    Self::memoized_node(&hasher, &mut memo, lnode, rnode)
+                } else {
+                    hasher.hash_node(lnode, rnode)
+                };
+                if let Some(audit_trace) = audit_trace.as_mut() {
+                    audit_trace.record(lnode, rnode, inter_node);
                 }
+                level_cache.push(inter_node);
             }
+            self.nodes.append(&mut level_cache);
+            self.report_progress(level, started);
         }
+        self.audit_trace = audit_trace;
+        if let (Some(metrics), Some(started)) = (self.metrics(), started) {
+            metrics.tree_built(self.leaf_count, started.elapsed());
+        }
+        self.root_changed();
         self.nodes.iter().last()
     }
+
+    /// Returns the parent of the pair, reusing the result of an identical pair hashed earlier.
+    fn memoized_node(
+        hasher: &TreeHasher,
+        memo: &mut HashMap<(Hash, Hash), Hash>,
+        lnode: &Hash,
+        rnode: &Hash,
+    ) -> Hash {
+        if let Some(inter_node) = memo.get(&(*lnode, *rnode)) {
+            return *inter_node;
+        }
+        let inter_node = hasher.hash_node(lnode, rnode);
+        if memo.len() < SUBTREE_MEMO_LIMIT {
+            memo.insert((*lnode, *rnode), inter_node);
+        }
+        inter_node
+    }
     /// Returns the opening for the tree.
-    /// Opening - A list of all partner nodes with which when hashed together computes to the root.
-    pub fn get_opening(self: &Self, leaf_index: usize) -> Result<Vec<Hash>, MerkleTreeError> {
+    /// Opening - A list of all partner nodes with which when hashed together computes to the root,
+    /// along with the leaf index and the root itself.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(leaf_count = self.leaf_count, leaf_index = leaf_index))
+    )]
+    pub fn get_opening(&self, leaf_index: usize) -> Result<Opening, MerkleTreeError> {
         if leaf_index >= self.leaf_count {
             return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
                 "Tree has {} leaves but index given was {}",
                 self.leaf_count, leaf_index
             )));
         };
-        let height = Self::calculate_height(self.leaf_count);
-        let mut current_index = leaf_index;
-        let mut current_level_len: usize = self.leaf_count;
-        let mut current_level: usize = height + 1;
-        let mut path: Vec<Hash> = vec![];
-
-        let mut right_node = None;
-        let mut left_node = None;
-        let mut current_level_nodes = &self.nodes[0..self.leaf_count];
-        let mut prev_level_len: usize = 0;
-        while current_level > 0 {
-            if let Some(lnode) = left_node {
-                path.push(lnode);
-            }
-
-            if let Some(rnode) = right_node {
-                path.push(rnode);
+        self.ensure_openable()?;
+        if let Some(opening) = self.cached_opening(leaf_index) {
+            if let Some(metrics) = self.metrics() {
+                metrics.opening_generated(true);
             }
+            return Ok(opening);
+        }
+        let opening = self.view().get_opening_in(&self.layout(), leaf_index)?;
+        self.cache_opening(&opening);
+        if let Some(metrics) = self.metrics() {
+            metrics.opening_generated(false);
+        }
+        Ok(opening)
+    }
 
-            if current_index % 2 == 0 {
-                if current_index + 1 < current_level_len {
-                    right_node = Some(current_level_nodes[current_index + 1]);
-                } else {
-                    right_node = Some(current_level_nodes[current_index]);
-                }
-                left_node = None;
-            } else {
-                left_node = Some(current_level_nodes[current_index - 1]);
-                right_node = None;
-            }
-            current_index /= 2;
-            prev_level_len += current_level_len;
-            current_level_len = Self::calculate_next_level_len(current_level_len);
-            current_level -= 1;
+    /// Returns a bool in a result signifying if the opening is valid and the leaf stored at
+    /// its index computes to its root.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(leaf_count = self.leaf_count, leaf_index = opening.leaf_index))
+    )]
+    pub fn verify_opening(&self, opening: &Opening) -> Result<bool, MerkleTreeError> {
+        if opening.leaf_index >= self.leaf_count {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Tree has {} leaves but index given was {}",
+                self.leaf_count, opening.leaf_index
+            )));
+        }
+        self.ensure_built()?;
 
-            current_level_nodes = &self.nodes[prev_level_len..(prev_level_len + current_level_len)];
+        let verified = opening.verify_with(&self.tree_hasher(), &self.nodes[opening.leaf_index]);
+        if let (false, Some(metrics)) = (verified, self.metrics()) {
+            metrics.verification_failed();
         }
+        Ok(verified)
+    }
 
-        Ok(path)
+    /// Borrows the node storage as a read-only view.
+    pub fn view(&self) -> MerkleTreeView<'_> {
+        MerkleTreeView {
+            leaf_count: self.leaf_count,
+            nodes: &self.nodes,
+        }
     }
 
-    /// Returns a bool in a result signifying if the opening is valid and computes to the given root.
-    pub fn verify_opening(
-        self: &Self,
-        opening: Vec<Hash>,
-        root: Hash,
+    /// Replaces the leaf at the given index, recomputing its path if the tree is built.
+    pub fn update_leaf<T: AsRef<[u8]>>(
+        &mut self,
         leaf_index: usize,
-    ) -> Result<bool, MerkleTreeError> {
-        if leaf_index >= self.leaf_count {
+        leaf: T,
+    ) -> Result<(), MerkleTreeError> {
+        self.apply_updates(&[(leaf_index, leaf)])
+    }
+
+    /// Replaces all the given leaves, then recomputes the union of their paths once
+    /// if the tree is built. Nothing is written if any index is out of bounds.
+    pub fn apply_updates<T: AsRef<[u8]>>(
+        &mut self,
+        updates: &[(usize, T)],
+    ) -> Result<(), MerkleTreeError> {
+        let leaf_hashes: Vec<(usize, Hash)> = updates
+            .iter()
+            .map(|(leaf_index, leaf)| (*leaf_index, self.hash_leaf(leaf.as_ref())))
+            .collect();
+        self.set_leaf_hashes(&leaf_hashes)
+    }
+
+    /// Like `apply_updates`, but for leaves that are already hashed.
+    pub fn set_leaf_hashes(&mut self, updates: &[(usize, Hash)]) -> Result<(), MerkleTreeError> {
+        if let Some((leaf_index, _)) = updates.iter().find(|(i, _)| *i >= self.leaf_count) {
             return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
                 "Tree has {} leaves but index given was {}",
                 self.leaf_count, leaf_index
             )));
         }
 
-        let leaf = self.nodes[leaf_index];
-        let mut computed_root = Hash::default();
-        for (i, item) in opening.into_iter().enumerate() {
-            if i == 0 {
-                // Since the opening doesn't contain the leaf node
-                computed_root = hash_node!(item, leaf);
-            } else {
-                computed_root = hash_node!(item, computed_root)
+        let mut previous = Vec::with_capacity(updates.len());
+        for (leaf_index, leaf_hash) in updates {
+            previous.push(std::mem::replace(&mut self.nodes[*leaf_index], *leaf_hash));
+            self.record_bloom(*leaf_index..*leaf_index + 1);
+        }
+        if self.duplicates.is_some() {
+            let leaf_indices: Vec<usize> = updates.iter().map(|(i, _)| *i).collect();
+            self.record_duplicates(&leaf_indices, &previous);
+        }
+
+        self.mark_dirty(updates.iter().map(|(i, _)| *i));
+        Ok(())
+    }
+
+    /// Returns the Merkle Root of the tree, only rehashing the paths of leaves changed
+    /// since the last build. Falls back to a full build if the tree hasn't been built yet.
+    pub fn root(&mut self) -> Option<&Hash> {
+        if !self.is_built() {
+            return self.get_root();
+        }
+        self.flush();
+        self.nodes.last()
+    }
+
+    /// Returns a copy of the root without borrowing the tree mutably. Fails with
+    /// `RootNotComputed` if the tree hasn't been built or has pending updates.
+    pub fn try_root(&self) -> Result<Hash, MerkleTreeError> {
+        self.ensure_built()?;
+        if !self.dirty_leaves.is_empty() {
+            return Err(MerkleTreeError::RootNotComputed(
+                "Tree has pending updates, call root() or flush() first".to_string(),
+            ));
+        }
+        Ok(self.nodes[self.nodes.len() - 1])
+    }
+
+    /// Rehashes the paths of all leaves changed since the last build.
+    pub fn flush(&mut self) {
+        if self.dirty_leaves.is_empty() {
+            return;
+        }
+        let mut leaf_indices = std::mem::take(&mut self.dirty_leaves);
+        leaf_indices.sort_unstable();
+        leaf_indices.dedup();
+        self.recompute_paths(leaf_indices);
+    }
+
+    /// Records changed leaves of a built tree, rehashing their paths right away unless lazy.
+    fn mark_dirty<I: IntoIterator<Item = usize>>(&mut self, leaf_indices: I) {
+        if !self.is_built() {
+            return;
+        }
+        self.dirty_leaves.extend(leaf_indices);
+        if !self.lazy {
+            self.flush();
+        }
+    }
+
+    /// Returns true once the interior levels and root have been computed.
+    pub(crate) fn is_built(&self) -> bool {
+        self.leaf_count > 0 && self.nodes.len() == Self::calculate_max_capacity(self.leaf_count)
+    }
+
+    /// Fails with `RootNotComputed` until the interior levels have been computed.
+    pub(crate) fn ensure_built(&self) -> Result<(), MerkleTreeError> {
+        if !self.is_built() {
+            return Err(MerkleTreeError::RootNotComputed(
+                "Tree hasn't been built yet, call get_root() or root() first".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fails with `RootNotComputed` unless the tree is built and has no pending updates.
+    pub(crate) fn ensure_openable(&self) -> Result<(), MerkleTreeError> {
+        self.ensure_built()?;
+        if !self.dirty_leaves.is_empty() {
+            return Err(MerkleTreeError::RootNotComputed(format!(
+                "{} leaves changed since the last build, call root() or flush() first",
+                self.dirty_leaves.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Rehashes every ancestor of the given sorted, deduplicated leaf indices, level by level.
+    fn recompute_paths(&mut self, mut indices: Vec<usize>) {
+        let hasher = self.tree_hasher();
+        let layout = self.layout();
+
+        for level in 1..layout.level_count() {
+            let children = layout.level_range(level - 1).unwrap_or_default();
+            let offset = layout.offset_of_level(level).unwrap_or_default();
+            for index in indices.iter_mut() {
+                *index /= 2;
+            }
+            indices.dedup();
+
+            for &parent in &indices {
+                let lnode = self.nodes[children.start + 2 * parent];
+                let rnode = if 2 * parent + 1 < children.len() {
+                    self.nodes[children.start + 2 * parent + 1]
+                } else {
+                    lnode
+                };
+                self.nodes[offset + parent] = hasher.hash_node(&lnode, &rnode);
             }
         }
-        Ok(computed_root == root)
+        self.root_changed();
+    }
+
+    /// Bookkeeping after the root has been recomputed.
+    pub(crate) fn root_changed(&mut self) {
+        #[cfg(feature = "sanity-check")]
+        self.check_against_reference();
+        self.invalidate_proof_cache();
+        self.record_root();
+        self.record_chained_root();
+    }
+
+    /// Releases any spare capacity held by the node storage.
+    /// Returns the number of bytes freed.
+    pub fn shrink_to_fit(&mut self) -> usize {
+        let capacity = self.nodes.capacity();
+        self.nodes.shrink_to_fit();
+        (capacity - self.nodes.capacity()) * std::mem::size_of::<Hash>()
     }
 }
 
@@ -232,6 +560,36 @@ pub enum MerkleTreeError {
     LeafIndexOutOfBounds(String),
     #[error("Root not computed")]
     RootNotComputed(String),
+    #[error("proof does not match")]
+    ProofMismatch(String),
+    #[error("invalid checkpoint")]
+    InvalidCheckpoint(String),
+    #[error("invalid snapshot")]
+    InvalidSnapshot(String),
+    #[error("invalid changelog event")]
+    InvalidChangeLog(String),
+    #[error("local tree diverged from the source")]
+    RootDivergence(String),
+    #[error("not enough undo history")]
+    InsufficientUndoHistory(String),
+    #[error("invalid chunk size")]
+    InvalidChunkSize(String),
+    #[error("invalid proof encoding")]
+    InvalidProofEncoding(String),
+    #[error("keys are not strictly increasing")]
+    InvalidKeyOrder(String),
+    #[error("invalid recipients")]
+    InvalidRecipients(String),
+    #[error("invalid shard")]
+    InvalidShard(String),
+    #[error("leaf is already in the tree")]
+    DuplicateLeaf(String),
+    #[error("tree exceeds the maximum capacity")]
+    CapacityExceeded(String),
+    #[error("operation was cancelled")]
+    Cancelled(String),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
 }
 #[cfg(test)]
 mod tests {
@@ -267,6 +625,8 @@ mod tests {
         assert_eq!(MerkleTree::calculate_max_capacity(11), 23);
         assert_eq!(MerkleTree::calculate_max_capacity(16), 31);
         assert_eq!(MerkleTree::calculate_max_capacity(1024), 2047);
+        assert_eq!(MerkleTree::checked_max_capacity(usize::MAX), None);
+        assert_eq!(MerkleTree::checked_max_capacity(5), Some(11));
     }
     #[test]
     fn test_calculate_valid_height() {
@@ -274,6 +634,9 @@ mod tests {
         assert_eq!(MerkleTree::calculate_height(1), 0);
         assert_eq!(MerkleTree::calculate_height(5), 3);
         assert_eq!(MerkleTree::calculate_height(1024), 10);
+        assert_eq!(MerkleTree::calculate_height(1025), 11);
+        const HEIGHT: usize = MerkleTree::calculate_height(5);
+        assert_eq!(HEIGHT, 3);
     }
     #[test]
     fn test_valid_merkle_root() {
@@ -284,7 +647,7 @@ mod tests {
         }
 
         let root = merkle_tree.get_root();
-        matches!(root, Some(_));
+        assert!(root.is_some());
         assert_eq!(root.unwrap().to_string(), EXPECTED.to_string());
     }
     #[test]
@@ -297,11 +660,33 @@ mod tests {
         let _ = merkle_tree.get_root();
 
         let opening = merkle_tree.get_opening(9).unwrap();
-        assert_eq!(opening.len(), 4);
-        let is_valid = merkle_tree.verify_opening(opening, Hash::from_str(EXPECTED).unwrap(), 9);
+        assert_eq!(opening.path.len(), 4);
+        assert_eq!(opening.leaf_index, 9);
+        assert_eq!(opening.root, Hash::from_str(EXPECTED).unwrap());
+        let is_valid = merkle_tree.verify_opening(&opening);
         assert!(is_valid.is_ok());
         assert!(is_valid.unwrap())
     }
+
+    #[test]
+    fn test_valid_opening_every_index() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+
+        for leaf in SAMPLE {
+            let _ = merkle_tree.insert(leaf);
+        }
+        let _ = merkle_tree.get_root();
+
+        for leaf_index in 0..SAMPLE.len() {
+            let opening = merkle_tree.get_opening(leaf_index).unwrap();
+            assert!(merkle_tree.verify_opening(&opening).unwrap());
+            assert!(!opening.verify(
+                merkle_tree
+                    .get_value((leaf_index + 1) % SAMPLE.len())
+                    .unwrap()
+            ));
+        }
+    }
     #[test]
     fn test_invalid_index_opening() {
         let mut merkle_tree = MerkleTree::new(SAMPLE.len());
@@ -312,7 +697,63 @@ mod tests {
         let _ = merkle_tree.get_root();
 
         let opening = merkle_tree.get_opening(10);
-        matches!(opening, Err(_));
+        assert!(opening.is_err());
+    }
+
+    #[test]
+    fn test_opening_before_build() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+
+        for leaf in SAMPLE {
+            let _ = merkle_tree.insert(leaf);
+        }
+        assert!(matches!(
+            merkle_tree.get_opening(0),
+            Err(MerkleTreeError::RootNotComputed(_))
+        ));
+
+        let opening = MerkleTree::from_leaves(SAMPLE).get_opening(0).unwrap();
+        assert!(matches!(
+            merkle_tree.verify_opening(&opening),
+            Err(MerkleTreeError::RootNotComputed(_))
+        ));
+        let _ = merkle_tree.get_root();
+        assert_eq!(merkle_tree.get_opening(0).unwrap(), opening);
+        assert!(merkle_tree.verify_opening(&opening).unwrap());
+    }
+
+    #[test]
+    fn test_try_root() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        assert!(matches!(
+            merkle_tree.try_root(),
+            Err(MerkleTreeError::RootNotComputed(_))
+        ));
+
+        let root = *merkle_tree.get_root().unwrap();
+        assert_eq!(merkle_tree.try_root().unwrap(), root);
+        merkle_tree.lazy = true;
+        merkle_tree.update_leaf(0, b"foo").unwrap();
+        assert!(merkle_tree.try_root().is_err());
+        merkle_tree.flush();
+        assert_ne!(merkle_tree.try_root().unwrap(), root);
+    }
+
+    #[test]
+    fn test_hash_functions() {
+        let merkle_tree = MerkleTree::from_leaves(&SAMPLE[..2]);
+        let (lnode, rnode) = (hash_leaf(SAMPLE[0]), hash_leaf(SAMPLE[1]));
+        assert_eq!(merkle_tree.nodes[..2], [lnode, rnode]);
+        assert_eq!(merkle_tree.nodes[2], hash_node(&lnode, &rnode));
+
+        let mut tagged = MerkleTree::new(2);
+        tagged.hash_domain = HashDomain::Tagged;
+        assert_eq!(
+            tagged.hash_leaf(SAMPLE[0]),
+            HashDomain::Tagged.hash_leaf(SAMPLE[0])
+        );
+        assert_ne!(tagged.hash_node(&lnode, &rnode), hash_node(&lnode, &rnode));
     }
 
     #[test]
@@ -324,9 +765,206 @@ mod tests {
         }
         let _ = merkle_tree.get_root();
 
-        let opening = merkle_tree.get_opening(9).unwrap();
-        let is_valid = merkle_tree.verify_opening(opening, Hash::new_unique(), 9);
+        let mut opening = merkle_tree.get_opening(9).unwrap();
+        opening.root = Hash::new_unique();
+        let is_valid = merkle_tree.verify_opening(&opening);
         assert!(is_valid.is_ok());
         assert!(!is_valid.unwrap())
     }
+
+    #[test]
+    fn test_insert_returns_index() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+
+        assert_eq!(merkle_tree.insert_indexed(SAMPLE[0]).unwrap(), 0);
+        assert_eq!(merkle_tree.insert_batch(&SAMPLE[1..4]).unwrap(), 1..4);
+        assert!(merkle_tree.insert_batch(SAMPLE).is_err());
+        assert_eq!(merkle_tree.current_leaf_index, 4);
+        assert_eq!(merkle_tree.insert_batch(&SAMPLE[4..]).unwrap(), 4..10);
+        assert!(merkle_tree.insert_indexed(SAMPLE[0]).is_err());
+
+        let root = merkle_tree.get_root();
+        assert_eq!(root.unwrap().to_string(), EXPECTED.to_string());
+    }
+
+    #[test]
+    fn test_get_root_is_idempotent() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+
+        let _ = merkle_tree.get_root();
+        let root = merkle_tree.get_root();
+        assert_eq!(root.unwrap().to_string(), EXPECTED.to_string());
+    }
+
+    #[test]
+    fn test_apply_updates() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+
+        let updates: &[(usize, &[u8])] = &[(0, b"foo"), (9, b"bar"), (8, b"baz"), (0, b"qux")];
+        merkle_tree.apply_updates(updates).unwrap();
+        merkle_tree.update_leaf(4, b"quux").unwrap();
+        let updated_nodes = merkle_tree.nodes.clone();
+
+        let mut expected_tree = MerkleTree::new(SAMPLE.len());
+        expected_tree.insert_batch(SAMPLE).unwrap();
+        for (leaf_index, leaf) in updates.iter().chain(&[(4, &b"quux"[..])]) {
+            expected_tree.nodes[*leaf_index] = hash_leaf!(leaf);
+        }
+        let _ = expected_tree.get_root();
+        assert_eq!(updated_nodes, expected_tree.nodes);
+
+        assert!(merkle_tree.update_leaf(10, b"foo").is_err());
+        assert_eq!(merkle_tree.nodes, updated_nodes);
+    }
+
+    #[test]
+    fn test_lazy_updates() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(&SAMPLE[..8]).unwrap();
+        let _ = merkle_tree.get_root();
+
+        merkle_tree.lazy = true;
+        let stale_nodes = merkle_tree.nodes.clone();
+        merkle_tree.insert_batch(&SAMPLE[8..]).unwrap();
+        merkle_tree.update_leaf(3, b"foo").unwrap();
+        merkle_tree.update_leaf(3, SAMPLE[3]).unwrap();
+        assert_eq!(
+            merkle_tree.nodes[SAMPLE.len()..],
+            stale_nodes[SAMPLE.len()..]
+        );
+        assert!(merkle_tree.get_opening(0).is_err());
+
+        let root = merkle_tree.root();
+        assert_eq!(root.unwrap().to_string(), EXPECTED.to_string());
+        let opening = merkle_tree.get_opening(9).unwrap();
+        assert!(merkle_tree.verify_opening(&opening).unwrap());
+    }
+
+    #[test]
+    fn test_insert_after_build() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(&SAMPLE[..5]).unwrap();
+        let _ = merkle_tree.get_root();
+
+        merkle_tree.insert_batch(&SAMPLE[5..]).unwrap();
+        assert_eq!(merkle_tree.nodes.last().unwrap().to_string(), EXPECTED);
+    }
+
+    #[test]
+    fn test_leaf_range_reads() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+
+        let values = merkle_tree.get_values(2..5).unwrap();
+        assert_eq!(values.len(), 3);
+        assert_eq!(&values[0], merkle_tree.get_value(2).unwrap());
+        assert!(merkle_tree.get_values(8..11).is_none());
+
+        let pages: Vec<&[Hash]> = merkle_tree.leaf_pages(4).collect();
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[2], merkle_tree.get_values(8..10).unwrap());
+    }
+
+    #[test]
+    fn test_from_leaves() {
+        let merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        assert_eq!(merkle_tree.current_leaf_index, SAMPLE.len());
+        assert_eq!(merkle_tree.nodes.last().unwrap().to_string(), EXPECTED);
+        assert_eq!(merkle_tree.nodes.capacity(), merkle_tree.nodes.len());
+        assert!(MerkleTree::from_leaves::<&[u8]>(&[]).nodes.is_empty());
+    }
+
+    #[test]
+    fn test_memoized_subtrees() {
+        let mut merkle_tree = MerkleTree::new(1000);
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let expected_root = *merkle_tree.get_root().unwrap();
+
+        merkle_tree.memoize_subtrees = true;
+        assert_eq!(merkle_tree.get_root(), Some(&expected_root));
+    }
+
+    #[test]
+    fn test_shrink_to_fit() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.nodes.reserve(64);
+
+        for leaf in SAMPLE {
+            let _ = merkle_tree.insert(leaf);
+        }
+        let _ = merkle_tree.get_root();
+
+        let freed = merkle_tree.shrink_to_fit();
+        assert!(freed > 0);
+        assert_eq!(merkle_tree.nodes.capacity(), merkle_tree.nodes.len());
+        assert_eq!(merkle_tree.shrink_to_fit(), 0);
+        assert_eq!(merkle_tree.nodes.last().unwrap().to_string(), EXPECTED);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_span_fields() {
+        use crate::SpillBuilder;
+        use std::sync::{Arc, Mutex};
+        use tracing::{
+            field::{Field, Visit},
+            span, Event, Metadata, Subscriber,
+        };
+
+        /// Records `(span, field, value)` for every field a span is created with.
+        #[derive(Clone, Default)]
+        struct SpanRecorder(Arc<Mutex<Vec<(String, String, String)>>>);
+
+        struct FieldRecorder<'a>(&'a str, &'a mut Vec<(String, String, String)>);
+
+        impl Visit for FieldRecorder<'_> {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.1.push((
+                    self.0.to_string(),
+                    field.name().to_string(),
+                    format!("{:?}", value),
+                ));
+            }
+        }
+
+        impl Subscriber for SpanRecorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+                let mut fields = self.0.lock().unwrap();
+                span.record(&mut FieldRecorder(span.metadata().name(), &mut fields));
+                span::Id::from_u64(fields.len() as u64 + 1)
+            }
+
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+            fn event(&self, _: &Event<'_>) {}
+
+            fn enter(&self, _: &span::Id) {}
+
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let recorder = SpanRecorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            MerkleTree::from_leaves(SAMPLE).get_opening(3).unwrap();
+            SpillBuilder::new().build(SAMPLE, SAMPLE.len()).unwrap();
+        });
+        let fields = recorder.0.lock().unwrap();
+        let recorded = |span: &str, field: &str, value: &str| {
+            fields
+                .iter()
+                .any(|recorded| recorded == &(span.into(), field.into(), value.into()))
+        };
+        assert!(recorded("get_opening", "leaf_index", "3"));
+        assert!(recorded("build", "leaf_count", &SAMPLE.len().to_string()));
+    }
 }
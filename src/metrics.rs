@@ -0,0 +1,109 @@
+use crate::MerkleTree;
+use std::{fmt, sync::Arc, time::Duration};
+
+/// Hooks for exporting tree activity to a metrics system such as Prometheus. Every method
+/// defaults to a no-op, so implementors only override what they record.
+pub trait Metrics: Send + Sync {
+    /// Called after leaves are inserted, with the number inserted.
+    fn leaves_inserted(&self, _count: usize) {}
+
+    /// Called after a full build of the tree.
+    fn tree_built(&self, _leaf_count: usize, _duration: Duration) {}
+
+    /// Called after an opening is generated or served from the proof cache.
+    fn opening_generated(&self, _cached: bool) {}
+
+    /// Called when `verify_opening` rejects an opening.
+    fn verification_failed(&self) {}
+}
+
+/// Metrics implementation that records nothing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopMetrics;
+
+impl Metrics for NoopMetrics {}
+
+#[derive(Clone)]
+pub(crate) struct MetricsHandle(Arc<dyn Metrics>);
+
+impl fmt::Debug for MetricsHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MetricsHandle")
+    }
+}
+
+impl MerkleTree {
+    /// Reports tree activity to the given metrics implementation.
+    pub fn set_metrics(&mut self, metrics: Arc<dyn Metrics>) {
+        self.metrics = Some(MetricsHandle(metrics));
+    }
+
+    pub(crate) fn metrics(&self) -> Option<&dyn Metrics> {
+        self.metrics.as_ref().map(|handle| handle.0.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Opening;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[derive(Default)]
+    struct Counters {
+        inserted: AtomicUsize,
+        builds: AtomicUsize,
+        openings: AtomicUsize,
+        cached_openings: AtomicUsize,
+        failures: AtomicUsize,
+    }
+
+    impl Metrics for Counters {
+        fn leaves_inserted(&self, count: usize) {
+            self.inserted.fetch_add(count, Ordering::Relaxed);
+        }
+
+        fn tree_built(&self, _leaf_count: usize, _duration: Duration) {
+            self.builds.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn opening_generated(&self, cached: bool) {
+            self.openings.fetch_add(1, Ordering::Relaxed);
+            if cached {
+                self.cached_openings.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        fn verification_failed(&self) {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_metrics() {
+        let counters = Arc::new(Counters::default());
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.set_metrics(counters.clone());
+        merkle_tree.enable_proof_cache(2);
+        merkle_tree.insert(SAMPLE[0]).unwrap();
+        merkle_tree.insert_batch(&SAMPLE[1..]).unwrap();
+        let _ = merkle_tree.get_root();
+
+        let opening = merkle_tree.get_opening(1).unwrap();
+        merkle_tree.get_opening(1).unwrap();
+        assert!(merkle_tree.verify_opening(&opening).unwrap());
+        let wrong_leaf = Opening {
+            leaf_index: 2,
+            ..opening
+        };
+        assert!(!merkle_tree.verify_opening(&wrong_leaf).unwrap());
+
+        assert_eq!(counters.inserted.load(Ordering::Relaxed), SAMPLE.len());
+        assert_eq!(counters.builds.load(Ordering::Relaxed), 1);
+        assert_eq!(counters.openings.load(Ordering::Relaxed), 2);
+        assert_eq!(counters.cached_openings.load(Ordering::Relaxed), 1);
+        assert_eq!(counters.failures.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{HashDomain, MerkleTree, MerkleTreeError, Opening};
+use solana_program::hash::Hash;
+
+/// How a level with an odd number of nodes is completed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LeafPadding {
+    /// The last node is paired with itself, as in `MerkleTree::from_leaves`.
+    #[default]
+    DuplicateLast,
+    /// Leaves are padded with `DEFAULT_LEAF` up to the next power of two, as in a
+    /// fixed-depth `MerkleTreeState`.
+    PowerOfTwo,
+}
+
+/// Layout a deployment's tree is built with. Trees in this crate are binary, so only the
+/// prefixing and padding vary.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TreeConfig {
+    pub hash_domain: HashDomain,
+    pub padding: LeafPadding,
+}
+
+impl TreeConfig {
+    /// Builds the tree over the raw leaves in this layout.
+    pub fn build<T: AsRef<[u8]>>(&self, leaves: &[T]) -> Result<MerkleTree, MerkleTreeError> {
+        let leaf_count = match self.padding {
+            LeafPadding::DuplicateLast => leaves.len(),
+            LeafPadding::PowerOfTwo => leaves.len().next_power_of_two(),
+        };
+        let mut merkle_tree = MerkleTree::new(leaf_count);
+        merkle_tree.hash_domain = self.hash_domain;
+        merkle_tree.insert_batch(leaves)?;
+        let _ = merkle_tree.get_root();
+        Ok(merkle_tree)
+    }
+
+    /// Returns true if the raw leaf verifies against the opening's root in this layout.
+    pub fn verify(&self, opening: &Opening, leaf: &[u8]) -> bool {
+        opening.verify_in(self.hash_domain, &self.hash_domain.hash_leaf(leaf))
+    }
+}
+
+/// Openings of every leaf under an old and a new layout, mapping each leaf index to its
+/// proof in both so a migration can be audited.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofMigration {
+    pub from: TreeConfig,
+    pub to: TreeConfig,
+    pub old_root: Hash,
+    pub new_root: Hash,
+    /// `(old, new)` openings, indexed by leaf.
+    pub openings: Vec<(Opening, Opening)>,
+}
+
+impl ProofMigration {
+    /// Re-derives the proof of every leaf under both layouts.
+    pub fn new<T: AsRef<[u8]>>(
+        leaves: &[T],
+        from: TreeConfig,
+        to: TreeConfig,
+    ) -> Result<Self, MerkleTreeError> {
+        let old_tree = from.build(leaves)?;
+        let new_tree = to.build(leaves)?;
+        let openings = (0..leaves.len())
+            .map(|leaf_index| {
+                Ok((
+                    old_tree.get_opening(leaf_index)?,
+                    new_tree.get_opening(leaf_index)?,
+                ))
+            })
+            .collect::<Result<_, MerkleTreeError>>()?;
+        Ok(Self {
+            from,
+            to,
+            old_root: old_tree.try_root()?,
+            new_root: new_tree.try_root()?,
+            openings,
+        })
+    }
+
+    /// Returns true if every leaf is proven at the same index under both roots, i.e. the
+    /// new root commits to exactly the leaves the old one did.
+    pub fn verify<T: AsRef<[u8]>>(&self, leaves: &[T]) -> bool {
+        leaves.len() == self.openings.len()
+            && leaves.iter().zip(&self.openings).enumerate().all(
+                |(leaf_index, (leaf, (old, new)))| {
+                    old.leaf_index == leaf_index
+                        && new.leaf_index == leaf_index
+                        && old.root == self.old_root
+                        && new.root == self.new_root
+                        && self.from.verify(old, leaf.as_ref())
+                        && self.to.verify(new, leaf.as_ref())
+                },
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_proof_migration() {
+        let to = TreeConfig {
+            hash_domain: HashDomain::Tagged,
+            padding: LeafPadding::PowerOfTwo,
+        };
+        let migration = ProofMigration::new(SAMPLE, TreeConfig::default(), to).unwrap();
+        assert_eq!(
+            &migration.old_root,
+            MerkleTree::from_leaves(SAMPLE).nodes.last().unwrap()
+        );
+        assert_eq!(migration.openings[4].1.path.len(), 3);
+        assert!(migration.verify(SAMPLE));
+
+        let mut reordered = SAMPLE.to_vec();
+        reordered.swap(0, 1);
+        assert!(!migration.verify(&reordered));
+        assert!(!migration.verify(&SAMPLE[..4]));
+    }
+}
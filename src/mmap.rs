@@ -0,0 +1,154 @@
+use crate::{
+    MerkleTree, MerkleTreeError, MerkleTreeView, Opening, SnapshotHeader, SNAPSHOT_HEADER_LEN,
+};
+use memmap2::Mmap;
+use solana_program::hash::{Hash, HASH_BYTES};
+use std::{fs::File, path::Path};
+
+/// Serves openings straight from a memory-mapped snapshot file, so a saved tree can be
+/// queried without reading its nodes into memory. Opening `open` only checks the header
+/// and root; each opening is checked lazily against the root as it's served, rehashing its
+/// path in the hash domain recorded in the header, so corrupted nodes fail only the openings
+/// that touch them.
+#[derive(Debug)]
+pub struct SnapshotVerifier {
+    pub header: SnapshotHeader,
+    mmap: Mmap,
+}
+
+impl SnapshotVerifier {
+    /// Maps a snapshot written by `MerkleTree::save` read-only and validates its header
+    /// checksum, its length and that its last node is the root recorded in the header.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, MerkleTreeError> {
+        let file = File::open(path)?;
+        // Safety: the mapping is read-only. Callers must not truncate or rewrite the file
+        // while the verifier is alive, as with any memory-mapped file.
+        let mmap = unsafe { Mmap::map(&file)? };
+        if mmap.len() < SNAPSHOT_HEADER_LEN {
+            return Err(MerkleTreeError::InvalidSnapshot(format!(
+                "Snapshot of {} bytes is shorter than its header",
+                mmap.len()
+            )));
+        }
+        // Nothing is allocated from the header, the file length bounds the node count
+        let header =
+            SnapshotHeader::from_bytes_with_limit(&mmap[..SNAPSHOT_HEADER_LEN], usize::MAX)?;
+        if header.node_count != MerkleTree::check_capacity(header.leaf_count, usize::MAX)?
+            || header.leaf_count == 0
+        {
+            return Err(MerkleTreeError::RootNotComputed(
+                "Snapshot was saved before the tree was built".to_string(),
+            ));
+        }
+        let nodes_len = mmap.len() - SNAPSHOT_HEADER_LEN;
+        if nodes_len != header.node_count * HASH_BYTES {
+            return Err(MerkleTreeError::InvalidSnapshot(format!(
+                "Snapshot declares {} nodes but has {} bytes of them",
+                header.node_count, nodes_len
+            )));
+        }
+        let verifier = Self { header, mmap };
+        if *verifier.view().root() != header.root {
+            return Err(MerkleTreeError::InvalidSnapshot(
+                "Snapshot root doesn't match its header".to_string(),
+            ));
+        }
+        Ok(verifier)
+    }
+
+    /// Borrows the mapped nodes as a read-only view. Nodes read through it aren't verified.
+    pub fn view(&self) -> MerkleTreeView<'_> {
+        MerkleTreeView::from_bytes(self.header.leaf_count, &self.mmap[SNAPSHOT_HEADER_LEN..])
+            .expect("node count was validated on open")
+    }
+
+    /// Returns the Merkle Root of the tree.
+    pub fn root(&self) -> &Hash {
+        self.view().root()
+    }
+
+    /// Returns the leaf at given index, unverified; `get_verified_value` checks it.
+    pub fn get_value(&self, leaf_index: usize) -> Option<&Hash> {
+        self.view().get_value(leaf_index)
+    }
+
+    /// Returns the leaf at given index once its opening verifies against the root.
+    pub fn get_verified_value(&self, leaf_index: usize) -> Result<&Hash, MerkleTreeError> {
+        self.get_opening(leaf_index)?;
+        Ok(&self.view().nodes[leaf_index])
+    }
+
+    /// Returns the opening for the leaf at given index, failing with `InvalidSnapshot` if
+    /// the leaf and path read from the file don't compute to the root.
+    pub fn get_opening(&self, leaf_index: usize) -> Result<Opening, MerkleTreeError> {
+        let view = self.view();
+        let opening = view.get_opening(leaf_index)?;
+        if !opening.verify_in(self.header.hash_domain, &view.nodes[leaf_index]) {
+            return Err(MerkleTreeError::InvalidSnapshot(format!(
+                "Snapshot nodes on the path of leaf {} don't hash to its root",
+                leaf_index
+            )));
+        }
+        Ok(opening)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HashDomain;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_snapshot_verifier() {
+        let path = std::env::temp_dir().join(format!("fmt-mmap-{}.snapshot", std::process::id()));
+        let merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        merkle_tree.save(&path).unwrap();
+
+        let verifier = SnapshotVerifier::open(&path).unwrap();
+        assert_eq!(verifier.root(), merkle_tree.nodes.last().unwrap());
+        for leaf_index in 0..SAMPLE.len() {
+            let opening = verifier.get_opening(leaf_index).unwrap();
+            assert_eq!(opening, merkle_tree.get_opening(leaf_index).unwrap());
+            assert!(opening.verify(verifier.get_value(leaf_index).unwrap()));
+        }
+
+        // Corrupting leaf 4 fails its opening but leaves the other half of the tree usable
+        let mut snapshot = std::fs::read(&path).unwrap();
+        snapshot[SNAPSHOT_HEADER_LEN + 4 * HASH_BYTES] ^= 1;
+        std::fs::write(&path, &snapshot).unwrap();
+        let verifier = SnapshotVerifier::open(&path).unwrap();
+        assert!(matches!(
+            verifier.get_opening(4),
+            Err(MerkleTreeError::InvalidSnapshot(_))
+        ));
+        assert!(verifier.get_verified_value(4).is_err());
+        assert!(verifier.get_opening(0).is_ok());
+        assert_eq!(
+            verifier.get_verified_value(0).unwrap(),
+            &merkle_tree.nodes[0]
+        );
+        drop(verifier);
+
+        let mut tagged = MerkleTree::new(SAMPLE.len());
+        tagged.hash_domain = HashDomain::Tagged;
+        tagged.insert_batch(SAMPLE).unwrap();
+        let _ = tagged.get_root();
+        tagged.save(&path).unwrap();
+        let verifier = SnapshotVerifier::open(&path).unwrap();
+        for leaf_index in 0..SAMPLE.len() {
+            assert_eq!(
+                verifier.get_opening(leaf_index).unwrap(),
+                tagged.get_opening(leaf_index).unwrap()
+            );
+        }
+        drop(verifier);
+
+        let mut unbuilt = MerkleTree::new(SAMPLE.len());
+        unbuilt.insert_batch(SAMPLE).unwrap();
+        unbuilt.save(&path).unwrap();
+        assert!(SnapshotVerifier::open(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{
+    proof::{compute_root_from_bytes, split_compact_bytes},
+    HashDomain, MerkleHasher, MerkleProof, MerkleTreeError,
+};
+use solana_program::hash::Hash;
+
+/// Returns the left/right positions along the proof of a leaf in a full tree of the given
+/// depth: bit `i` is set when the node at level `i` is a right child, so its partner is hashed
+/// on the left. This is the low `depth` bits of the leaf index.
+pub const fn direction_bitmask(leaf_index: u32, depth: u8) -> u32 {
+    if depth >= 32 {
+        leaf_index
+    } else {
+        leaf_index & ((1 << depth) - 1)
+    }
+}
+
+/// Returns true if the node at `level` is a right child according to the bitmask.
+pub const fn is_right_child(bitmask: u32, level: u8) -> bool {
+    level < 32 && bitmask & (1 << level) != 0
+}
+
+/// A self-describing proof of inclusion for a single leaf.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Opening {
+    /// Index of the leaf the opening was generated for.
+    pub leaf_index: usize,
+    /// Partner nodes from the leaf level up to (but excluding) the root.
+    pub path: Vec<Hash>,
+    /// Root the path computes to.
+    pub root: Hash,
+}
+
+impl Opening {
+    /// Hashes the leaf up the path and returns the resulting root.
+    /// The bits of `leaf_index` decide which side each partner node sits on.
+    pub fn compute_root(&self, leaf: &Hash) -> Hash {
+        self.compute_root_in(HashDomain::Prefix, leaf)
+    }
+
+    /// Like `compute_root`, for trees using the given domain separation.
+    pub fn compute_root_in(&self, domain: HashDomain, leaf: &Hash) -> Hash {
+        self.compute_root_with(&domain, leaf)
+    }
+
+    /// Like `compute_root`, for trees using the given hasher.
+    pub fn compute_root_with<H: MerkleHasher + ?Sized>(&self, hasher: &H, leaf: &Hash) -> Hash {
+        let mut current_index = self.leaf_index;
+        let mut computed_root = *leaf;
+        for partner in &self.path {
+            computed_root = if current_index % 2 == 0 {
+                hasher.hash_node(&computed_root, partner)
+            } else {
+                hasher.hash_node(partner, &computed_root)
+            };
+            current_index /= 2;
+        }
+        computed_root
+    }
+
+    /// Returns true if the leaf hash computes to the root embedded in the opening.
+    /// Callers still need to check that `root` is one they trust.
+    pub fn verify(&self, leaf: &Hash) -> bool {
+        self.compute_root(leaf) == self.root
+    }
+
+    /// Like `verify`, for trees using the given domain separation.
+    pub fn verify_in(&self, domain: HashDomain, leaf: &Hash) -> bool {
+        self.compute_root_in(domain, leaf) == self.root
+    }
+
+    /// Like `verify`, for trees using the given hasher.
+    pub fn verify_with<H: MerkleHasher + ?Sized>(&self, hasher: &H, leaf: &Hash) -> bool {
+        self.compute_root_with(hasher, leaf) == self.root
+    }
+
+    /// Returns the direction bitmask of the path, or None if it's deeper than 32 levels or
+    /// the index doesn't fit in a u32.
+    pub fn direction_bitmask(&self) -> Option<u32> {
+        let depth = u8::try_from(self.path.len())
+            .ok()
+            .filter(|depth| *depth <= 32)?;
+        Some(direction_bitmask(
+            u32::try_from(self.leaf_index).ok()?,
+            depth,
+        ))
+    }
+
+    /// Encodes the proof as `u8 depth || u32 index (LE) || depth * 32 bytes`, so programs
+    /// can read it at fixed offsets. The index doubles as the direction bitmask of the path,
+    /// see `direction_bitmask`. The root isn't included.
+    pub fn to_compact_bytes(&self) -> Result<Vec<u8>, MerkleTreeError> {
+        MerkleProof::try_from(self)?.to_bytes()
+    }
+
+    /// Parses a proof written by `to_compact_bytes` for the given root, see
+    /// `MerkleProof::from_bytes` for the checks.
+    pub fn from_compact_bytes(bytes: &[u8], root: Hash) -> Result<Self, MerkleTreeError> {
+        Ok(MerkleProof::from_bytes(bytes)?.into_opening(root))
+    }
+
+    /// Verifies a proof written by `to_compact_bytes` straight from its bytes, walking the
+    /// direction bitmask without decoding the path.
+    pub fn verify_compact_bytes(
+        bytes: &[u8],
+        root: &Hash,
+        leaf: &Hash,
+    ) -> Result<bool, MerkleTreeError> {
+        Self::verify_compact_bytes_in(HashDomain::Prefix, bytes, root, leaf)
+    }
+
+    /// Like `verify_compact_bytes`, for trees using the given domain separation.
+    pub fn verify_compact_bytes_in(
+        domain: HashDomain,
+        bytes: &[u8],
+        root: &Hash,
+        leaf: &Hash,
+    ) -> Result<bool, MerkleTreeError> {
+        let (bitmask, path_bytes) = split_compact_bytes(bytes)?;
+        Ok(compute_root_from_bytes(domain, bitmask, path_bytes, leaf) == *root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MerkleTree, COMPACT_PROOF_HEADER_LEN};
+    use solana_program::hash::HASH_BYTES;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_compact_encoding() {
+        let merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        let opening = merkle_tree.get_opening(3).unwrap();
+        let bytes = opening.to_compact_bytes().unwrap();
+        assert_eq!(bytes.len(), COMPACT_PROOF_HEADER_LEN + 3 * HASH_BYTES);
+        assert_eq!(&bytes[..COMPACT_PROOF_HEADER_LEN], &[3, 3, 0, 0, 0]);
+        assert_eq!(
+            Opening::from_compact_bytes(&bytes, opening.root).unwrap(),
+            opening
+        );
+
+        assert!(Opening::from_compact_bytes(&bytes[..bytes.len() - 1], opening.root).is_err());
+        assert!(Opening::from_compact_bytes(&bytes[..4], opening.root).is_err());
+        let mut too_long = bytes.clone();
+        too_long.push(0);
+        assert!(Opening::from_compact_bytes(&too_long, opening.root).is_err());
+
+        let oversized = Opening {
+            leaf_index: u32::MAX as usize + 1,
+            ..opening
+        };
+        assert!(oversized.to_compact_bytes().is_err());
+    }
+
+    #[test]
+    fn test_direction_bitmask() {
+        assert_eq!(direction_bitmask(0b1011, 3), 0b011);
+        assert_eq!(direction_bitmask(u32::MAX, 32), u32::MAX);
+        assert!(is_right_child(0b010, 1));
+        assert!(!is_right_child(0b010, 2));
+
+        let merkle_tree = MerkleTree::from_leaves(&[SAMPLE, SAMPLE].concat());
+        let opening = merkle_tree.get_opening(6).unwrap();
+        assert_eq!(opening.direction_bitmask(), Some(0b0110));
+        let bytes = opening.to_compact_bytes().unwrap();
+        let leaf = merkle_tree.nodes[6];
+        assert!(Opening::verify_compact_bytes(&bytes, &opening.root, &leaf).unwrap());
+        assert!(
+            !Opening::verify_compact_bytes(&bytes, &opening.root, &merkle_tree.nodes[7]).unwrap()
+        );
+        assert!(Opening::verify_compact_bytes(&bytes[1..], &opening.root, &leaf).is_err());
+    }
+}
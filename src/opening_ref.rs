@@ -0,0 +1,167 @@
+use crate::{
+    HashDomain, MerkleHasher, MerkleTree, MerkleTreeError, MerkleTreeView, Opening,
+    SharedMerkleTree,
+};
+use solana_program::hash::Hash;
+
+/// An opening that borrows its partner nodes from the tree's node buffer instead of copying
+/// them. The partners of a leaf are spread across levels, so a `MerkleProof` over a
+/// `Cow<[Hash]>` path could only borrow by copying them into one slice first; the path is
+/// walked lazily instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpeningRef<'a> {
+    pub leaf_index: usize,
+    view: MerkleTreeView<'a>,
+}
+
+impl<'a> OpeningRef<'a> {
+    /// Returns the partner nodes from the leaf level up to (but excluding) the root.
+    pub fn path(&self) -> impl Iterator<Item = &'a Hash> + 'a {
+        let nodes = self.view.nodes;
+        let mut position = self.leaf_index;
+        let mut level_offset = 0;
+        let mut level_len = self.view.leaf_count;
+        std::iter::from_fn(move || {
+            if level_len <= 1 {
+                return None;
+            }
+            let partner = if position ^ 1 < level_len {
+                position ^ 1
+            } else {
+                position
+            };
+            let node = &nodes[level_offset + partner];
+            position /= 2;
+            level_offset += level_len;
+            level_len = MerkleTree::calculate_next_level_len(level_len);
+            Some(node)
+        })
+    }
+
+    /// Returns the root the path computes to.
+    pub fn root(&self) -> &'a Hash {
+        self.view.root()
+    }
+
+    /// Like `Opening::compute_root`, for trees using the given domain separation.
+    pub fn compute_root_in(&self, domain: HashDomain, leaf: &Hash) -> Hash {
+        self.compute_root_with(&domain, leaf)
+    }
+
+    /// Like `Opening::compute_root`, for trees using the given hasher.
+    pub fn compute_root_with<H: MerkleHasher + ?Sized>(&self, hasher: &H, leaf: &Hash) -> Hash {
+        let mut current_index = self.leaf_index;
+        let mut computed_root = *leaf;
+        for partner in self.path() {
+            computed_root = if current_index % 2 == 0 {
+                hasher.hash_node(&computed_root, partner)
+            } else {
+                hasher.hash_node(partner, &computed_root)
+            };
+            current_index /= 2;
+        }
+        computed_root
+    }
+
+    /// Returns true if the leaf hash computes to the root.
+    pub fn verify(&self, leaf: &Hash) -> bool {
+        self.verify_with(&HashDomain::Prefix, leaf)
+    }
+
+    /// Like `verify`, for trees using the given hasher.
+    pub fn verify_with<H: MerkleHasher + ?Sized>(&self, hasher: &H, leaf: &Hash) -> bool {
+        self.compute_root_with(hasher, leaf) == *self.root()
+    }
+
+    /// Copies the path into an owned `Opening`.
+    pub fn to_opening(&self) -> Opening {
+        Opening {
+            leaf_index: self.leaf_index,
+            path: self.path().copied().collect(),
+            root: *self.root(),
+        }
+    }
+}
+
+impl<'a> From<OpeningRef<'a>> for Opening {
+    fn from(opening: OpeningRef<'a>) -> Self {
+        opening.to_opening()
+    }
+}
+
+impl<'a> MerkleTreeView<'a> {
+    /// Like `get_opening`, borrowing the path from the view's nodes.
+    pub fn get_opening_ref(&self, leaf_index: usize) -> Result<OpeningRef<'a>, MerkleTreeError> {
+        if leaf_index >= self.leaf_count {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Tree has {} leaves but index given was {}",
+                self.leaf_count, leaf_index
+            )));
+        }
+        Ok(OpeningRef {
+            leaf_index,
+            view: *self,
+        })
+    }
+}
+
+impl MerkleTree {
+    /// Like `get_opening`, borrowing the path from the tree's nodes.
+    pub fn get_opening_ref(&self, leaf_index: usize) -> Result<OpeningRef<'_>, MerkleTreeError> {
+        if !self.is_built() || !self.dirty_leaves.is_empty() {
+            return Err(MerkleTreeError::RootNotComputed(
+                "Tree has pending updates, call root() or flush() first".to_string(),
+            ));
+        }
+        self.view().get_opening_ref(leaf_index)
+    }
+}
+
+impl SharedMerkleTree {
+    /// Like `get_opening`, borrowing the path from the shared nodes.
+    pub fn get_opening_ref(&self, leaf_index: usize) -> Result<OpeningRef<'_>, MerkleTreeError> {
+        self.view().get_opening_ref(leaf_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeccakHasher;
+    use std::sync::Arc;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_opening_ref() {
+        let merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        for leaf_index in 0..SAMPLE.len() {
+            let opening = merkle_tree.get_opening_ref(leaf_index).unwrap();
+            assert_eq!(
+                opening.to_opening(),
+                merkle_tree.get_opening(leaf_index).unwrap()
+            );
+            assert!(opening.verify(merkle_tree.get_value(leaf_index).unwrap()));
+            assert!(opening
+                .path()
+                .all(|node| merkle_tree.nodes.iter().any(|n| std::ptr::eq(n, node))));
+        }
+        assert!(merkle_tree.get_opening_ref(SAMPLE.len()).is_err());
+
+        let mut unbuilt = MerkleTree::new(SAMPLE.len());
+        unbuilt.insert_batch(SAMPLE).unwrap();
+        assert!(unbuilt.get_opening_ref(0).is_err());
+    }
+
+    #[test]
+    fn test_opening_ref_with_hasher() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.set_hasher(Arc::new(KeccakHasher));
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+        let opening = merkle_tree.get_opening_ref(2).unwrap();
+        let leaf = merkle_tree.get_value(2).unwrap();
+        assert!(!opening.verify(leaf));
+        assert!(opening.verify_with(&KeccakHasher, leaf));
+    }
+}
@@ -0,0 +1,349 @@
+use crate::{MerkleTree, MerkleTreeError, Opening, TreeLayout, LEAF_PREFIX, NODE_PREFIX};
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+use solana_program::hash::{hashv, Hash};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Height of the subtrees the pipelined build works on, i.e. 16384 leaves per chunk.
+const PIPELINE_CHUNK_HEIGHT: usize = 14;
+/// Number of openings generated between cancellation checks.
+const OPENING_CHUNK_LEN: usize = 1 << 14;
+/// Chunk heights `verify_determinism` builds with besides `PIPELINE_CHUNK_HEIGHT`.
+const DETERMINISM_CHUNK_HEIGHTS: [usize; 3] = [0, 1, 4];
+
+/// Flag shared with a long-running parallel operation, checked between chunks so the
+/// operation can be aborted with `MerkleTreeError::Cancelled`.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every operation holding a clone of the token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn check(token: Option<&Self>) -> Result<(), MerkleTreeError> {
+        if token.map_or(false, Self::is_cancelled) {
+            return Err(MerkleTreeError::Cancelled(
+                "Cancellation was requested".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Hashes each pair of nodes in the level, duplicating the last node of an odd level.
+fn hash_level(level: &[Hash]) -> Vec<Hash> {
+    level
+        .par_chunks(2)
+        .map(|pair| {
+            let (lnode, rnode) = (&pair[0], pair.last().unwrap());
+            hash_node!(lnode, rnode)
+        })
+        .collect()
+}
+
+/// Builds the levels of a chunk up to its root at `chunk_height`, or up to the tree's root
+/// if the chunk holds every leaf.
+fn build_chunk_levels(
+    leaves: Vec<Hash>,
+    chunk_height: usize,
+    single_chunk: bool,
+) -> Vec<Vec<Hash>> {
+    let mut levels = vec![leaves];
+    while levels.len() <= chunk_height {
+        let level = levels.last().unwrap();
+        if single_chunk && level.len() <= 1 {
+            break;
+        }
+        levels.push(hash_level(level));
+    }
+    levels
+}
+
+impl MerkleTree {
+    /// Returns a parallel iterator over the leaf hashes.
+    pub fn par_leaves(&self) -> rayon::slice::Iter<'_, Hash> {
+        self.nodes[0..self.leaf_count].par_iter()
+    }
+
+    /// Returns a parallel iterator over the openings of every leaf, in leaf order.
+    pub fn par_openings(
+        &self,
+    ) -> impl IndexedParallelIterator<Item = Result<Opening, MerkleTreeError>> + '_ {
+        let layout = self.layout();
+        (0..self.leaf_count)
+            .into_par_iter()
+            .map(move |leaf_index| self.uncached_opening(&layout, leaf_index))
+    }
+
+    /// Like `get_opening`, computing the opening from the nodes without going through the
+    /// proof cache or the layout cache, whose locks would serialize the workers.
+    fn uncached_opening(
+        &self,
+        layout: &TreeLayout,
+        leaf_index: usize,
+    ) -> Result<Opening, MerkleTreeError> {
+        self.ensure_openable()?;
+        let opening = self.view().get_opening_in(layout, leaf_index)?;
+        if let Some(metrics) = self.metrics() {
+            metrics.opening_generated(false);
+        }
+        Ok(opening)
+    }
+
+    /// Like `from_leaves`, hashing in parallel on the global pool. The leaves are processed in
+    /// chunks, and hashing the leaves of one chunk overlaps with building the levels above
+    /// the previous one. The nodes are bit-identical to `from_leaves` whatever the thread
+    /// count, since every node only depends on its children and is written by position.
+    pub fn par_from_leaves<T: AsRef<[u8]> + Sync>(leaves: &[T]) -> Self {
+        match Self::par_from_leaves_chunked(leaves, PIPELINE_CHUNK_HEIGHT, None) {
+            Ok(merkle_tree) => merkle_tree,
+            Err(_) => unreachable!("builds without a token can't be cancelled"),
+        }
+    }
+
+    /// Like `par_from_leaves`, checking the token between chunks and levels and failing
+    /// with `Cancelled` once it is cancelled.
+    pub fn par_from_leaves_cancellable<T: AsRef<[u8]> + Sync>(
+        leaves: &[T],
+        token: &CancellationToken,
+    ) -> Result<Self, MerkleTreeError> {
+        Self::par_from_leaves_chunked(leaves, PIPELINE_CHUNK_HEIGHT, Some(token))
+    }
+
+    /// Pipelined build over chunks of `2^chunk_height` leaves. Chunks are aligned subtrees,
+    /// so each one's levels up to `chunk_height` can be built independently.
+    pub(crate) fn par_from_leaves_chunked<T: AsRef<[u8]> + Sync>(
+        leaves: &[T],
+        chunk_height: usize,
+        token: Option<&CancellationToken>,
+    ) -> Result<Self, MerkleTreeError> {
+        let leaf_count = leaves.len();
+        let chunks: Vec<&[T]> = leaves.chunks(1 << chunk_height).collect();
+        let single_chunk = chunks.len() == 1;
+        let hash_chunk =
+            |chunk: &[T]| -> Vec<Hash> { chunk.par_iter().map(|leaf| hash_leaf!(leaf)).collect() };
+
+        // Stage 1 hashes the leaves of chunk i + 1 while stage 2 builds the levels of chunk i
+        let mut subtrees: Vec<Vec<Vec<Hash>>> = Vec::with_capacity(chunks.len());
+        let mut hashed = chunks.first().map(|chunk| hash_chunk(chunk));
+        for i in 0..chunks.len() {
+            CancellationToken::check(token)?;
+            let chunk_leaves = hashed.take().unwrap();
+            let (next, subtree) = rayon::join(
+                || chunks.get(i + 1).map(|chunk| hash_chunk(chunk)),
+                || build_chunk_levels(chunk_leaves, chunk_height, single_chunk),
+            );
+            hashed = next;
+            subtrees.push(subtree);
+        }
+
+        let mut nodes = Vec::with_capacity(MerkleTree::calculate_max_capacity(leaf_count));
+        let chunk_levels = subtrees.first().map_or(0, Vec::len);
+        let mut level_len = 0;
+        for level in 0..chunk_levels {
+            let level_offset = nodes.len();
+            for subtree in &mut subtrees {
+                nodes.append(&mut subtree[level]);
+            }
+            level_len = nodes.len() - level_offset;
+        }
+
+        // Levels above the chunk roots
+        while level_len > 1 {
+            CancellationToken::check(token)?;
+            let level_offset = nodes.len() - level_len;
+            let next_level = hash_level(&nodes[level_offset..]);
+            level_len = next_level.len();
+            nodes.extend(next_level);
+        }
+
+        let mut merkle_tree = Self {
+            leaf_count,
+            nodes,
+            current_leaf_index: leaf_count,
+            ..Default::default()
+        };
+        if leaf_count > 0 {
+            merkle_tree.root_changed();
+        }
+        Ok(merkle_tree)
+    }
+
+    /// Collects the openings of every leaf in parallel, checking the token between chunks
+    /// of `OPENING_CHUNK_LEN` leaves.
+    pub fn par_openings_cancellable(
+        &self,
+        token: &CancellationToken,
+    ) -> Result<Vec<Opening>, MerkleTreeError> {
+        let layout = self.layout();
+        let mut openings = Vec::with_capacity(self.leaf_count);
+        for start in (0..self.leaf_count).step_by(OPENING_CHUNK_LEN) {
+            CancellationToken::check(Some(token))?;
+            let end = self.leaf_count.min(start + OPENING_CHUNK_LEN);
+            let chunk: Result<Vec<Opening>, MerkleTreeError> = (start..end)
+                .into_par_iter()
+                .map(|leaf_index| self.uncached_opening(&layout, leaf_index))
+                .collect();
+            openings.extend(chunk?);
+        }
+        Ok(openings)
+    }
+
+    /// Self-test that builds the leaves serially, then in parallel on one thread and on the
+    /// global pool with several chunk heights, and fails with `RootDivergence` at the first
+    /// node where any build differs. Returns the root otherwise.
+    pub fn verify_determinism<T: AsRef<[u8]> + Sync>(
+        leaves: &[T],
+    ) -> Result<Hash, MerkleTreeError> {
+        let serial = Self::from_leaves(leaves);
+        let single_thread = ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+
+        let mut chunk_heights = DETERMINISM_CHUNK_HEIGHTS.to_vec();
+        chunk_heights.push(PIPELINE_CHUNK_HEIGHT);
+        for chunk_height in chunk_heights {
+            let builds = [
+                (
+                    1,
+                    single_thread
+                        .install(|| Self::par_from_leaves_chunked(leaves, chunk_height, None))?,
+                ),
+                (
+                    rayon::current_num_threads(),
+                    Self::par_from_leaves_chunked(leaves, chunk_height, None)?,
+                ),
+            ];
+            for (threads, parallel) in builds {
+                if let Some(node_index) = (0..serial.nodes.len().max(parallel.nodes.len()))
+                    .find(|i| serial.nodes.get(*i) != parallel.nodes.get(*i))
+                {
+                    return Err(MerkleTreeError::RootDivergence(format!(
+                        "Parallel build on {} threads with chunk height {} diverges from the serial build at node {}",
+                        threads, chunk_height, node_index
+                    )));
+                }
+            }
+        }
+        Ok(serial.nodes.last().copied().unwrap_or_default())
+    }
+
+    /// Like `par_from_leaves`, running on the given pool instead of the global one.
+    pub fn par_from_leaves_in<T: AsRef<[u8]> + Sync>(pool: &ThreadPool, leaves: &[T]) -> Self {
+        pool.install(|| Self::par_from_leaves(leaves))
+    }
+
+    /// Verifies the openings in parallel against the leaves of the tree, in order.
+    /// Openings for leaves outside the tree don't verify.
+    pub fn par_verify_openings(&self, openings: &[Opening]) -> Vec<bool> {
+        openings
+            .par_iter()
+            .map(|opening| self.verify_opening(opening).unwrap_or(false))
+            .collect()
+    }
+
+    /// Like `par_verify_openings`, running on the given pool instead of the global one.
+    pub fn par_verify_openings_in(&self, pool: &ThreadPool, openings: &[Opening]) -> Vec<bool> {
+        pool.install(|| self.par_verify_openings(openings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_parallel_iterators() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+
+        let leaves: Vec<Hash> = merkle_tree.par_leaves().copied().collect();
+        assert_eq!(leaves, merkle_tree.nodes[0..SAMPLE.len()]);
+
+        let openings: Vec<Opening> = merkle_tree.par_openings().map(Result::unwrap).collect();
+        assert_eq!(openings.len(), SAMPLE.len());
+        for (leaf, opening) in leaves.iter().zip(&openings) {
+            assert!(opening.verify(leaf));
+        }
+    }
+
+    #[test]
+    fn test_parallel_build_in_pool() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let mut merkle_tree = MerkleTree::par_from_leaves_in(&pool, SAMPLE);
+        for leaf_count in 1..40 {
+            let leaves: Vec<[u8; 8]> = (0..leaf_count as u64).map(u64::to_le_bytes).collect();
+            for chunk_height in 0..4 {
+                assert_eq!(
+                    MerkleTree::par_from_leaves_chunked(&leaves, chunk_height, None)
+                        .unwrap()
+                        .nodes,
+                    MerkleTree::from_leaves(&leaves).nodes
+                );
+            }
+        }
+        assert_eq!(merkle_tree.nodes, MerkleTree::from_leaves(SAMPLE).nodes);
+        assert!(MerkleTree::par_from_leaves::<&[u8]>(&[]).nodes.is_empty());
+
+        merkle_tree.enable_proof_cache(SAMPLE.len());
+        let mut openings: Vec<Opening> = merkle_tree.par_openings().map(Result::unwrap).collect();
+        assert_eq!(merkle_tree.cached_openings(), 0);
+        assert_eq!(openings[3], merkle_tree.get_opening(3).unwrap());
+        openings[0].leaf_index = 1;
+        let verified = merkle_tree.par_verify_openings_in(&pool, &openings);
+        assert_eq!(verified, vec![false, true, true, true, true]);
+    }
+
+    #[test]
+    fn test_verify_determinism() {
+        let leaves: Vec<[u8; 8]> = (0..1000u64).map(u64::to_le_bytes).collect();
+        assert_eq!(
+            MerkleTree::verify_determinism(&leaves).unwrap(),
+            *MerkleTree::from_leaves(&leaves).get_root().unwrap()
+        );
+        assert_eq!(
+            MerkleTree::verify_determinism::<&[u8]>(&[]).unwrap(),
+            Hash::default()
+        );
+    }
+
+    #[test]
+    fn test_cancellable_build() {
+        let token = CancellationToken::new();
+        let mut merkle_tree = MerkleTree::par_from_leaves_cancellable(SAMPLE, &token).unwrap();
+        assert_eq!(
+            merkle_tree.par_openings_cancellable(&token).unwrap().len(),
+            SAMPLE.len()
+        );
+        assert_eq!(
+            merkle_tree.get_root(),
+            MerkleTree::from_leaves(SAMPLE).get_root()
+        );
+
+        token.clone().cancel();
+        assert!(matches!(
+            MerkleTree::par_from_leaves_cancellable(SAMPLE, &token),
+            Err(MerkleTreeError::Cancelled(_))
+        ));
+        assert!(merkle_tree.par_openings_cancellable(&token).is_err());
+    }
+}
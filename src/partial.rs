@@ -0,0 +1,268 @@
+use crate::{hasher::TreeHasher, HashDomain, MerkleHasher, MerkleTree, MerkleTreeError, Opening};
+use solana_program::hash::Hash;
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+/// A tree holding only the nodes needed to prove a subset of its leaves, for light clients
+/// that track a few leaves without downloading the full node vector. Nodes on the path of a
+/// proven leaf are recomputed on demand rather than stored.
+#[derive(Clone)]
+pub struct PartialTree {
+    pub leaf_count: usize,
+    pub root: Hash,
+    pub hash_domain: HashDomain,
+    hasher: Option<Arc<dyn MerkleHasher>>,
+    level_lens: Vec<usize>,
+    /// Stored nodes keyed by (level, position), with the leaves at level 0.
+    nodes: BTreeMap<(usize, usize), Hash>,
+}
+
+impl fmt::Debug for PartialTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PartialTree")
+            .field("leaf_count", &self.leaf_count)
+            .field("root", &self.root)
+            .field("hash_domain", &self.hash_domain)
+            .field("custom_hasher", &self.hasher.is_some())
+            .field("nodes", &self.nodes)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Partial trees are equal if they hold the same nodes under the same root, whichever
+/// hasher computed them.
+impl PartialEq for PartialTree {
+    fn eq(&self, other: &Self) -> bool {
+        self.leaf_count == other.leaf_count
+            && self.root == other.root
+            && self.hash_domain == other.hash_domain
+            && self.nodes == other.nodes
+    }
+}
+
+impl Eq for PartialTree {}
+
+impl PartialTree {
+    /// Creates a partial tree that knows only the root of a tree with the given number of leaves.
+    pub fn new(leaf_count: usize, root: Hash) -> Self {
+        let mut level_lens = vec![leaf_count];
+        let mut level_len = leaf_count;
+        while level_len > 1 {
+            level_len = MerkleTree::calculate_next_level_len(level_len);
+            level_lens.push(level_len);
+        }
+        Self {
+            leaf_count,
+            root,
+            hash_domain: HashDomain::default(),
+            hasher: None,
+            level_lens,
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    /// Hashes nodes with the given hasher instead of `hash_domain`, for trees built with
+    /// `MerkleTree::set_hasher`. Set it before adding any openings.
+    pub fn set_hasher(&mut self, hasher: Arc<dyn MerkleHasher>) {
+        self.hasher = Some(hasher);
+    }
+
+    fn tree_hasher(&self) -> TreeHasher {
+        TreeHasher::new(self.hash_domain, self.hasher.as_ref())
+    }
+
+    /// Verifies the opening against the root and adds the leaf, storing only the partner
+    /// nodes that can't be derived from leaves already present.
+    pub fn add_opening(&mut self, leaf: &Hash, opening: &Opening) -> Result<(), MerkleTreeError> {
+        let leaf_index = opening.leaf_index;
+        if leaf_index >= self.leaf_count {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Tree has {} leaves but index given was {}",
+                self.leaf_count, leaf_index
+            )));
+        }
+        if opening.path.len() + 1 != self.level_lens.len()
+            || opening.root != self.root
+            || !opening.verify_with(&self.tree_hasher(), leaf)
+        {
+            return Err(MerkleTreeError::ProofMismatch(format!(
+                "Opening for leaf {} doesn't compute to root {}",
+                leaf_index, self.root
+            )));
+        }
+
+        self.nodes.insert((0, leaf_index), *leaf);
+        for (level, partner) in opening.path.iter().enumerate() {
+            let position = leaf_index >> level;
+            // The new leaf makes its own ancestors derivable
+            if level > 0 {
+                self.nodes.remove(&(level, position));
+            }
+            let partner_position = position ^ 1;
+            if partner_position < self.level_lens[level]
+                && !self.has_proven_descendant(level, partner_position)
+            {
+                self.nodes.insert((level, partner_position), *partner);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the proven leaf at given index.
+    pub fn get_value(&self, leaf_index: usize) -> Option<&Hash> {
+        self.nodes.get(&(0, leaf_index))
+    }
+
+    /// Returns the indices of the proven leaves in ascending order.
+    pub fn proven_leaves(&self) -> impl Iterator<Item = usize> + '_ {
+        self.nodes
+            .range((0, 0)..(1, 0))
+            .map(|(&(_, leaf_index), _)| leaf_index)
+    }
+
+    /// Returns the number of stored nodes, leaves included.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the opening for a proven leaf.
+    pub fn get_opening(&self, leaf_index: usize) -> Result<Opening, MerkleTreeError> {
+        if self.get_value(leaf_index).is_none() {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Leaf {} isn't part of the partial tree",
+                leaf_index
+            )));
+        }
+        let mut path = Vec::with_capacity(self.level_lens.len() - 1);
+        for level in 0..self.level_lens.len() - 1 {
+            let position = leaf_index >> level;
+            let partner_position = if position ^ 1 < self.level_lens[level] {
+                position ^ 1
+            } else {
+                position
+            };
+            let partner = self.node(level, partner_position).ok_or_else(|| {
+                MerkleTreeError::ProofMismatch(format!(
+                    "Partial tree is missing node {} at level {}",
+                    partner_position, level
+                ))
+            })?;
+            path.push(partner);
+        }
+        Ok(Opening {
+            leaf_index,
+            path,
+            root: self.root,
+        })
+    }
+
+    /// Returns true if the stored nodes compute to the root.
+    pub fn verify(&self) -> bool {
+        self.node(self.level_lens.len() - 1, 0) == Some(self.root)
+    }
+
+    fn has_proven_descendant(&self, level: usize, position: usize) -> bool {
+        let first = position << level;
+        let last = ((position + 1) << level).min(self.leaf_count);
+        self.nodes.range((0, first)..(0, last)).next().is_some()
+    }
+
+    /// Returns the node at the given position, deriving it from its children if not stored.
+    fn node(&self, level: usize, position: usize) -> Option<Hash> {
+        if let Some(node) = self.nodes.get(&(level, position)) {
+            return Some(*node);
+        }
+        if level == 0 {
+            return None;
+        }
+        let lnode = self.node(level - 1, position * 2)?;
+        let rnode = if position * 2 + 1 < self.level_lens[level - 1] {
+            self.node(level - 1, position * 2 + 1)?
+        } else {
+            lnode
+        };
+        Some(self.tree_hasher().hash_node(&lnode, &rnode))
+    }
+}
+
+impl MerkleTree {
+    /// Returns a partial tree with only the nodes needed to prove the given leaves.
+    pub fn prune_to(&self, leaf_indices: &[usize]) -> Result<PartialTree, MerkleTreeError> {
+        if !self.is_built() {
+            return Err(MerkleTreeError::RootNotComputed(
+                "Tree must be built before it can be pruned".to_string(),
+            ));
+        }
+        let mut partial_tree = PartialTree::new(self.leaf_count, self.nodes[self.nodes.len() - 1]);
+        partial_tree.hash_domain = self.hash_domain;
+        partial_tree.hasher = self.hasher.clone();
+        for &leaf_index in leaf_indices {
+            let opening = self.get_opening(leaf_index)?;
+            partial_tree.add_opening(&self.nodes[leaf_index], &opening)?;
+        }
+        Ok(partial_tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeccakHasher;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_prune_and_expand() {
+        let merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        let mut partial_tree = merkle_tree.prune_to(&[0, 1]).unwrap();
+        assert!(partial_tree.verify());
+        assert_eq!(partial_tree.proven_leaves().collect::<Vec<_>>(), vec![0, 1]);
+        // Two leaves plus one partner on each of the two upper levels
+        assert_eq!(partial_tree.node_count(), 4);
+        assert_eq!(
+            partial_tree.get_opening(1).unwrap(),
+            merkle_tree.get_opening(1).unwrap()
+        );
+        assert!(partial_tree.get_opening(2).is_err());
+
+        partial_tree
+            .add_opening(&merkle_tree.nodes[4], &merkle_tree.get_opening(4).unwrap())
+            .unwrap();
+        assert!(partial_tree.verify());
+        assert_eq!(partial_tree.node_count(), 4);
+        for leaf_index in [0, 1, 4] {
+            assert_eq!(
+                partial_tree.get_opening(leaf_index).unwrap(),
+                merkle_tree.get_opening(leaf_index).unwrap()
+            );
+        }
+
+        let wrong_leaf = merkle_tree.nodes[3];
+        assert!(partial_tree
+            .add_opening(&wrong_leaf, &merkle_tree.get_opening(2).unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_prune_with_hasher() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.set_hasher(Arc::new(KeccakHasher));
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+
+        let mut partial_tree = merkle_tree.prune_to(&[0, 3]).unwrap();
+        assert!(partial_tree.verify());
+        assert_eq!(
+            partial_tree.get_opening(3).unwrap(),
+            merkle_tree.get_opening(3).unwrap()
+        );
+        partial_tree
+            .add_opening(&merkle_tree.nodes[4], &merkle_tree.get_opening(4).unwrap())
+            .unwrap();
+        assert!(partial_tree.verify());
+
+        let mut default_hashed = PartialTree::new(SAMPLE.len(), *merkle_tree.get_root().unwrap());
+        assert!(default_hashed
+            .add_opening(&merkle_tree.nodes[4], &merkle_tree.get_opening(4).unwrap())
+            .is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use crate::MerkleTree;
+use solana_program::hash::Hash;
+use std::{
+    fmt::{self, Write},
+    ops::Range,
+};
+
+/// Number of base58 characters of a hash shown when abbreviating it.
+const ABBREVIATED_HASH_LEN: usize = 8;
+/// Nodes shown per level by `pretty_print` before the rest are summarized.
+pub const PRETTY_PRINT_LEVEL_WIDTH: usize = 8;
+
+/// Returns the first few base58 characters of the hash.
+pub(crate) fn abbreviate_hash(hash: &Hash) -> String {
+    let mut hash = hash.to_string();
+    hash.truncate(ABBREVIATED_HASH_LEN);
+    hash
+}
+
+impl MerkleTree {
+    /// Ranges of `nodes` that are filled in, leaves first: every level once built, otherwise
+    /// just the leaves.
+    fn filled_levels(&self) -> Vec<Range<usize>> {
+        if self.is_built() {
+            self.layout().levels().to_vec()
+        } else {
+            let leaves = 0..self.nodes.len().min(self.leaf_count);
+            vec![leaves]
+        }
+    }
+
+    /// Prints the tree root first, one level per line, as `[index] hash` pairs with
+    /// abbreviated hashes. Levels wider than `PRETTY_PRINT_LEVEL_WIDTH` are cut short.
+    pub fn pretty_print(&self) -> String {
+        let levels = self.filled_levels();
+        let height = levels.len() - 1;
+        let mut out = String::new();
+        for (level, range) in levels.into_iter().enumerate().rev() {
+            let label = if level == height && self.is_built() {
+                "root".to_string()
+            } else {
+                format!("L{}", level)
+            };
+            // Writing to a String can't fail
+            let _ = write!(out, "{:>4}:", label);
+            let level_len = range.len();
+            for (index, node) in self.nodes[range]
+                .iter()
+                .take(PRETTY_PRINT_LEVEL_WIDTH)
+                .enumerate()
+            {
+                let _ = write!(out, " [{}] {}", index, abbreviate_hash(node));
+            }
+            if level_len > PRETTY_PRINT_LEVEL_WIDTH {
+                let _ = write!(out, " ... {} more", level_len - PRETTY_PRINT_LEVEL_WIDTH);
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Summarizes the entries left out past `PRETTY_PRINT_LEVEL_WIDTH`.
+struct Truncated(usize, &'static str);
+
+impl fmt::Debug for Truncated {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "… {} more {}", self.0, self.1)
+    }
+}
+
+/// Lists the filled levels root first with abbreviated hashes instead of dumping `nodes`.
+/// Like `pretty_print`, at most `PRETTY_PRINT_LEVEL_WIDTH` nodes are shown per level, and
+/// as many levels.
+impl fmt::Debug for MerkleTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Level<'a>(&'a [Hash]);
+
+        impl fmt::Debug for Level<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let mut map = f.debug_map();
+                map.entries(
+                    self.0
+                        .iter()
+                        .take(PRETTY_PRINT_LEVEL_WIDTH)
+                        .enumerate()
+                        .map(|(index, node)| (index, abbreviate_hash(node))),
+                );
+                if self.0.len() > PRETTY_PRINT_LEVEL_WIDTH {
+                    map.entry(
+                        &format_args!("…"),
+                        &Truncated(self.0.len() - PRETTY_PRINT_LEVEL_WIDTH, "nodes"),
+                    );
+                }
+                map.finish()
+            }
+        }
+
+        struct Levels<'a>(&'a MerkleTree);
+
+        impl fmt::Debug for Levels<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let levels = self.0.filled_levels();
+                let mut list = f.debug_list();
+                list.entries(
+                    levels
+                        .iter()
+                        .rev()
+                        .take(PRETTY_PRINT_LEVEL_WIDTH)
+                        .map(|range| Level(&self.0.nodes[range.clone()])),
+                );
+                if levels.len() > PRETTY_PRINT_LEVEL_WIDTH {
+                    list.entry(&Truncated(
+                        levels.len() - PRETTY_PRINT_LEVEL_WIDTH,
+                        "levels",
+                    ));
+                }
+                list.finish()
+            }
+        }
+
+        f.debug_struct("MerkleTree")
+            .field("leaf_count", &self.leaf_count)
+            .field("current_leaf_index", &self.current_leaf_index)
+            .field("lazy", &self.lazy)
+            .field("hash_domain", &self.hash_domain)
+            .field("built", &self.is_built())
+            .field("levels", &Levels(self))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_pretty_print() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        assert_eq!(merkle_tree.pretty_print().lines().count(), 1);
+        let _ = merkle_tree.get_root();
+
+        let printed = merkle_tree.pretty_print();
+        let lines: Vec<&str> = printed.lines().collect();
+        assert_eq!(lines.len(), 4);
+        let root = abbreviate_hash(merkle_tree.nodes.last().unwrap());
+        assert_eq!(lines[0], format!("root: [0] {}", root));
+        assert!(lines[3].starts_with("  L0: [0] "));
+        assert!(lines[3].contains("[4] "));
+
+        let debug = format!("{:?}", merkle_tree);
+        assert!(debug.contains(&root));
+        assert!(!debug.contains(&merkle_tree.nodes[0].to_string()));
+
+        let wide = MerkleTree::from_leaves(&[SAMPLE, SAMPLE].concat());
+        assert!(wide.pretty_print().ends_with(" ... 2 more\n"));
+
+        // 1000 leaves make 11 levels, the widest cut to 8 nodes
+        let leaves: Vec<[u8; 8]> = (0..1000u64).map(u64::to_le_bytes).collect();
+        let tall = MerkleTree::from_leaves(&leaves);
+        let debug = format!("{:?}", tall);
+        assert!(debug.contains(&abbreviate_hash(tall.nodes.last().unwrap())));
+        assert!(debug.contains("… 3 more levels"));
+        assert!(debug.contains("… 117 more nodes"));
+        assert!(!debug.contains(&abbreviate_hash(&tall.nodes[0])));
+    }
+}
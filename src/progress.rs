@@ -0,0 +1,132 @@
+use crate::{MerkleTree, LEAF_PREFIX};
+use solana_program::hash::hashv;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Number of leaves hashed between progress reports while building from raw leaves.
+pub const PROGRESS_REPORT_INTERVAL: usize = 1 << 16;
+
+/// Called with the state of a build as it progresses, e.g. to drive an `indicatif` bar.
+pub type ProgressFn = dyn Fn(&BuildProgress) + Send + Sync;
+
+/// How far a build has got. Nodes count both the leaves and the interior levels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BuildProgress {
+    pub leaf_count: usize,
+    pub leaves_hashed: usize,
+    /// Interior levels computed so far, out of `height`.
+    pub levels_completed: usize,
+    pub height: usize,
+    pub nodes_hashed: usize,
+    pub total_nodes: usize,
+    pub elapsed: Duration,
+}
+
+impl BuildProgress {
+    fn new(
+        leaf_count: usize,
+        leaves_hashed: usize,
+        levels_completed: usize,
+        started: Instant,
+    ) -> Self {
+        Self {
+            leaf_count,
+            leaves_hashed,
+            levels_completed,
+            height: MerkleTree::calculate_height(leaf_count),
+            nodes_hashed: leaves_hashed,
+            total_nodes: MerkleTree::calculate_max_capacity(leaf_count),
+            elapsed: started.elapsed(),
+        }
+    }
+
+    /// Estimates the time left from the rate nodes have been hashed at so far.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.nodes_hashed == 0 {
+            return None;
+        }
+        let remaining = self.total_nodes.saturating_sub(self.nodes_hashed);
+        Some(
+            self.elapsed
+                .mul_f64(remaining as f64 / self.nodes_hashed as f64),
+        )
+    }
+}
+
+impl MerkleTree {
+    /// Reports progress after every level of later builds.
+    pub fn set_progress(&mut self, progress: Arc<ProgressFn>) {
+        self.progress = Some(progress);
+    }
+
+    /// Like `from_leaves`, reporting progress every `PROGRESS_REPORT_INTERVAL` leaves hashed
+    /// and after every level. The returned tree keeps reporting on rebuilds.
+    pub fn from_leaves_with_progress<T: AsRef<[u8]>>(
+        leaves: &[T],
+        progress: Arc<ProgressFn>,
+    ) -> Self {
+        let started = Instant::now();
+        let leaf_count = leaves.len();
+        let mut nodes = Vec::with_capacity(MerkleTree::calculate_max_capacity(leaf_count));
+        for chunk in leaves.chunks(PROGRESS_REPORT_INTERVAL) {
+            nodes.extend(chunk.iter().map(|leaf| hash_leaf!(leaf)));
+            progress(&BuildProgress::new(leaf_count, nodes.len(), 0, started));
+        }
+
+        let mut merkle_tree = Self {
+            leaf_count,
+            nodes,
+            current_leaf_index: leaf_count,
+            progress: Some(progress),
+            ..Default::default()
+        };
+        let _ = merkle_tree.build(Some(started));
+        merkle_tree
+    }
+
+    /// Reports the build state to the progress callback, if any.
+    pub(crate) fn report_progress(&self, levels_completed: usize, started: Option<Instant>) {
+        if let (Some(progress), Some(started)) = (&self.progress, started) {
+            let mut state =
+                BuildProgress::new(self.leaf_count, self.leaf_count, levels_completed, started);
+            state.nodes_hashed = self.nodes.len();
+            progress(&state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_build_progress() {
+        let reports = Arc::new(Mutex::new(vec![]));
+        let recorder = reports.clone();
+        let mut merkle_tree = MerkleTree::from_leaves_with_progress(
+            SAMPLE,
+            Arc::new(move |progress: &BuildProgress| recorder.lock().unwrap().push(*progress)),
+        );
+        assert_eq!(merkle_tree, MerkleTree::from_leaves(SAMPLE));
+
+        let levels: Vec<(usize, usize)> = reports
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|progress| (progress.levels_completed, progress.nodes_hashed))
+            .collect();
+        assert_eq!(levels, vec![(0, 5), (1, 8), (2, 10), (3, 11)]);
+        let last = *reports.lock().unwrap().last().unwrap();
+        assert_eq!(last.height, 3);
+        assert_eq!(last.eta(), Some(Duration::ZERO));
+
+        reports.lock().unwrap().clear();
+        let _ = merkle_tree.get_root();
+        assert_eq!(reports.lock().unwrap().len(), 3);
+    }
+}
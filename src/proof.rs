@@ -0,0 +1,317 @@
+use crate::{
+    direction_bitmask, is_right_child, root::parse_hash, HashDomain, MerkleHasher, MerkleTreeError,
+    Opening,
+};
+use solana_program::hash::{Hash, HASH_BYTES};
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+/// Length of the `u8 depth || u32 index` header of a compact proof.
+pub const COMPACT_PROOF_HEADER_LEN: usize = 5;
+
+/// Why compact proof bytes were rejected.
+#[derive(Error, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofParseError {
+    #[error("proof is shorter than its header")]
+    MissingHeader,
+    #[error("proof is shorter than its declared depth")]
+    TruncatedPath,
+    #[error("proof has bytes past its declared depth")]
+    TrailingBytes,
+    #[error("leaf index doesn't fit the declared depth")]
+    IndexExceedsDepth,
+}
+
+impl From<ProofParseError> for MerkleTreeError {
+    fn from(err: ProofParseError) -> Self {
+        MerkleTreeError::InvalidProofEncoding(err.to_string())
+    }
+}
+
+/// A proof in the compact `u8 depth || u32 index (LE) || depth * 32 bytes` format, without
+/// the root it proves against.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleProof {
+    /// Index of the leaf, which doubles as the direction bitmask of the path.
+    pub leaf_index: u32,
+    /// Partner nodes from the leaf level up to (but excluding) the root.
+    pub path: Vec<Hash>,
+}
+
+impl MerkleProof {
+    /// Parses a proof, checking its length against the declared depth and that the leaf
+    /// index fits in a tree of that depth, before any of it gets hashed.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProofParseError> {
+        let (leaf_index, path_bytes) = split_compact_bytes(bytes)?;
+        Ok(Self {
+            leaf_index,
+            path: path_bytes.chunks_exact(HASH_BYTES).map(Hash::new).collect(),
+        })
+    }
+
+    /// Encodes the proof, failing if the path is deeper than a u8 can declare.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MerkleTreeError> {
+        let depth = u8::try_from(self.path.len()).map_err(|_| {
+            MerkleTreeError::InvalidProofEncoding(format!(
+                "Path of {} nodes exceeds the maximum depth of {}",
+                self.path.len(),
+                u8::MAX
+            ))
+        })?;
+        let mut bytes = Vec::with_capacity(COMPACT_PROOF_HEADER_LEN + self.path.len() * HASH_BYTES);
+        bytes.push(depth);
+        bytes.extend_from_slice(&self.leaf_index.to_le_bytes());
+        for node in &self.path {
+            bytes.extend_from_slice(node.as_ref());
+        }
+        Ok(bytes)
+    }
+
+    /// Hashes the leaf up the path and returns the index of the first of `roots` it computes
+    /// to, e.g. to accept proofs against any root in a recent window or across shards.
+    pub fn verify_any(&self, leaf: &Hash, roots: &[Hash]) -> Option<usize> {
+        self.verify_any_with(&HashDomain::Prefix, leaf, roots)
+    }
+
+    /// Like `verify_any`, for trees using the given hasher.
+    pub fn verify_any_with<H: MerkleHasher + ?Sized>(
+        &self,
+        hasher: &H,
+        leaf: &Hash,
+        roots: &[Hash],
+    ) -> Option<usize> {
+        let computed_root = fold_path(hasher, self.leaf_index, self.path.iter().copied(), leaf);
+        roots.iter().position(|root| *root == computed_root)
+    }
+
+    /// Attaches the root the proof is meant to verify against.
+    pub fn into_opening(self, root: Hash) -> Opening {
+        Opening {
+            leaf_index: self.leaf_index as usize,
+            path: self.path,
+            root,
+        }
+    }
+}
+
+impl TryFrom<&Opening> for MerkleProof {
+    type Error = MerkleTreeError;
+
+    fn try_from(opening: &Opening) -> Result<Self, Self::Error> {
+        let leaf_index = u32::try_from(opening.leaf_index).map_err(|_| {
+            MerkleTreeError::InvalidProofEncoding(format!(
+                "Leaf index {} doesn't fit in a u32",
+                opening.leaf_index
+            ))
+        })?;
+        Ok(Self {
+            leaf_index,
+            path: opening.path.clone(),
+        })
+    }
+}
+
+/// Prints as `<index>:<node>,<node>,...` with base58 nodes, or with `{:#}` as the index
+/// followed by one node per line.
+impl fmt::Display for MerkleProof {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "{}", self.leaf_index)?;
+            for node in &self.path {
+                write!(f, "\n{}", node)?;
+            }
+            return Ok(());
+        }
+        write!(f, "{}:", self.leaf_index)?;
+        for (level, node) in self.path.iter().enumerate() {
+            if level > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", node)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses either form printed by `Display`, with nodes in base58 or hex.
+impl FromStr for MerkleProof {
+    type Err = MerkleTreeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (leaf_index, path) = s
+            .split_once(|c: char| c == ':' || c.is_whitespace())
+            .unwrap_or((s, ""));
+        let leaf_index = u32::from_str(leaf_index.trim()).map_err(|err| {
+            MerkleTreeError::InvalidProofEncoding(format!(
+                "Invalid leaf index {:?}: {}",
+                leaf_index, err
+            ))
+        })?;
+        let path = path
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|node| !node.is_empty())
+            .map(parse_hash)
+            .collect::<Result<Vec<_>, _>>()?;
+        let depth = u8::try_from(path.len()).map_err(|_| {
+            MerkleTreeError::InvalidProofEncoding(format!(
+                "Path of {} nodes exceeds the maximum depth of {}",
+                path.len(),
+                u8::MAX
+            ))
+        })?;
+        if direction_bitmask(leaf_index, depth) != leaf_index {
+            return Err(ProofParseError::IndexExceedsDepth.into());
+        }
+        Ok(Self { leaf_index, path })
+    }
+}
+
+/// Checks a compact proof's length and leaf index against its declared depth and splits
+/// it into the leaf index and the path bytes.
+pub(crate) fn split_compact_bytes(bytes: &[u8]) -> Result<(u32, &[u8]), ProofParseError> {
+    if bytes.len() < COMPACT_PROOF_HEADER_LEN {
+        return Err(ProofParseError::MissingHeader);
+    }
+    let depth = bytes[0];
+    let path_bytes = &bytes[COMPACT_PROOF_HEADER_LEN..];
+    let path_len = depth as usize * HASH_BYTES;
+    if path_bytes.len() < path_len {
+        return Err(ProofParseError::TruncatedPath);
+    }
+    if path_bytes.len() > path_len {
+        return Err(ProofParseError::TrailingBytes);
+    }
+    let mut leaf_index = [0u8; 4];
+    leaf_index.copy_from_slice(&bytes[1..COMPACT_PROOF_HEADER_LEN]);
+    let leaf_index = u32::from_le_bytes(leaf_index);
+    if direction_bitmask(leaf_index, depth) != leaf_index {
+        return Err(ProofParseError::IndexExceedsDepth);
+    }
+    Ok((leaf_index, path_bytes))
+}
+
+/// Hashes the leaf up a path of compact proof bytes, taking sides from the bitmask.
+pub(crate) fn compute_root_from_bytes(
+    domain: HashDomain,
+    bitmask: u32,
+    path_bytes: &[u8],
+    leaf: &Hash,
+) -> Hash {
+    let path = path_bytes.chunks_exact(HASH_BYTES).map(Hash::new);
+    fold_path(&domain, bitmask, path, leaf)
+}
+
+/// Hashes the leaf up the path, taking sides from the bitmask.
+fn fold_path<H: MerkleHasher + ?Sized>(
+    hasher: &H,
+    bitmask: u32,
+    path: impl Iterator<Item = Hash>,
+    leaf: &Hash,
+) -> Hash {
+    let mut computed_root = *leaf;
+    for (level, partner) in path.enumerate() {
+        computed_root = if is_right_child(bitmask, level as u8) {
+            hasher.hash_node(&partner, &computed_root)
+        } else {
+            hasher.hash_node(&computed_root, &partner)
+        };
+    }
+    computed_root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleTree;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_strict_proof_parsing() {
+        let merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        let opening = merkle_tree.get_opening(4).unwrap();
+        let bytes = opening.to_compact_bytes().unwrap();
+        let proof = MerkleProof::from_bytes(&bytes).unwrap();
+        assert_eq!(proof.to_bytes().unwrap(), bytes);
+        assert_eq!(proof.into_opening(opening.root), opening);
+
+        assert_eq!(
+            MerkleProof::from_bytes(&bytes[..4]),
+            Err(ProofParseError::MissingHeader)
+        );
+        assert_eq!(
+            MerkleProof::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(ProofParseError::TruncatedPath)
+        );
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            MerkleProof::from_bytes(&trailing),
+            Err(ProofParseError::TrailingBytes)
+        );
+        let mut wide_index = bytes;
+        wide_index[1] = 8;
+        assert_eq!(
+            MerkleProof::from_bytes(&wide_index),
+            Err(ProofParseError::IndexExceedsDepth)
+        );
+    }
+
+    #[test]
+    fn test_proof_text_roundtrip() {
+        let merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        let proof = MerkleProof::try_from(&merkle_tree.get_opening(3).unwrap()).unwrap();
+        assert_eq!(MerkleProof::from_str(&proof.to_string()).unwrap(), proof);
+        let multi_line = format!("{:#}", proof);
+        assert_eq!(multi_line.lines().count(), 4);
+        assert_eq!(MerkleProof::from_str(&multi_line).unwrap(), proof);
+
+        let hex: Vec<String> = proof
+            .path
+            .iter()
+            .map(|node| format!("{:x}", crate::Root(*node)))
+            .collect();
+        let hex_proof = format!("3:{}", hex.join(","));
+        assert_eq!(MerkleProof::from_str(&hex_proof).unwrap(), proof);
+
+        assert!(MerkleProof::from_str("8:").is_err());
+        assert!(MerkleProof::from_str("x:").is_err());
+        assert!(MerkleProof::from_str("0:foo").is_err());
+    }
+
+    #[test]
+    fn test_verify_any() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(&SAMPLE[..4]).unwrap();
+        let old_root = *merkle_tree.get_root().unwrap();
+        let proof = MerkleProof::try_from(&merkle_tree.get_opening(3).unwrap()).unwrap();
+        let leaf = merkle_tree.nodes[3];
+        merkle_tree.insert(SAMPLE[4]).unwrap();
+        let new_root = *merkle_tree.get_root().unwrap();
+
+        let shard_root = *MerkleTree::from_leaves(&SAMPLE[..2]).nodes.last().unwrap();
+        assert_eq!(
+            proof.verify_any(&leaf, &[shard_root, new_root, old_root]),
+            Some(2)
+        );
+        assert_eq!(proof.verify_any(&leaf, &[shard_root, new_root]), None);
+        assert_eq!(proof.verify_any(&Hash::default(), &[old_root]), None);
+    }
+
+    #[test]
+    fn test_verify_any_with() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.hash_domain = HashDomain::Tagged;
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let root = *merkle_tree.get_root().unwrap();
+        let proof = MerkleProof::try_from(&merkle_tree.get_opening(4).unwrap()).unwrap();
+        let leaf = merkle_tree.nodes[4];
+
+        assert_eq!(proof.verify_any(&leaf, &[root]), None);
+        assert_eq!(
+            proof.verify_any_with(&HashDomain::Tagged, &leaf, &[Hash::default(), root]),
+            Some(1)
+        );
+    }
+}
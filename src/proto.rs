@@ -0,0 +1,196 @@
+//! Protobuf messages for proofs, roots and multiproofs, matching
+//! `proto/fast_merkle_tree.proto`, with conversions to and from the crate's types.
+use crate::{MerkleTreeError, Opening, Root as TreeRoot};
+use prost::Message;
+use solana_program::hash::{Hash, HASH_BYTES};
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Root {
+    #[prost(bytes = "vec", tag = "1")]
+    pub hash: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Proof {
+    #[prost(uint64, tag = "1")]
+    pub leaf_index: u64,
+    #[prost(bytes = "vec", repeated, tag = "2")]
+    pub path: Vec<Vec<u8>>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub root: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ProofPath {
+    #[prost(uint64, tag = "1")]
+    pub leaf_index: u64,
+    #[prost(bytes = "vec", repeated, tag = "2")]
+    pub path: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MultiProof {
+    #[prost(bytes = "vec", tag = "1")]
+    pub root: Vec<u8>,
+    #[prost(message, repeated, tag = "2")]
+    pub proofs: Vec<ProofPath>,
+}
+
+fn to_hash(bytes: &[u8]) -> Result<Hash, MerkleTreeError> {
+    if bytes.len() != HASH_BYTES {
+        return Err(MerkleTreeError::InvalidProofEncoding(format!(
+            "Hash of {} bytes, expected {}",
+            bytes.len(),
+            HASH_BYTES
+        )));
+    }
+    Ok(Hash::new(bytes))
+}
+
+fn to_leaf_index(leaf_index: u64) -> Result<usize, MerkleTreeError> {
+    usize::try_from(leaf_index).map_err(|_| {
+        MerkleTreeError::InvalidProofEncoding(format!(
+            "Leaf index {} doesn't fit in a usize",
+            leaf_index
+        ))
+    })
+}
+
+fn to_path(path: &[Vec<u8>]) -> Result<Vec<Hash>, MerkleTreeError> {
+    path.iter().map(|node| to_hash(node)).collect()
+}
+
+impl From<&TreeRoot> for Root {
+    fn from(root: &TreeRoot) -> Self {
+        Self {
+            hash: root.0.to_bytes().to_vec(),
+        }
+    }
+}
+
+impl TryFrom<&Root> for TreeRoot {
+    type Error = MerkleTreeError;
+
+    fn try_from(root: &Root) -> Result<Self, Self::Error> {
+        Ok(Self(to_hash(&root.hash)?))
+    }
+}
+
+impl From<&Opening> for Proof {
+    fn from(opening: &Opening) -> Self {
+        Self {
+            leaf_index: opening.leaf_index as u64,
+            path: opening
+                .path
+                .iter()
+                .map(|node| node.as_ref().to_vec())
+                .collect(),
+            root: opening.root.as_ref().to_vec(),
+        }
+    }
+}
+
+impl TryFrom<&Proof> for Opening {
+    type Error = MerkleTreeError;
+
+    fn try_from(proof: &Proof) -> Result<Self, Self::Error> {
+        Ok(Self {
+            leaf_index: to_leaf_index(proof.leaf_index)?,
+            path: to_path(&proof.path)?,
+            root: to_hash(&proof.root)?,
+        })
+    }
+}
+
+impl MultiProof {
+    /// Groups openings of the same tree, failing if their roots differ.
+    pub fn from_openings(openings: &[Opening]) -> Result<Self, MerkleTreeError> {
+        let root = openings
+            .first()
+            .map(|opening| opening.root)
+            .unwrap_or_default();
+        if let Some(opening) = openings.iter().find(|opening| opening.root != root) {
+            return Err(MerkleTreeError::InvalidProofEncoding(format!(
+                "Opening of leaf {} is for a different root",
+                opening.leaf_index
+            )));
+        }
+        Ok(Self {
+            root: root.as_ref().to_vec(),
+            proofs: openings
+                .iter()
+                .map(|opening| ProofPath {
+                    leaf_index: opening.leaf_index as u64,
+                    path: opening
+                        .path
+                        .iter()
+                        .map(|node| node.as_ref().to_vec())
+                        .collect(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Returns an opening per leaf, each against the shared root.
+    pub fn to_openings(&self) -> Result<Vec<Opening>, MerkleTreeError> {
+        let root = to_hash(&self.root)?;
+        self.proofs
+            .iter()
+            .map(|proof| {
+                Ok(Opening {
+                    leaf_index: to_leaf_index(proof.leaf_index)?,
+                    path: to_path(&proof.path)?,
+                    root,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Opening {
+    /// Encodes the opening as a protobuf `Proof`.
+    pub fn to_proto_bytes(&self) -> Vec<u8> {
+        Proof::from(self).encode_to_vec()
+    }
+
+    /// Decodes an opening from a protobuf `Proof`.
+    pub fn from_proto_bytes(bytes: &[u8]) -> Result<Self, MerkleTreeError> {
+        let proof = Proof::decode(bytes)
+            .map_err(|err| MerkleTreeError::InvalidProofEncoding(err.to_string()))?;
+        Self::try_from(&proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleTree;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_proto_roundtrip() {
+        let merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        let opening = merkle_tree.get_opening(2).unwrap();
+        let bytes = opening.to_proto_bytes();
+        assert_eq!(Opening::from_proto_bytes(&bytes).unwrap(), opening);
+        assert!(Opening::from_proto_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        let root = TreeRoot(opening.root);
+        let encoded = Root::from(&root).encode_to_vec();
+        let decoded = Root::decode(encoded.as_slice()).unwrap();
+        assert_eq!(TreeRoot::try_from(&decoded).unwrap(), root);
+
+        let openings: Vec<Opening> = (0..SAMPLE.len())
+            .map(|i| merkle_tree.get_opening(i).unwrap())
+            .collect();
+        let multiproof = MultiProof::from_openings(&openings).unwrap();
+        let decoded = MultiProof::decode(multiproof.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(decoded.to_openings().unwrap(), openings);
+
+        let other = MerkleTree::from_leaves(&SAMPLE[..2])
+            .get_opening(0)
+            .unwrap();
+        assert!(MultiProof::from_openings(&[opening, other]).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{HashDomain, MerkleHasher, MerkleTree, MerkleTreeError, Opening};
+use solana_program::hash::Hash;
+
+/// An `Opening` using plain byte arrays, for consumers that don't depend on solana-program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawOpening {
+    pub leaf_index: usize,
+    pub path: Vec<[u8; 32]>,
+    pub root: [u8; 32],
+}
+
+impl From<Opening> for RawOpening {
+    fn from(opening: Opening) -> Self {
+        Self {
+            leaf_index: opening.leaf_index,
+            path: opening.path.into_iter().map(Hash::to_bytes).collect(),
+            root: opening.root.to_bytes(),
+        }
+    }
+}
+
+impl From<RawOpening> for Opening {
+    fn from(opening: RawOpening) -> Self {
+        Self {
+            leaf_index: opening.leaf_index,
+            path: opening.path.into_iter().map(Hash::new_from_array).collect(),
+            root: Hash::new_from_array(opening.root),
+        }
+    }
+}
+
+impl RawOpening {
+    /// Hashes the leaf up the path and returns the resulting root.
+    pub fn compute_root(&self, leaf: &[u8; 32]) -> [u8; 32] {
+        self.compute_root_in(HashDomain::Prefix, leaf)
+    }
+
+    /// Like `compute_root`, for trees using the given domain separation.
+    pub fn compute_root_in(&self, domain: HashDomain, leaf: &[u8; 32]) -> [u8; 32] {
+        self.compute_root_with(&domain, leaf)
+    }
+
+    /// Like `compute_root`, for trees built with a custom hasher.
+    pub fn compute_root_with<H: MerkleHasher + ?Sized>(
+        &self,
+        hasher: &H,
+        leaf: &[u8; 32],
+    ) -> [u8; 32] {
+        let mut current_index = self.leaf_index;
+        let mut computed_root = Hash::new_from_array(*leaf);
+        for partner in &self.path {
+            let partner = Hash::new_from_array(*partner);
+            computed_root = if current_index % 2 == 0 {
+                hasher.hash_node(&computed_root, &partner)
+            } else {
+                hasher.hash_node(&partner, &computed_root)
+            };
+            current_index /= 2;
+        }
+        computed_root.to_bytes()
+    }
+
+    /// Returns true if the leaf hash computes to the root embedded in the opening.
+    pub fn verify(&self, leaf: &[u8; 32]) -> bool {
+        self.compute_root(leaf) == self.root
+    }
+
+    /// Like `verify`, for trees using the given domain separation.
+    pub fn verify_in(&self, domain: HashDomain, leaf: &[u8; 32]) -> bool {
+        self.compute_root_in(domain, leaf) == self.root
+    }
+
+    /// Like `verify`, for trees built with a custom hasher.
+    pub fn verify_with<H: MerkleHasher + ?Sized>(&self, hasher: &H, leaf: &[u8; 32]) -> bool {
+        self.compute_root_with(hasher, leaf) == self.root
+    }
+}
+
+impl MerkleTree {
+    /// Like `root`, returning the root as bytes.
+    pub fn root_bytes(&mut self) -> Option<[u8; 32]> {
+        self.root().map(|root| root.to_bytes())
+    }
+
+    /// Like `get_value`, returning the leaf hash as bytes.
+    pub fn get_value_bytes(&self, leaf_index: usize) -> Option<[u8; 32]> {
+        self.get_value(leaf_index).map(|leaf| leaf.to_bytes())
+    }
+
+    /// Like `get_opening`, returning the opening with byte arrays in place of hashes.
+    pub fn get_raw_opening(&self, leaf_index: usize) -> Result<RawOpening, MerkleTreeError> {
+        self.get_opening(leaf_index).map(RawOpening::from)
+    }
+
+    /// Like `verify_opening`, for a `RawOpening`.
+    pub fn verify_raw_opening(&self, opening: &RawOpening) -> Result<bool, MerkleTreeError> {
+        match self.get_value_bytes(opening.leaf_index) {
+            Some(leaf) => Ok(opening.verify_with(&self.tree_hasher(), &leaf)),
+            None => Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Tree has {} leaves but index given was {}",
+                self.leaf_count, opening.leaf_index
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeccakHasher;
+    use std::sync::Arc;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_raw_opening() {
+        let mut merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        let root = merkle_tree.root_bytes().unwrap();
+        let opening = merkle_tree.get_raw_opening(4).unwrap();
+        assert_eq!(opening.root, root);
+        assert!(opening.verify(&merkle_tree.get_value_bytes(4).unwrap()));
+        assert!(merkle_tree.verify_raw_opening(&opening).unwrap());
+        assert!(!opening.verify(&merkle_tree.get_value_bytes(3).unwrap()));
+        assert_eq!(Opening::from(opening), merkle_tree.get_opening(4).unwrap());
+    }
+
+    #[test]
+    fn test_raw_opening_with_hasher() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.set_hasher(Arc::new(KeccakHasher));
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+        let opening = merkle_tree.get_raw_opening(2).unwrap();
+        let leaf = merkle_tree.get_value_bytes(2).unwrap();
+        assert!(!opening.verify(&leaf));
+        assert!(opening.verify_with(&KeccakHasher, &leaf));
+        assert!(merkle_tree.verify_raw_opening(&opening).unwrap());
+    }
+}
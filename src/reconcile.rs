@@ -0,0 +1,134 @@
+use crate::{MerkleTree, MerkleTreeError};
+use solana_program::hash::Hash;
+
+/// Nodes of one level sent to the other replica, which compares them with its own.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReconcileMessage {
+    /// Leaf count of the sender's tree, which must match the receiver's.
+    pub leaf_count: usize,
+    /// Level of the nodes, with the leaves at level 0.
+    pub level: usize,
+    /// Positions within the level and the sender's hashes there.
+    pub nodes: Vec<(usize, Hash)>,
+}
+
+/// Outcome of comparing a `ReconcileMessage` with the local tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReconcileStep {
+    /// The children of the mismatching nodes, to send back to the other replica.
+    Reply(ReconcileMessage),
+    /// The indices of the leaves that differ between the replicas, in order.
+    Done(Vec<usize>),
+}
+
+impl MerkleTree {
+    /// Starts reconciling with a replica of the same size by sending it the root.
+    /// Fails with `RootNotComputed` if the tree is empty or hasn't been built.
+    pub fn reconcile_start(&self) -> Result<ReconcileMessage, MerkleTreeError> {
+        self.ensure_built()?;
+        let level = self.layout().level_count() - 1;
+        Ok(ReconcileMessage {
+            leaf_count: self.leaf_count,
+            level,
+            nodes: vec![(0, self.subtree_root(level, 0)?)],
+        })
+    }
+
+    /// Compares the received nodes with the local ones. Unless the nodes are leaves or all
+    /// match, replies with the local children of the mismatching nodes, so each round
+    /// descends one level and only into subtrees that differ.
+    pub fn reconcile_step(
+        &self,
+        message: &ReconcileMessage,
+    ) -> Result<ReconcileStep, MerkleTreeError> {
+        if message.leaf_count != self.leaf_count {
+            return Err(MerkleTreeError::ProofMismatch(format!(
+                "Replica has {} leaves but the local tree has {}",
+                message.leaf_count, self.leaf_count
+            )));
+        }
+        let mut mismatched = vec![];
+        for (index, node) in &message.nodes {
+            if self.subtree_root(message.level, *index)? != *node {
+                mismatched.push(*index);
+            }
+        }
+        if message.level == 0 || mismatched.is_empty() {
+            return Ok(ReconcileStep::Done(mismatched));
+        }
+
+        let level = message.level - 1;
+        let level_len = self.layout().levels()[level].len();
+        let mut nodes = Vec::with_capacity(mismatched.len() * 2);
+        for child in mismatched
+            .iter()
+            .flat_map(|index| [index * 2, index * 2 + 1])
+        {
+            if child < level_len {
+                nodes.push((child, self.subtree_root(level, child)?));
+            }
+        }
+        Ok(ReconcileStep::Reply(ReconcileMessage {
+            leaf_count: self.leaf_count,
+            level,
+            nodes,
+        }))
+    }
+
+    /// Runs the reconciliation protocol against an in-memory replica and returns the
+    /// indices of the leaves that differ.
+    pub fn diff_leaves(&self, other: &MerkleTree) -> Result<Vec<usize>, MerkleTreeError> {
+        let replicas = [other, self];
+        let mut message = self.reconcile_start()?;
+        let mut turn = 0;
+        loop {
+            match replicas[turn].reconcile_step(&message)? {
+                ReconcileStep::Reply(reply) => {
+                    message = reply;
+                    turn ^= 1;
+                }
+                ReconcileStep::Done(leaf_indices) => return Ok(leaf_indices),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_reconcile() {
+        let local = MerkleTree::from_leaves(SAMPLE);
+        let mut replica = local.clone();
+        assert_eq!(local.diff_leaves(&replica).unwrap(), Vec::<usize>::new());
+
+        replica.update_leaf(1, b"foo").unwrap();
+        replica.update_leaf(4, b"bar").unwrap();
+        assert_eq!(local.diff_leaves(&replica).unwrap(), vec![1, 4]);
+        assert_eq!(replica.diff_leaves(&local).unwrap(), vec![1, 4]);
+
+        // The root mismatch only sends the children of the root back
+        let ReconcileStep::Reply(reply) = replica
+            .reconcile_step(&local.reconcile_start().unwrap())
+            .unwrap()
+        else {
+            panic!("roots should differ");
+        };
+        assert_eq!(reply.nodes.len(), 2);
+        assert!(local
+            .diff_leaves(&MerkleTree::from_leaves(&SAMPLE[1..]))
+            .is_err());
+
+        let mut unbuilt = MerkleTree::new(SAMPLE.len());
+        unbuilt.insert_batch(SAMPLE).unwrap();
+        for tree in [MerkleTree::new(0), unbuilt] {
+            assert!(matches!(
+                tree.reconcile_start(),
+                Err(MerkleTreeError::RootNotComputed(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{MerkleTree, MerkleTreeError};
+use solana_program::hash::Hash;
+use std::ops::Range;
+
+impl MerkleTree {
+    /// Remembers the leaves overwritten by up to `capacity` of the most recent inserts so
+    /// they can be rolled back. A capacity of 0 (the default) disables the undo log.
+    pub fn keep_undo_history(&mut self, capacity: usize) {
+        self.undo_capacity = capacity;
+        while self.undo_log.len() > capacity {
+            self.undo_log.pop_front();
+        }
+    }
+
+    /// Returns the number of inserts that can currently be rolled back.
+    pub fn undo_history_len(&self) -> usize {
+        self.undo_log.len()
+    }
+
+    /// Undoes the last `n` inserts: restores the leaves they overwrote, rewinds
+    /// `current_leaf_index` and rehashes the affected paths (or marks them dirty if lazy).
+    pub fn rollback(&mut self, n: usize) -> Result<(), MerkleTreeError> {
+        if n > self.undo_log.len() {
+            return Err(MerkleTreeError::InsufficientUndoHistory(format!(
+                "Cannot roll back {} inserts, only {} are recorded",
+                n,
+                self.undo_log.len()
+            )));
+        }
+
+        let start = self.current_leaf_index - n;
+        let restored: Vec<(usize, Hash)> = self
+            .undo_log
+            .drain(self.undo_log.len() - n..)
+            .enumerate()
+            .map(|(i, previous)| (start + i, previous))
+            .collect();
+        self.set_leaf_hashes(&restored)?;
+        self.current_leaf_index = start;
+        self.forget_duplicates_from(start);
+        Ok(())
+    }
+
+    /// Saves the current value of the leaves about to be overwritten by an insert.
+    pub(crate) fn record_undo(&mut self, leaf_indices: Range<usize>) {
+        if self.undo_capacity == 0 {
+            return;
+        }
+        for leaf_index in leaf_indices {
+            if self.undo_log.len() == self.undo_capacity {
+                self.undo_log.pop_front();
+            }
+            self.undo_log.push_back(self.nodes[leaf_index]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_rollback() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.keep_undo_history(3);
+        merkle_tree.insert_batch(&SAMPLE[..2]).unwrap();
+        let _ = merkle_tree.get_root();
+        let nodes = merkle_tree.nodes.clone();
+
+        merkle_tree.insert_batch(&SAMPLE[2..]).unwrap();
+        assert_eq!(merkle_tree.undo_history_len(), 3);
+        assert!(merkle_tree.rollback(4).is_err());
+        merkle_tree.rollback(3).unwrap();
+        assert_eq!(merkle_tree.current_leaf_index, 2);
+        assert_eq!(merkle_tree.nodes, nodes);
+
+        assert_eq!(merkle_tree.insert_indexed(SAMPLE[2]).unwrap(), 2);
+        merkle_tree.rollback(1).unwrap();
+        assert_eq!(merkle_tree.nodes, nodes);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::MerkleTreeError;
+use solana_program::hash::{Hash, HASH_BYTES};
+use std::{fmt, str::FromStr};
+
+/// A Merkle Root that prints as base58 (or hex with `{:x}`) and parses from either.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Root(pub Hash);
+
+impl From<Hash> for Root {
+    fn from(hash: Hash) -> Self {
+        Self(hash)
+    }
+}
+
+impl From<Root> for Hash {
+    fn from(root: Root) -> Self {
+        root.0
+    }
+}
+
+impl fmt::Display for Root {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl fmt::LowerHex for Root {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+        for byte in self.0.as_ref() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Root {
+    type Err = MerkleTreeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_hash(s).map(Self)
+    }
+}
+
+/// Parses a hash given as 64 hex digits (optionally `0x` prefixed) or base58.
+pub(crate) fn parse_hash(s: &str) -> Result<Hash, MerkleTreeError> {
+    let s = s.trim();
+    let digits = s.strip_prefix("0x").unwrap_or(s);
+    if digits.len() == HASH_BYTES * 2 && digits.bytes().all(|c| c.is_ascii_hexdigit()) {
+        let mut bytes = [0u8; HASH_BYTES];
+        for (byte, pair) in bytes.iter_mut().zip(digits.as_bytes().chunks_exact(2)) {
+            // Both digits were checked above
+            let pair = std::str::from_utf8(pair).unwrap();
+            *byte = u8::from_str_radix(pair, 16).unwrap();
+        }
+        return Ok(Hash::new_from_array(bytes));
+    }
+    Hash::from_str(s).map_err(|err| {
+        MerkleTreeError::InvalidProofEncoding(format!("{:?} is neither hex nor base58: {}", s, err))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MerkleTree;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_root_text_roundtrip() {
+        let merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        let root = Root(*merkle_tree.nodes.last().unwrap());
+        assert_eq!(Root::from_str(&root.to_string()).unwrap(), root);
+        assert_eq!(Root::from_str(&format!("{:x}", root)).unwrap(), root);
+        assert_eq!(Root::from_str(&format!("{:#x}", root)).unwrap(), root);
+        assert_eq!(format!("{:x}", root).len(), 64);
+        assert!(Root::from_str("not a root").is_err());
+    }
+}
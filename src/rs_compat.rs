@@ -0,0 +1,234 @@
+use crate::MerkleTreeError;
+use solana_program::hash::{hash, hashv, Hash, HASH_BYTES};
+
+/// Tree laid out like `rs_merkle::MerkleTree<Sha256>`: leaves are plain SHA-256 hashes,
+/// parents hash their concatenated children without a prefix and the last node of an odd
+/// level moves up unhashed. Its proofs are byte-compatible with `rs_merkle::MerkleProof`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RsMerkleTree {
+    /// Levels from the leaves up to the root.
+    levels: Vec<Vec<Hash>>,
+}
+
+/// Sibling hashes of an `RsMerkleTree` multiproof, in the order `rs_merkle` serializes them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RsMerkleProof {
+    pub proof_hashes: Vec<Hash>,
+}
+
+fn hash_pair(lnode: &Hash, rnode: &Hash) -> Hash {
+    hashv(&[lnode.as_ref(), rnode.as_ref()])
+}
+
+impl RsMerkleTree {
+    /// Hashes each leaf with SHA-256 and builds the tree.
+    pub fn from_leaves<T: AsRef<[u8]>>(leaves: &[T]) -> Self {
+        Self::from_leaf_hashes(leaves.iter().map(|leaf| hash(leaf.as_ref())).collect())
+    }
+
+    /// Builds the tree over already hashed leaves.
+    pub fn from_leaf_hashes(leaves: Vec<Hash>) -> Self {
+        let mut levels = vec![leaves];
+        while let Some(level) = levels.last().filter(|level| level.len() > 1) {
+            let next_level = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [lnode, rnode] => hash_pair(lnode, rnode),
+                    _ => pair[0],
+                })
+                .collect();
+            levels.push(next_level);
+        }
+        Self { levels }
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.levels.first().map_or(0, Vec::len)
+    }
+
+    /// Returns the root, or `None` for an empty tree.
+    pub fn root(&self) -> Option<Hash> {
+        self.levels.last()?.first().copied()
+    }
+
+    /// Returns the proof for the given leaves, in any order.
+    pub fn proof(&self, leaf_indices: &[usize]) -> Result<RsMerkleProof, MerkleTreeError> {
+        let mut indices = leaf_indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        if let Some(leaf_index) = indices.last().filter(|i| **i >= self.leaf_count()) {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Tree has {} leaves but index given was {}",
+                self.leaf_count(),
+                leaf_index
+            )));
+        }
+
+        let mut proof_hashes = vec![];
+        for level in &self.levels {
+            for index in &indices {
+                let sibling = index ^ 1;
+                if indices.binary_search(&sibling).is_err() {
+                    proof_hashes.extend(level.get(sibling));
+                }
+            }
+            indices = indices.iter().map(|index| index / 2).collect();
+            indices.dedup();
+        }
+        Ok(RsMerkleProof { proof_hashes })
+    }
+}
+
+impl RsMerkleProof {
+    /// Serializes the proof as its hashes concatenated, like `rs_merkle::MerkleProof::to_bytes`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.proof_hashes
+            .iter()
+            .flat_map(|node| node.to_bytes())
+            .collect()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MerkleTreeError> {
+        if bytes.len() % HASH_BYTES != 0 {
+            return Err(MerkleTreeError::InvalidProofEncoding(format!(
+                "Proof length {} is not a multiple of {}",
+                bytes.len(),
+                HASH_BYTES
+            )));
+        }
+        Ok(Self {
+            proof_hashes: bytes.chunks(HASH_BYTES).map(Hash::new).collect(),
+        })
+    }
+
+    /// Computes the root of a tree of `leaf_count` leaves from the proven leaf hashes, failing
+    /// if the proof has too few or too many hashes.
+    pub fn root(
+        &self,
+        leaf_indices: &[usize],
+        leaf_hashes: &[Hash],
+        leaf_count: usize,
+    ) -> Result<Hash, MerkleTreeError> {
+        if leaf_indices.len() != leaf_hashes.len() || leaf_indices.is_empty() {
+            return Err(MerkleTreeError::ProofMismatch(format!(
+                "Got {} leaf indices for {} leaf hashes",
+                leaf_indices.len(),
+                leaf_hashes.len()
+            )));
+        }
+        let mut level: Vec<(usize, Hash)> = leaf_indices
+            .iter()
+            .copied()
+            .zip(leaf_hashes.iter().copied())
+            .collect();
+        level.sort_unstable_by_key(|(index, _)| *index);
+        if level.windows(2).any(|pair| pair[0].0 == pair[1].0)
+            || level[level.len() - 1].0 >= leaf_count
+        {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Leaf indices must be distinct and below {}",
+                leaf_count
+            )));
+        }
+
+        let not_enough_hashes =
+            || MerkleTreeError::ProofMismatch("Proof has too few hashes".to_string());
+        let mut proof_hashes = self.proof_hashes.iter();
+        let mut level_len = leaf_count;
+        while level_len > 1 {
+            let mut siblings = vec![];
+            for (index, _) in &level {
+                let sibling = index ^ 1;
+                if sibling < level_len && level.binary_search_by_key(&sibling, |(i, _)| *i).is_err()
+                {
+                    siblings.push((sibling, *proof_hashes.next().ok_or_else(not_enough_hashes)?));
+                }
+            }
+            level.append(&mut siblings);
+            level.sort_unstable_by_key(|(index, _)| *index);
+
+            let mut next_level = Vec::with_capacity(level.len() / 2 + 1);
+            let mut nodes = level.iter().peekable();
+            while let Some((index, node)) = nodes.next() {
+                let parent = match nodes.peek() {
+                    Some((sibling, rnode)) if *sibling == index + 1 && index % 2 == 0 => {
+                        nodes.next();
+                        hash_pair(node, rnode)
+                    }
+                    _ => *node,
+                };
+                next_level.push((index / 2, parent));
+            }
+            level = next_level;
+            level_len = (level_len + 1) / 2;
+        }
+
+        if proof_hashes.next().is_some() {
+            return Err(MerkleTreeError::ProofMismatch(
+                "Proof has more hashes than the leaves need".to_string(),
+            ));
+        }
+        Ok(level[0].1)
+    }
+
+    /// Returns true if the proven leaf hashes compute to the root.
+    pub fn verify(
+        &self,
+        root: &Hash,
+        leaf_indices: &[usize],
+        leaf_hashes: &[Hash],
+        leaf_count: usize,
+    ) -> bool {
+        self.root(leaf_indices, leaf_hashes, leaf_count)
+            .map_or(false, |computed_root| computed_root == *root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rs_merkle::{algorithms::Sha256, Hasher, MerkleProof, MerkleTree};
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_rs_merkle_compatibility() {
+        for leaf_count in 1..=SAMPLE.len() * 2 {
+            let leaves: Vec<Vec<u8>> = SAMPLE
+                .iter()
+                .cycle()
+                .take(leaf_count)
+                .map(|leaf| leaf.to_vec())
+                .collect();
+            let tree = RsMerkleTree::from_leaves(&leaves);
+            let leaf_hashes: Vec<[u8; 32]> = leaves.iter().map(|leaf| Sha256::hash(leaf)).collect();
+            let rs_tree = MerkleTree::<Sha256>::from_leaves(&leaf_hashes);
+            assert_eq!(tree.root().unwrap().to_bytes(), rs_tree.root().unwrap());
+
+            let subsets = [
+                vec![0],
+                vec![leaf_count - 1],
+                (0..leaf_count).step_by(3).collect(),
+            ];
+            for indices in subsets {
+                let proof = tree.proof(&indices).unwrap();
+                let proof_bytes = proof.to_bytes();
+                assert_eq!(proof_bytes, rs_tree.proof(&indices).to_bytes());
+
+                let proven: Vec<Hash> = indices
+                    .iter()
+                    .map(|i| Hash::new(&leaf_hashes[*i]))
+                    .collect();
+                let decoded = RsMerkleProof::from_bytes(&proof_bytes).unwrap();
+                assert!(decoded.verify(&tree.root().unwrap(), &indices, &proven, leaf_count));
+                let rs_proof = MerkleProof::<Sha256>::from_bytes(&proof_bytes).unwrap();
+                let rs_proven: Vec<[u8; 32]> = indices.iter().map(|i| leaf_hashes[*i]).collect();
+                assert!(rs_proof.verify(rs_tree.root().unwrap(), &indices, &rs_proven, leaf_count));
+            }
+        }
+        assert!(RsMerkleTree::from_leaves::<&[u8]>(&[]).root().is_none());
+        assert!(RsMerkleTree::from_leaves(SAMPLE)
+            .proof(&[SAMPLE.len()])
+            .is_err());
+    }
+}
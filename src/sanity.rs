@@ -0,0 +1,59 @@
+use crate::{MerkleHasher, MerkleTree};
+use solana_program::hash::Hash;
+
+impl MerkleTree {
+    /// Rebuilds every level from the leaves the straightforward way, pairing the last node
+    /// of an odd level with itself.
+    pub(crate) fn reference_nodes(&self) -> Vec<Hash> {
+        let hasher = self.tree_hasher();
+        let mut nodes = self.nodes[..self.leaf_count].to_vec();
+        let mut level = nodes.clone();
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| hasher.hash_node(&pair[0], pair.last().unwrap_or(&pair[0])))
+                .collect();
+            nodes.extend_from_slice(&level);
+        }
+        nodes
+    }
+
+    /// Panics if the nodes the optimized builders produced diverge from `reference_nodes`.
+    /// Runs after every build and path recomputation when the `sanity-check` feature is on.
+    pub(crate) fn check_against_reference(&self) {
+        let reference = self.reference_nodes();
+        if let Some(index) = (0..reference.len().max(self.nodes.len()))
+            .find(|index| self.nodes.get(*index) != reference.get(*index))
+        {
+            panic!(
+                "Tree of {} leaves diverges from the reference build at node {} of {}",
+                self.leaf_count,
+                index,
+                reference.len()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_reference_check() {
+        let mut merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        merkle_tree.update_leaf(3, b"foo").unwrap();
+        assert_eq!(merkle_tree.reference_nodes(), merkle_tree.nodes);
+    }
+
+    #[test]
+    #[should_panic(expected = "diverges from the reference build at node 5")]
+    fn test_reference_divergence() {
+        let mut merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        // Off the updated leaf's path, so only the reference build notices
+        merkle_tree.nodes[5] = Hash::default();
+        merkle_tree.update_leaf(4, b"foo").unwrap();
+    }
+}
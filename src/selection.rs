@@ -0,0 +1,95 @@
+use crate::{MerkleTree, MerkleTreeError, Opening};
+use solana_program::hash::{hashv, Hash};
+
+/// Domain tag of the hash leaf indices are derived from.
+pub const SELECTION_TAG: &[u8] = b"FastMerkleTree/select";
+
+/// What a verifier needs to recompute a selected leaf index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelectionProof {
+    pub seed: Vec<u8>,
+    /// Number of leaves selected from. Verifiers should check it against the population
+    /// they expect, since a smaller one changes the outcome.
+    pub population: usize,
+    pub root: Hash,
+}
+
+/// Derives an index below `population` from `SHA256(SELECTION_TAG || root || seed)`. The
+/// first 16 bytes are reduced as a u128, leaving a bias of at most `population / 2^128`.
+pub fn derive_selection(seed: &[u8], root: &Hash, population: usize) -> Option<usize> {
+    if population == 0 {
+        return None;
+    }
+    let digest = hashv(&[SELECTION_TAG, root.as_ref(), seed]).to_bytes();
+    let mut word = [0u8; 16];
+    word.copy_from_slice(&digest[..16]);
+    Some((u128::from_le_bytes(word) % population as u128) as usize)
+}
+
+impl SelectionProof {
+    /// Returns the index the seed selects.
+    pub fn selected_index(&self) -> Option<usize> {
+        derive_selection(&self.seed, &self.root, self.population)
+    }
+
+    /// Returns true if the opening is for the selected index and proves the leaf hash
+    /// under the same root.
+    pub fn verify(&self, opening: &Opening, leaf: &Hash) -> bool {
+        self.selected_index() == Some(opening.leaf_index)
+            && opening.root == self.root
+            && opening.verify(leaf)
+    }
+}
+
+impl MerkleTree {
+    /// Deterministically picks one of the inserted leaves from the seed, e.g. a slot hash,
+    /// and the root. Returns the index with its opening and the proof of the selection.
+    pub fn select_leaf(
+        &self,
+        seed: &[u8],
+    ) -> Result<(usize, Opening, SelectionProof), MerkleTreeError> {
+        let root = self.try_root()?;
+        let population = self.current_leaf_index;
+        let leaf_index = derive_selection(seed, &root, population).ok_or_else(|| {
+            MerkleTreeError::LeafIndexOutOfBounds("Tree has no leaves to select from".to_string())
+        })?;
+        let proof = SelectionProof {
+            seed: seed.to_vec(),
+            population,
+            root,
+        };
+        Ok((leaf_index, self.get_opening(leaf_index)?, proof))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_select_leaf() {
+        let mut merkle_tree = MerkleTree::new(8);
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+
+        let mut selected = [false; 5];
+        for slot in 0u64..64 {
+            let seed = slot.to_le_bytes();
+            let (leaf_index, opening, proof) = merkle_tree.select_leaf(&seed).unwrap();
+            assert_eq!(merkle_tree.select_leaf(&seed).unwrap().0, leaf_index);
+            assert!(proof.verify(&opening, &merkle_tree.nodes[leaf_index]));
+            selected[leaf_index] = true;
+
+            let other = merkle_tree
+                .get_opening((leaf_index + 1) % SAMPLE.len())
+                .unwrap();
+            assert!(!proof.verify(&other, &merkle_tree.nodes[other.leaf_index]));
+        }
+        assert_eq!(selected, [true; 5]);
+        assert!(MerkleTree::from_leaves::<&[u8]>(&[])
+            .select_leaf(b"seed")
+            .is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use crate::MerkleTree;
+use solana_program::hash::{Hash, HASH_BYTES};
+use zeroize::Zeroize;
+
+/// Overwrites the hashes with zeros in a way the compiler won't optimize away.
+fn zeroize_hashes(hashes: &mut [Hash]) {
+    // Safety: `Hash` is a repr(transparent) wrapper around `[u8; 32]`, so it has
+    // alignment 1 and every byte pattern is valid.
+    let bytes = unsafe {
+        std::slice::from_raw_parts_mut(hashes.as_mut_ptr() as *mut u8, hashes.len() * HASH_BYTES)
+    };
+    bytes.zeroize();
+}
+
+impl MerkleTree {
+    /// Wipes every leaf and node hash, the undo log, cached openings, the bloom filter, the
+    /// audit trace, recent and chained roots and the duplicate index, then resets the tree
+    /// to empty. The tree never holds leaf preimages, but hashes of low-entropy data can be
+    /// brute-forced, so wipe them before the memory is released. Audit mode and the
+    /// duplicate policy stay enabled; root chaining is turned off.
+    pub fn clear_sensitive(&mut self) {
+        zeroize_hashes(&mut self.nodes);
+        zeroize_hashes(self.undo_log.make_contiguous());
+        self.undo_log.clear();
+        self.invalidate_proof_cache();
+        if let Some(bloom) = self.bloom.as_mut() {
+            bloom.zeroize();
+        }
+        self.bloom = None;
+        self.dirty_leaves.clear();
+        for recent in self.root_history.iter_mut() {
+            zeroize_hashes(std::slice::from_mut(&mut recent.root));
+        }
+        self.root_history.clear();
+        if let Some(audit_trace) = self.audit_trace.as_mut() {
+            zeroize_hashes(&mut audit_trace.leaves);
+            audit_trace.leaves.clear();
+            for step in audit_trace.steps.iter_mut() {
+                for hash in [&mut step.left, &mut step.right, &mut step.output] {
+                    zeroize_hashes(std::slice::from_mut(hash));
+                }
+            }
+            audit_trace.steps.clear();
+        }
+        if let Some(root_chain) = self.root_chain.as_mut() {
+            zeroize_hashes(std::slice::from_mut(&mut root_chain.chained_root));
+            if let Some(last_root) = root_chain.last_root.as_mut() {
+                zeroize_hashes(std::slice::from_mut(last_root));
+            }
+        }
+        self.root_chain = None;
+        // Keys can't be wiped in place, so the index is dropped and left empty
+        self.forget_duplicates_from(0);
+
+        let leaf_count = self.leaf_count;
+        self.nodes = MerkleTree::new(leaf_count).nodes;
+        self.current_leaf_index = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DuplicatePolicy;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_clear_sensitive() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.keep_undo_history(2);
+        merkle_tree.keep_recent_roots(2);
+        merkle_tree.enable_bloom_filter(SAMPLE.len(), 0.01);
+        merkle_tree.enable_audit();
+        merkle_tree.enable_root_chaining(Hash::default());
+        merkle_tree.set_duplicate_policy(DuplicatePolicy::Dedupe);
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+        let leaves: Vec<Hash> = merkle_tree.nodes[..SAMPLE.len()].to_vec();
+
+        merkle_tree.clear_sensitive();
+        assert_eq!(merkle_tree.current_leaf_index, 0);
+        assert_eq!(merkle_tree.undo_history_len(), 0);
+        assert!(merkle_tree.recent_roots().next().is_none());
+        assert_eq!(merkle_tree.nodes, MerkleTree::new(SAMPLE.len()).nodes);
+        let audit_trace = merkle_tree.audit_trace().unwrap();
+        assert!(audit_trace.leaves.is_empty() && audit_trace.steps.is_empty());
+        assert_eq!(merkle_tree.chained_root(), None);
+        assert_eq!(merkle_tree.positions_of(&leaves), vec![None; SAMPLE.len()]);
+        assert_eq!(merkle_tree.duplicate_policy(), DuplicatePolicy::Dedupe);
+
+        let mut hashes = vec![Hash::new_unique(); 2];
+        zeroize_hashes(&mut hashes);
+        assert_eq!(hashes, vec![Hash::default(); 2]);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{MerkleTree, MerkleTreeError, Opening, NODE_PREFIX};
+use solana_program::hash::{hashv, Hash};
+
+/// Root of one shard, which is all the combiner needs from the machine that built it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShardRoot {
+    pub shard_index: usize,
+    /// Every shard but the last holds exactly `2^shard_height` leaves.
+    pub shard_height: usize,
+    pub leaf_count: usize,
+    pub root: Hash,
+}
+
+/// A shard built on its own machine: the tree over its leaf range.
+#[derive(Debug)]
+pub struct ShardOutput {
+    pub shard_index: usize,
+    pub shard_height: usize,
+    pub tree: MerkleTree,
+}
+
+impl ShardOutput {
+    /// Returns the summary to send to the combiner.
+    pub fn summary(&self) -> ShardRoot {
+        ShardRoot {
+            shard_index: self.shard_index,
+            shard_height: self.shard_height,
+            leaf_count: self.tree.leaf_count,
+            root: self.tree.nodes[self.tree.nodes.len() - 1],
+        }
+    }
+}
+
+/// The levels above the shard roots, joining every shard under the final root.
+#[derive(Debug)]
+pub struct CombinedShards {
+    pub shard_height: usize,
+    pub shard_count: usize,
+    /// Tree whose leaves are the shard roots, raised to `shard_height` where a short last
+    /// shard is lower.
+    pub upper: MerkleTree,
+}
+
+impl MerkleTree {
+    /// Builds the shard holding leaves `shard_index * 2^shard_height ..` of the full tree.
+    /// Shards are aligned subtrees, so they can be built independently and combined.
+    pub fn build_shard<T: AsRef<[u8]>>(
+        leaves: &[T],
+        shard_index: usize,
+        shard_height: usize,
+    ) -> Result<ShardOutput, MerkleTreeError> {
+        if leaves.is_empty() || leaves.len() > 1 << shard_height {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Shard of height {} holds 1 to {} leaves but {} were given",
+                shard_height,
+                1usize << shard_height,
+                leaves.len()
+            )));
+        }
+        Ok(ShardOutput {
+            shard_index,
+            shard_height,
+            tree: MerkleTree::from_leaves(leaves),
+        })
+    }
+}
+
+/// Number of levels above the leaves of a tree with the given leaf count.
+fn tree_height(leaf_count: usize) -> usize {
+    let mut height = 0;
+    let mut level_len = leaf_count;
+    while level_len > 1 {
+        level_len = MerkleTree::calculate_next_level_len(level_len);
+        height += 1;
+    }
+    height
+}
+
+impl CombinedShards {
+    /// Joins the shard roots, which must cover shards `0..n` with the same height and only
+    /// the last one partially filled.
+    pub fn combine(shard_roots: &[ShardRoot]) -> Result<Self, MerkleTreeError> {
+        let Some(first) = shard_roots.first() else {
+            return Err(MerkleTreeError::InvalidShard(
+                "No shards to combine".to_string(),
+            ));
+        };
+        let shard_height = first.shard_height;
+        let shard_count = shard_roots.len();
+        for (i, shard) in shard_roots.iter().enumerate() {
+            let full = shard.leaf_count == 1 << shard_height;
+            if shard.shard_index != i
+                || shard.shard_height != shard_height
+                || (!full && i + 1 != shard_count)
+            {
+                return Err(MerkleTreeError::InvalidShard(format!(
+                    "Shard {} doesn't fit after {} aligned shards of height {}",
+                    shard.shard_index, i, shard_height
+                )));
+            }
+        }
+        let upper_leaves = shard_roots
+            .iter()
+            .map(|shard| {
+                if shard_count == 1 {
+                    shard.root
+                } else {
+                    padding(shard.root, tree_height(shard.leaf_count), shard_height)
+                        .last()
+                        .copied()
+                        .unwrap_or(shard.root)
+                }
+            })
+            .collect();
+        Ok(Self {
+            shard_height,
+            shard_count,
+            upper: MerkleTree::from_leaf_hashes(upper_leaves),
+        })
+    }
+
+    /// Returns the root of the full tree.
+    pub fn root(&self) -> &Hash {
+        &self.upper.nodes[self.upper.nodes.len() - 1]
+    }
+
+    /// Returns the index in the full tree of the shard's first leaf.
+    pub fn proof_offset(&self, shard_index: usize) -> usize {
+        shard_index << self.shard_height
+    }
+
+    /// Returns the opening in the full tree of a leaf of the given shard.
+    pub fn get_opening(
+        &self,
+        shard: &ShardOutput,
+        local_index: usize,
+    ) -> Result<Opening, MerkleTreeError> {
+        let local = shard.tree.get_opening(local_index)?;
+        let upper = self.upper.get_opening(shard.shard_index)?;
+        let mut path = local.path;
+        let local_height = tree_height(shard.tree.leaf_count);
+        if self.shard_count > 1 && local_height < self.shard_height {
+            // A short last shard is hashed with itself until it reaches the shard height
+            let mut pad = padding(local.root, local_height, self.shard_height);
+            pad.pop();
+            path.push(local.root);
+            path.extend(pad);
+        }
+        path.extend(upper.path);
+        Ok(Opening {
+            leaf_index: self.proof_offset(shard.shard_index) + local_index,
+            path,
+            root: upper.root,
+        })
+    }
+}
+
+/// Returns the nodes above `root` when it's hashed with itself from `from_height` up to
+/// `to_height`, the last one being the raised root.
+fn padding(root: Hash, from_height: usize, to_height: usize) -> Vec<Hash> {
+    let mut node = root;
+    (from_height..to_height)
+        .map(|_| {
+            node = hash_node!(node, node);
+            node
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_build() {
+        for leaf_count in [1, 3, 4, 9, 11, 16] {
+            let leaves: Vec<[u8; 8]> = (0..leaf_count as u64).map(u64::to_le_bytes).collect();
+            let full_tree = MerkleTree::from_leaves(&leaves);
+
+            let shards: Vec<ShardOutput> = leaves
+                .chunks(4)
+                .enumerate()
+                .map(|(i, chunk)| MerkleTree::build_shard(chunk, i, 2).unwrap())
+                .collect();
+            let summaries: Vec<ShardRoot> = shards.iter().map(ShardOutput::summary).collect();
+            let combined = CombinedShards::combine(&summaries).unwrap();
+            assert_eq!(Some(combined.root()), full_tree.nodes.last());
+
+            for shard in &shards {
+                for local_index in 0..shard.tree.leaf_count {
+                    let opening = combined.get_opening(shard, local_index).unwrap();
+                    assert_eq!(opening, full_tree.get_opening(opening.leaf_index).unwrap());
+                }
+            }
+        }
+
+        assert!(MerkleTree::build_shard(&[b"a"; 5], 0, 2).is_err());
+        let short = MerkleTree::build_shard(&[b"a"; 3], 0, 2).unwrap().summary();
+        let full = MerkleTree::build_shard(&[b"a"; 4], 1, 2).unwrap().summary();
+        assert!(CombinedShards::combine(&[short, full]).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{HashDomain, MerkleHasher, MerkleTree, MerkleTreeError, MerkleTreeView, Opening};
+use solana_program::hash::Hash;
+use std::{fmt, sync::Arc};
+
+/// A finalized tree whose nodes live in shared storage, so clones are O(1) and can be
+/// handed to other threads. Mutations copy the nodes and return a new tree.
+#[derive(Clone)]
+pub struct SharedMerkleTree {
+    pub leaf_count: usize,
+    pub current_leaf_index: usize,
+    pub hash_domain: HashDomain,
+    /// Custom hasher of the tree it was made from, kept for the trees derived from it.
+    hasher: Option<Arc<dyn MerkleHasher>>,
+    nodes: Arc<[Hash]>,
+}
+
+impl fmt::Debug for SharedMerkleTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedMerkleTree")
+            .field("leaf_count", &self.leaf_count)
+            .field("current_leaf_index", &self.current_leaf_index)
+            .field("hash_domain", &self.hash_domain)
+            .field("custom_hasher", &self.hasher.is_some())
+            .field("root", &self.root())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Shared trees are equal if they hold the same nodes. The hasher isn't compared, any
+/// difference in it shows in the nodes.
+impl PartialEq for SharedMerkleTree {
+    fn eq(&self, other: &Self) -> bool {
+        self.leaf_count == other.leaf_count
+            && self.current_leaf_index == other.current_leaf_index
+            && self.hash_domain == other.hash_domain
+            && self.nodes == other.nodes
+    }
+}
+
+impl Eq for SharedMerkleTree {}
+
+impl From<MerkleTree> for SharedMerkleTree {
+    /// Finalizes the tree, building its root if needed.
+    fn from(mut merkle_tree: MerkleTree) -> Self {
+        let _ = merkle_tree.root();
+        Self {
+            leaf_count: merkle_tree.leaf_count,
+            current_leaf_index: merkle_tree.current_leaf_index,
+            hash_domain: merkle_tree.hash_domain,
+            hasher: merkle_tree.hasher,
+            nodes: merkle_tree.nodes.into(),
+        }
+    }
+}
+
+impl SharedMerkleTree {
+    /// Finalizes the tree and copies its nodes into shared storage, keeping the tree usable.
+    pub(crate) fn copy_from(merkle_tree: &mut MerkleTree) -> Self {
+        let _ = merkle_tree.root();
+        Self {
+            leaf_count: merkle_tree.leaf_count,
+            current_leaf_index: merkle_tree.current_leaf_index,
+            hash_domain: merkle_tree.hash_domain,
+            hasher: merkle_tree.hasher.clone(),
+            nodes: merkle_tree.nodes.as_slice().into(),
+        }
+    }
+
+    /// Borrows the shared nodes as a read-only view.
+    pub fn view(&self) -> MerkleTreeView<'_> {
+        MerkleTreeView {
+            leaf_count: self.leaf_count,
+            nodes: &self.nodes,
+        }
+    }
+
+    pub fn nodes(&self) -> &[Hash] {
+        &self.nodes
+    }
+
+    /// Returns the Merkle Root of the tree.
+    pub fn root(&self) -> Option<&Hash> {
+        self.nodes.last()
+    }
+
+    /// Returns the leaf at given index.
+    pub fn get_value(&self, leaf_index: usize) -> Option<&Hash> {
+        self.nodes[0..self.leaf_count].get(leaf_index)
+    }
+
+    /// Returns the opening for the leaf at given index.
+    pub fn get_opening(&self, leaf_index: usize) -> Result<Opening, MerkleTreeError> {
+        self.view().get_opening(leaf_index)
+    }
+
+    /// Copies the nodes into an owned, mutable tree.
+    pub fn to_merkle_tree(&self) -> MerkleTree {
+        MerkleTree {
+            leaf_count: self.leaf_count,
+            nodes: self.nodes.to_vec(),
+            current_leaf_index: self.current_leaf_index,
+            hash_domain: self.hash_domain,
+            hasher: self.hasher.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns a new tree with the leaves replaced, leaving this one untouched.
+    pub fn with_updates<T: AsRef<[u8]>>(
+        &self,
+        updates: &[(usize, T)],
+    ) -> Result<Self, MerkleTreeError> {
+        let mut merkle_tree = self.to_merkle_tree();
+        merkle_tree.apply_updates(updates)?;
+        Ok(merkle_tree.into())
+    }
+
+    /// Returns a new tree with the leaves appended, leaving this one untouched.
+    pub fn with_inserted<T: AsRef<[u8]>>(&self, leaves: &[T]) -> Result<Self, MerkleTreeError> {
+        let mut merkle_tree = self.to_merkle_tree();
+        merkle_tree.insert_batch(leaves)?;
+        Ok(merkle_tree.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeccakHasher;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_shared_tree() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(&SAMPLE[..3]).unwrap();
+        let shared = SharedMerkleTree::from(merkle_tree);
+        let clone = shared.clone();
+        assert!(std::ptr::eq(shared.nodes(), clone.nodes()));
+
+        let appended = shared.with_inserted(&SAMPLE[3..]).unwrap();
+        assert_eq!(
+            appended.root(),
+            MerkleTree::from_leaves(SAMPLE).nodes.last()
+        );
+        assert_eq!(shared, clone);
+
+        let updated = appended.with_updates(&[(0, b"foo")]).unwrap();
+        assert_ne!(updated.root(), appended.root());
+        let opening = updated.get_opening(0).unwrap();
+        assert!(opening.verify(updated.get_value(0).unwrap()));
+
+        let handle = std::thread::spawn(move || clone.get_opening(1).unwrap());
+        assert!(handle.join().unwrap().verify(shared.get_value(1).unwrap()));
+    }
+
+    #[test]
+    fn test_shared_tree_keeps_hasher() {
+        let hasher: Arc<dyn MerkleHasher> = Arc::new(KeccakHasher);
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.set_hasher(hasher.clone());
+        merkle_tree.insert_batch(&SAMPLE[..3]).unwrap();
+        let mut expected = MerkleTree::new(SAMPLE.len());
+        expected.set_hasher(hasher);
+        expected.insert_batch(SAMPLE).unwrap();
+
+        let copied = SharedMerkleTree::copy_from(&mut merkle_tree);
+        let shared = SharedMerkleTree::from(merkle_tree);
+        assert_eq!(copied, shared);
+        let appended = shared.with_inserted(&SAMPLE[3..]).unwrap();
+        assert_eq!(appended.root(), expected.get_root());
+        let opening = appended.get_opening(4).unwrap();
+        assert!(opening.verify_with(&KeccakHasher, appended.get_value(4).unwrap()));
+        assert_eq!(
+            appended.to_merkle_tree().hash_leaf(SAMPLE[0]),
+            KeccakHasher.hash_leaf(SAMPLE[0])
+        );
+    }
+}
@@ -0,0 +1,319 @@
+use crate::{HashDomain, MerkleTree, MerkleTreeError, DEFAULT_MAX_LEAF_COUNT};
+use solana_program::hash::{hashv, Hash, HASH_BYTES};
+use std::io::{Read, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+};
+
+/// Identifies a serialized snapshot of a tree.
+pub const SNAPSHOT_MAGIC: &[u8; 4] = b"FMTS";
+/// Size of the snapshot header preceding the nodes.
+pub const SNAPSHOT_HEADER_LEN: usize = 4 + 8 + 8 + 8 + 1 + HASH_BYTES + HASH_BYTES;
+#[cfg(all(feature = "zstd", not(target_arch = "wasm32")))]
+const ZSTD_LEVEL: i32 = 3;
+
+/// Fixed-size header of a snapshot.
+/// Layout: magic || leaf_count || current_leaf_index || node_count (u64 LE) || hash_domain (u8)
+/// || root || checksum
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SnapshotHeader {
+    pub leaf_count: usize,
+    pub current_leaf_index: usize,
+    pub node_count: usize,
+    /// Domain the nodes were hashed in.
+    pub hash_domain: HashDomain,
+    /// Last node of the snapshot, which is the root once the tree is built.
+    pub root: Hash,
+}
+
+impl SnapshotHeader {
+    fn checksum(&self) -> Hash {
+        hashv(&[
+            SNAPSHOT_MAGIC,
+            &(self.leaf_count as u64).to_le_bytes(),
+            &(self.current_leaf_index as u64).to_le_bytes(),
+            &(self.node_count as u64).to_le_bytes(),
+            &[domain_to_byte(self.hash_domain)],
+            self.root.as_ref(),
+        ])
+    }
+
+    pub fn to_bytes(&self) -> [u8; SNAPSHOT_HEADER_LEN] {
+        let mut bytes = [0u8; SNAPSHOT_HEADER_LEN];
+        bytes[0..4].copy_from_slice(SNAPSHOT_MAGIC);
+        bytes[4..12].copy_from_slice(&(self.leaf_count as u64).to_le_bytes());
+        bytes[12..20].copy_from_slice(&(self.current_leaf_index as u64).to_le_bytes());
+        bytes[20..28].copy_from_slice(&(self.node_count as u64).to_le_bytes());
+        bytes[28] = domain_to_byte(self.hash_domain);
+        bytes[29..61].copy_from_slice(self.root.as_ref());
+        bytes[61..93].copy_from_slice(self.checksum().as_ref());
+        bytes
+    }
+
+    /// Parses a header, validating the magic bytes, checksum, node count and that the leaf
+    /// count is within `DEFAULT_MAX_LEAF_COUNT`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MerkleTreeError> {
+        Self::from_bytes_with_limit(bytes, DEFAULT_MAX_LEAF_COUNT)
+    }
+
+    /// Like `from_bytes`, with the given limit on the leaf count.
+    pub fn from_bytes_with_limit(
+        bytes: &[u8],
+        max_leaf_count: usize,
+    ) -> Result<Self, MerkleTreeError> {
+        if bytes.len() < SNAPSHOT_HEADER_LEN || &bytes[0..4] != SNAPSHOT_MAGIC {
+            return Err(MerkleTreeError::InvalidSnapshot(
+                "Snapshot header is missing or has the wrong magic bytes".to_string(),
+            ));
+        }
+        let read_u64 = |offset: usize| {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(&bytes[offset..offset + 8]);
+            u64::from_le_bytes(word) as usize
+        };
+        let header = Self {
+            leaf_count: read_u64(4),
+            current_leaf_index: read_u64(12),
+            node_count: read_u64(20),
+            hash_domain: domain_from_byte(bytes[28])?,
+            root: Hash::new(&bytes[29..61]),
+        };
+        if header.checksum().as_ref() != &bytes[61..93] {
+            return Err(MerkleTreeError::InvalidSnapshot(
+                "Snapshot header checksum doesn't match".to_string(),
+            ));
+        }
+        let node_capacity = MerkleTree::check_capacity(header.leaf_count, max_leaf_count)?;
+        if header.current_leaf_index > header.leaf_count
+            || (header.node_count != header.leaf_count && header.node_count != node_capacity)
+        {
+            return Err(MerkleTreeError::InvalidSnapshot(format!(
+                "Snapshot has {} nodes and {} inserted leaves for a tree of {} leaves",
+                header.node_count, header.current_leaf_index, header.leaf_count
+            )));
+        }
+        Ok(header)
+    }
+}
+
+fn domain_to_byte(hash_domain: HashDomain) -> u8 {
+    match hash_domain {
+        HashDomain::Prefix => 0,
+        HashDomain::Tagged => 1,
+    }
+}
+
+fn domain_from_byte(byte: u8) -> Result<HashDomain, MerkleTreeError> {
+    match byte {
+        0 => Ok(HashDomain::Prefix),
+        1 => Ok(HashDomain::Tagged),
+        _ => Err(MerkleTreeError::InvalidSnapshot(format!(
+            "Snapshot has unknown hash domain {}",
+            byte
+        ))),
+    }
+}
+
+impl MerkleTree {
+    /// Writes the whole tree, including any computed interior levels. Trees with a custom
+    /// hasher are rejected since the header can only record a `HashDomain`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(leaf_count = self.leaf_count))
+    )]
+    pub fn write_snapshot<W: Write>(&self, mut writer: W) -> Result<(), MerkleTreeError> {
+        if !self.dirty_leaves.is_empty() {
+            return Err(MerkleTreeError::RootNotComputed(
+                "Tree has pending updates, call root() or flush() first".to_string(),
+            ));
+        }
+        if self.hasher.is_some() {
+            return Err(MerkleTreeError::InvalidSnapshot(
+                "Trees with a custom hasher can't be snapshotted".to_string(),
+            ));
+        }
+        let header = SnapshotHeader {
+            leaf_count: self.leaf_count,
+            current_leaf_index: self.current_leaf_index,
+            node_count: self.nodes.len(),
+            hash_domain: self.hash_domain,
+            root: self.nodes.last().copied().unwrap_or_default(),
+        };
+        writer.write_all(&header.to_bytes())?;
+        for node in &self.nodes {
+            writer.write_all(node.as_ref())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Restores a tree written by `write_snapshot`. The interior levels of a built tree are
+    /// rehashed from the leaves in the header's hash domain and must match the header root.
+    /// Trees above `DEFAULT_MAX_LEAF_COUNT` leaves are rejected, see
+    /// `read_snapshot_with_limit`.
+    pub fn read_snapshot<R: Read>(reader: R) -> Result<Self, MerkleTreeError> {
+        Self::read_snapshot_with_limit(reader, DEFAULT_MAX_LEAF_COUNT)
+    }
+
+    /// Like `read_snapshot`, with the given limit on the leaf count.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn read_snapshot_with_limit<R: Read>(
+        mut reader: R,
+        max_leaf_count: usize,
+    ) -> Result<Self, MerkleTreeError> {
+        let mut header = [0u8; SNAPSHOT_HEADER_LEN];
+        reader.read_exact(&mut header)?;
+        let header = SnapshotHeader::from_bytes_with_limit(&header, max_leaf_count)?;
+
+        // Grown as nodes arrive so a forged node count can't reserve memory up front
+        let mut nodes = vec![];
+        let mut node = [0u8; HASH_BYTES];
+        for _ in 0..header.node_count {
+            reader.read_exact(&mut node)?;
+            nodes.push(Hash::new_from_array(node));
+        }
+        let merkle_tree = Self {
+            leaf_count: header.leaf_count,
+            nodes,
+            current_leaf_index: header.current_leaf_index,
+            hash_domain: header.hash_domain,
+            ..Default::default()
+        };
+        if merkle_tree.nodes.last().copied().unwrap_or_default() != header.root {
+            return Err(MerkleTreeError::InvalidSnapshot(
+                "Snapshot root doesn't match its header".to_string(),
+            ));
+        }
+        if merkle_tree.is_built() && !merkle_tree.has_consistent_levels() {
+            return Err(MerkleTreeError::InvalidSnapshot(
+                "Snapshot nodes don't hash to its root".to_string(),
+            ));
+        }
+        Ok(merkle_tree)
+    }
+
+    /// Returns true if every interior node is the hash of its children in the tree's domain.
+    fn has_consistent_levels(&self) -> bool {
+        let layout = self.layout();
+        layout.levels().windows(2).all(|pair| {
+            let children = &self.nodes[pair[0].clone()];
+            self.nodes[pair[1].clone()]
+                .iter()
+                .enumerate()
+                .all(|(index, parent)| {
+                    let lnode = &children[2 * index];
+                    let rnode = children.get(2 * index + 1).unwrap_or(lnode);
+                    self.hash_domain.hash_node(lnode, rnode) == *parent
+                })
+        })
+    }
+}
+
+/// File-backed snapshots, unavailable on wasm32 where there is no filesystem.
+#[cfg(not(target_arch = "wasm32"))]
+impl MerkleTree {
+    /// Saves an uncompressed snapshot to the given path.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), MerkleTreeError> {
+        self.write_snapshot(BufWriter::new(File::create(path)?))
+    }
+
+    /// Loads an uncompressed snapshot from the given path.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, MerkleTreeError> {
+        Self::read_snapshot(BufReader::new(File::open(path)?))
+    }
+
+    /// Saves a zstd compressed snapshot to the given path.
+    #[cfg(feature = "zstd")]
+    pub fn save_compressed<P: AsRef<Path>>(&self, path: P) -> Result<(), MerkleTreeError> {
+        let mut encoder = zstd::Encoder::new(BufWriter::new(File::create(path)?), ZSTD_LEVEL)?;
+        self.write_snapshot(&mut encoder)?;
+        encoder.finish()?.flush()?;
+        Ok(())
+    }
+
+    /// Loads a zstd compressed snapshot from the given path.
+    #[cfg(feature = "zstd")]
+    pub fn load_compressed<P: AsRef<Path>>(path: P) -> Result<Self, MerkleTreeError> {
+        Self::read_snapshot(zstd::Decoder::new(File::open(path)?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+
+        let mut snapshot = vec![];
+        merkle_tree.write_snapshot(&mut snapshot).unwrap();
+        let restored = MerkleTree::read_snapshot(snapshot.as_slice()).unwrap();
+        assert_eq!(restored.nodes, merkle_tree.nodes);
+        assert_eq!(restored.current_leaf_index, SAMPLE.len());
+        assert!(restored.get_opening(3).unwrap().verify(&restored.nodes[3]));
+
+        snapshot[SNAPSHOT_HEADER_LEN] ^= 1;
+        assert!(matches!(
+            MerkleTree::read_snapshot(snapshot.as_slice()),
+            Err(MerkleTreeError::InvalidSnapshot(_))
+        ));
+        snapshot[SNAPSHOT_HEADER_LEN] ^= 1;
+        assert!(matches!(
+            MerkleTree::read_snapshot_with_limit(snapshot.as_slice(), SAMPLE.len() - 1),
+            Err(MerkleTreeError::CapacityExceeded(_))
+        ));
+        snapshot[5] ^= 1;
+        assert!(MerkleTree::read_snapshot(snapshot.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_tagged_snapshot_roundtrip() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.hash_domain = HashDomain::Tagged;
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+
+        let mut snapshot = vec![];
+        merkle_tree.write_snapshot(&mut snapshot).unwrap();
+        let restored = MerkleTree::read_snapshot(snapshot.as_slice()).unwrap();
+        assert_eq!(restored.hash_domain, HashDomain::Tagged);
+        assert_eq!(restored.nodes, merkle_tree.nodes);
+        assert!(restored
+            .verify_opening(&restored.get_opening(3).unwrap())
+            .unwrap());
+
+        snapshot[28] = 0;
+        assert!(matches!(
+            MerkleTree::read_snapshot(snapshot.as_slice()),
+            Err(MerkleTreeError::InvalidSnapshot(_))
+        ));
+
+        merkle_tree.set_hasher(std::sync::Arc::new(crate::KeccakHasher));
+        assert!(matches!(
+            merkle_tree.write_snapshot(&mut vec![]),
+            Err(MerkleTreeError::InvalidSnapshot(_))
+        ));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_compressed_snapshot_roundtrip() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+
+        let path = std::env::temp_dir().join(format!("fmt-snapshot-{}.zst", std::process::id()));
+        merkle_tree.save_compressed(&path).unwrap();
+        let restored = MerkleTree::load_compressed(&path);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(restored.unwrap().nodes, merkle_tree.nodes);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{MerkleTree, MerkleTreeError, Opening, NODE_PREFIX};
+use solana_merkle_tree::{
+    merkle_tree::{Proof, ProofEntry},
+    MerkleTree as SolanaMerkleTree,
+};
+use solana_program::hash::{hashv, Hash};
+
+// `solana_merkle_tree` keeps its nodes and proof entries private, so a tree can only be
+// converted given the items it was built from, and proofs only translate into its format.
+impl<T: AsRef<[u8]>> TryFrom<(&SolanaMerkleTree, &[T])> for MerkleTree {
+    type Error = MerkleTreeError;
+
+    /// Builds a tree from the items a `solana_merkle_tree::MerkleTree` was built from,
+    /// failing if the roots don't match.
+    fn try_from((solana_tree, items): (&SolanaMerkleTree, &[T])) -> Result<Self, Self::Error> {
+        let mut merkle_tree = MerkleTree::new(items.len());
+        merkle_tree.insert_batch(items)?;
+        if merkle_tree.get_root() != solana_tree.get_root() {
+            return Err(MerkleTreeError::ProofMismatch(
+                "Items don't compute to the root of the Solana tree".to_string(),
+            ));
+        }
+        Ok(merkle_tree)
+    }
+}
+
+/// The nodes of an opening laid out as `solana_merkle_tree` proof entries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolanaProofEntries {
+    // (target, left sibling, right sibling) for each level above the leaf
+    entries: Vec<(Hash, Option<Hash>, Option<Hash>)>,
+}
+
+impl SolanaProofEntries {
+    /// Borrows the entries as a `solana_merkle_tree::Proof`.
+    pub fn as_proof(&self) -> Proof<'_> {
+        let mut proof = Proof::default();
+        for (target, lsib, rsib) in &self.entries {
+            proof.push(ProofEntry::new(target, lsib.as_ref(), rsib.as_ref()));
+        }
+        proof
+    }
+}
+
+impl Opening {
+    /// Translates the opening of the given leaf hash into `solana_merkle_tree` proof entries.
+    pub fn to_solana_proof(&self, leaf: &Hash) -> SolanaProofEntries {
+        let mut current_index = self.leaf_index;
+        let mut target = *leaf;
+        let mut entries = Vec::with_capacity(self.path.len());
+        for partner in &self.path {
+            let entry = if current_index % 2 == 0 {
+                target = hash_node!(target, partner);
+                (target, None, Some(*partner))
+            } else {
+                target = hash_node!(partner, target);
+                (target, Some(*partner), None)
+            };
+            entries.push(entry);
+            current_index /= 2;
+        }
+        SolanaProofEntries { entries }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_solana_tree_conversion() {
+        let solana_tree = SolanaMerkleTree::new(SAMPLE);
+        let mut merkle_tree = MerkleTree::try_from((&solana_tree, SAMPLE)).unwrap();
+        assert_eq!(merkle_tree.get_root(), solana_tree.get_root());
+        assert!(MerkleTree::try_from((&solana_tree, &SAMPLE[1..])).is_err());
+
+        for leaf_index in 0..SAMPLE.len() {
+            let opening = merkle_tree.get_opening(leaf_index).unwrap();
+            let entries = opening.to_solana_proof(&merkle_tree.nodes[leaf_index]);
+            assert_eq!(
+                entries.as_proof(),
+                solana_tree.find_path(leaf_index).unwrap()
+            );
+            assert!(entries.as_proof().verify(merkle_tree.nodes[leaf_index]));
+        }
+    }
+}
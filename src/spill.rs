@@ -0,0 +1,360 @@
+use crate::{MerkleTree, MerkleTreeError, Opening, LEAF_PREFIX, NODE_PREFIX};
+use solana_program::hash::{hashv, Hash, HASH_BYTES};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+static SPILL_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Builds a tree while keeping the resident node storage within a memory budget.
+/// Levels that don't fit are written to temporary files and read back for openings.
+#[derive(Debug, Clone)]
+pub struct SpillBuilder {
+    memory_budget: usize,
+    spill_dir: PathBuf,
+}
+
+impl Default for SpillBuilder {
+    fn default() -> Self {
+        Self {
+            memory_budget: usize::MAX,
+            spill_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+impl SpillBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum number of bytes of node storage kept in memory.
+    pub fn memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = bytes;
+        self
+    }
+
+    /// Directory spilled levels are written to. Defaults to the system temp directory.
+    pub fn spill_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.spill_dir = dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Hashes the leaves and every level above them, spilling a level to disk
+    /// whenever keeping it in memory would exceed the budget.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(leaf_count = leaf_count))
+    )]
+    pub fn build<T, I>(self, leaves: I, leaf_count: usize) -> Result<SpilledTree, MerkleTreeError>
+    where
+        T: AsRef<[u8]>,
+        I: IntoIterator<Item = T>,
+    {
+        // Keep levels resident from the root down while they fit, so only lower levels spill
+        let mut level_lens = vec![leaf_count];
+        while level_lens[level_lens.len() - 1] > 1 {
+            level_lens.push(MerkleTree::calculate_next_level_len(
+                level_lens[level_lens.len() - 1],
+            ));
+        }
+        let mut resident_bytes: usize = 0;
+        let mut resident_levels = level_lens.len();
+        for level_len in level_lens.iter().rev() {
+            resident_bytes = resident_bytes.saturating_add(level_len * HASH_BYTES);
+            if resident_bytes > self.memory_budget {
+                break;
+            }
+            resident_levels -= 1;
+        }
+        let spilled_levels = resident_levels;
+
+        let mut tree = SpilledTree {
+            leaf_count,
+            levels: Vec::with_capacity(level_lens.len()),
+        };
+
+        let mut level_writer = self.level_writer(leaf_count, spilled_levels > 0)?;
+        let mut inserted: usize = 0;
+        for leaf in leaves.into_iter().take(leaf_count) {
+            level_writer.push(hash_leaf!(leaf))?;
+            inserted += 1;
+        }
+        if inserted < leaf_count {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Expected {} leaves but only {} were given",
+                leaf_count, inserted
+            )));
+        }
+        tree.levels.push(level_writer.finish()?);
+
+        for (level, level_len) in level_lens.iter().enumerate().skip(1) {
+            let mut level_writer = self.level_writer(*level_len, level < spilled_levels)?;
+            let current_level = &tree.levels[level - 1];
+            current_level.for_each_pair(level_lens[level - 1], |lnode, rnode| {
+                level_writer.push(hash_node!(lnode, rnode))
+            })?;
+            tree.levels.push(level_writer.finish()?);
+        }
+        Ok(tree)
+    }
+
+    fn level_writer(&self, level_len: usize, spill: bool) -> Result<LevelWriter, MerkleTreeError> {
+        if !spill {
+            return Ok(LevelWriter::Memory(Vec::with_capacity(level_len)));
+        }
+
+        let path = self.spill_dir.join(format!(
+            "fast-merkle-tree-{}-{}.level",
+            std::process::id(),
+            SPILL_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(LevelWriter::Disk {
+            writer: BufWriter::new(file),
+            path: SpillPath(path),
+            len: 0,
+        })
+    }
+}
+
+/// Removes a spill file when dropped, whether the build finished or bailed out midway.
+/// Declared after the file handle in each variant so the file is closed first.
+struct SpillPath(PathBuf);
+
+impl Drop for SpillPath {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+enum LevelWriter {
+    Memory(Vec<Hash>),
+    Disk {
+        writer: BufWriter<File>,
+        path: SpillPath,
+        len: usize,
+    },
+}
+
+impl LevelWriter {
+    fn push(&mut self, node: Hash) -> Result<(), MerkleTreeError> {
+        match self {
+            LevelWriter::Memory(nodes) => nodes.push(node),
+            LevelWriter::Disk { writer, len, .. } => {
+                writer.write_all(node.as_ref())?;
+                *len += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Level, MerkleTreeError> {
+        match self {
+            LevelWriter::Memory(nodes) => Ok(Level::Memory(nodes)),
+            LevelWriter::Disk { writer, path, len } => {
+                let file = writer.into_inner().map_err(|e| e.into_error())?;
+                Ok(Level::Disk {
+                    file: Mutex::new(file),
+                    _path: path,
+                    len,
+                })
+            }
+        }
+    }
+}
+
+enum Level {
+    Memory(Vec<Hash>),
+    Disk {
+        file: Mutex<File>,
+        /// Only held to remove the file along with the level.
+        _path: SpillPath,
+        len: usize,
+    },
+}
+
+impl Level {
+    fn len(&self) -> usize {
+        match self {
+            Level::Memory(nodes) => nodes.len(),
+            Level::Disk { len, .. } => *len,
+        }
+    }
+
+    fn node(&self, index: usize) -> Result<Hash, MerkleTreeError> {
+        match self {
+            Level::Memory(nodes) => Ok(nodes[index]),
+            Level::Disk { file, .. } => {
+                let mut file = file.lock().unwrap();
+                file.seek(SeekFrom::Start((index * HASH_BYTES) as u64))?;
+                let mut node = [0u8; HASH_BYTES];
+                file.read_exact(&mut node)?;
+                Ok(Hash::new_from_array(node))
+            }
+        }
+    }
+
+    /// Calls `f` with each pair of nodes, duplicating the last node of an odd length level.
+    fn for_each_pair<F>(&self, level_len: usize, mut f: F) -> Result<(), MerkleTreeError>
+    where
+        F: FnMut(Hash, Hash) -> Result<(), MerkleTreeError>,
+    {
+        match self {
+            Level::Memory(nodes) => {
+                for pair in nodes.chunks(2) {
+                    match pair {
+                        [lnode, rnode] => f(*lnode, *rnode)?,
+                        [lnode] => f(*lnode, *lnode)?,
+                        _ => unreachable!(),
+                    }
+                }
+            }
+            Level::Disk { file, .. } => {
+                let mut file = file.lock().unwrap();
+                file.seek(SeekFrom::Start(0))?;
+                let mut reader = BufReader::new(&mut *file);
+                let mut node = [0u8; HASH_BYTES];
+                for index in (0..level_len).step_by(2) {
+                    reader.read_exact(&mut node)?;
+                    let lnode = Hash::new_from_array(node);
+                    if index + 1 < level_len {
+                        reader.read_exact(&mut node)?;
+                        f(lnode, Hash::new_from_array(node))?;
+                    } else {
+                        f(lnode, lnode)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A built tree whose levels live either in memory or in spill files.
+pub struct SpilledTree {
+    pub leaf_count: usize,
+    levels: Vec<Level>,
+}
+
+impl SpilledTree {
+    /// Returns the Merkle Root of the tree.
+    pub fn get_root(&self) -> Option<Hash> {
+        if self.leaf_count == 0 {
+            return None;
+        }
+        self.levels.last().and_then(|level| level.node(0).ok())
+    }
+
+    /// Returns the number of levels that were spilled to disk.
+    pub fn spilled_levels(&self) -> usize {
+        self.levels
+            .iter()
+            .filter(|level| matches!(level, Level::Disk { .. }))
+            .count()
+    }
+
+    /// Returns the leaf at given index.
+    pub fn get_value(&self, leaf_index: usize) -> Result<Hash, MerkleTreeError> {
+        if leaf_index >= self.leaf_count {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Tree has {} leaves but index given was {}",
+                self.leaf_count, leaf_index
+            )));
+        }
+        self.levels[0].node(leaf_index)
+    }
+
+    /// Returns the opening for the leaf, reading spilled levels back from disk.
+    pub fn get_opening(&self, leaf_index: usize) -> Result<Opening, MerkleTreeError> {
+        if leaf_index >= self.leaf_count {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Tree has {} leaves but index given was {}",
+                self.leaf_count, leaf_index
+            )));
+        }
+
+        let mut current_index = leaf_index;
+        let mut path = Vec::with_capacity(self.levels.len() - 1);
+        for level in &self.levels[..self.levels.len() - 1] {
+            let partner_index = if current_index % 2 == 1 {
+                current_index - 1
+            } else if current_index + 1 < level.len() {
+                current_index + 1
+            } else {
+                current_index
+            };
+            path.push(level.node(partner_index)?);
+            current_index /= 2;
+        }
+
+        Ok(Opening {
+            leaf_index,
+            path,
+            root: self.levels[self.levels.len() - 1].node(0)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spilled_tree_matches_in_memory_tree() {
+        let leaves: Vec<Vec<u8>> = (0..37u32).map(|i| i.to_le_bytes().to_vec()).collect();
+        let mut merkle_tree = MerkleTree::new(leaves.len());
+        merkle_tree.insert_batch(&leaves).unwrap();
+        let root = *merkle_tree.get_root().unwrap();
+
+        let spilled_tree = SpillBuilder::new()
+            .memory_budget(16 * HASH_BYTES)
+            .build(&leaves, leaves.len())
+            .unwrap();
+        assert_eq!(spilled_tree.spilled_levels(), 3);
+        assert_eq!(spilled_tree.get_root(), Some(root));
+        assert_eq!(
+            spilled_tree.get_root().as_ref(),
+            MerkleTree::from_leaves(&leaves).nodes.last()
+        );
+
+        for leaf_index in 0..leaves.len() {
+            let opening = spilled_tree.get_opening(leaf_index).unwrap();
+            assert_eq!(opening, merkle_tree.get_opening(leaf_index).unwrap());
+        }
+        assert!(spilled_tree.get_opening(leaves.len()).is_err());
+        assert!(SpillBuilder::new().build(&leaves, 38).is_err());
+    }
+
+    #[test]
+    fn test_spill_files_are_removed() {
+        let spill_dir = std::env::temp_dir().join(format!("fmt-spill-{}", std::process::id()));
+        fs::create_dir_all(&spill_dir).unwrap();
+        let spill_files = || fs::read_dir(&spill_dir).unwrap().count();
+        let leaves: Vec<Vec<u8>> = (0..37u32).map(|i| i.to_le_bytes().to_vec()).collect();
+        let builder = SpillBuilder::new()
+            .memory_budget(16 * HASH_BYTES)
+            .spill_dir(&spill_dir);
+
+        let spilled_tree = builder.clone().build(&leaves, leaves.len()).unwrap();
+        assert!(spilled_tree.spilled_levels() > 0);
+        assert_eq!(spill_files(), spilled_tree.spilled_levels());
+        drop(spilled_tree);
+        assert_eq!(spill_files(), 0);
+
+        // The leaf level's file is removed when the build fails while writing it
+        assert!(builder.build(&leaves, 38).is_err());
+        assert_eq!(spill_files(), 0);
+        fs::remove_dir(&spill_dir).unwrap();
+    }
+}
@@ -0,0 +1,402 @@
+use crate::{MerkleTree, MerkleTreeError, Opening, DEFAULT_LEAF, NODE_PREFIX};
+use core::mem::{align_of, size_of};
+use solana_program::hash::hashv;
+use thiserror::Error;
+
+/// Anchor account discriminator, the first 8 bytes of `sha256("account:MerkleTreeState")`.
+pub const MERKLE_TREE_STATE_DISCRIMINATOR: [u8; 8] = [110, 93, 37, 106, 12, 80, 172, 23];
+
+fn hash_pair(lnode: &[u8; 32], rnode: &[u8; 32]) -> [u8; 32] {
+    hash_node!(lnode, rnode).to_bytes()
+}
+
+/// Fixed-layout tree of up to `MAX_DEPTH` levels meant to live inside a program account.
+/// Only the root and the frontier of filled left subtrees are stored, so every operation
+/// costs O(depth) hashes and never allocates. Leaves are node hashes, e.g. `hash_leaf!(data)`,
+/// and empty leaves are `DEFAULT_LEAF`, matching a `MerkleTree` of `2^depth` leaves.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MerkleTreeState<const MAX_DEPTH: usize> {
+    pub discriminator: [u8; 8],
+    pub depth: u32,
+    pub _padding: [u8; 4],
+    pub next_index: u64,
+    pub root: [u8; 32],
+    /// For each level, the left node the next append at that level will be hashed with.
+    pub filled_subtrees: [[u8; 32]; MAX_DEPTH],
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum MerkleTreeStateError {
+    #[error("account data doesn't hold a merkle tree state")]
+    InvalidAccountData,
+    #[error("depth exceeds the maximum depth")]
+    InvalidDepth,
+    #[error("tree is full")]
+    TreeFull,
+    #[error("leaf index out of bounds")]
+    LeafIndexOutOfBounds,
+    #[error("proof does not match the root")]
+    InvalidProof,
+}
+
+/// Size of the account data holding a state of the given maximum depth, usable in const
+/// contexts before the depth is a type parameter.
+pub const fn merkle_tree_state_len(max_depth: usize) -> usize {
+    8 + 4 + 4 + 8 + 32 + 32 * max_depth
+}
+
+impl<const MAX_DEPTH: usize> MerkleTreeState<MAX_DEPTH> {
+    /// Size of the account data holding the state. Evaluating it fails to compile for a
+    /// depth whose capacity doesn't fit `next_index`, or if the layout isn't the packed one
+    /// `merkle_tree_state_len` describes.
+    pub const LEN: usize = {
+        assert!(MAX_DEPTH < u64::BITS as usize, "MAX_DEPTH must be below 64");
+        assert!(size_of::<Self>() == merkle_tree_state_len(MAX_DEPTH));
+        assert!(align_of::<Self>() == align_of::<u64>());
+        size_of::<Self>()
+    };
+
+    /// Number of leaves a tree of `MAX_DEPTH` levels can hold.
+    pub const MAX_CAPACITY: u64 = 1 << MAX_DEPTH;
+
+    /// Resets the state to an empty tree of the given depth.
+    pub fn initialize(&mut self, depth: u32) -> Result<(), MerkleTreeStateError> {
+        // Forces the layout assertions for every depth that gets initialized
+        let _ = Self::LEN;
+        if depth as usize > MAX_DEPTH {
+            return Err(MerkleTreeStateError::InvalidDepth);
+        }
+        let mut zero = DEFAULT_LEAF;
+        for filled_subtree in self.filled_subtrees.iter_mut().take(depth as usize) {
+            *filled_subtree = zero;
+            zero = hash_pair(&zero, &zero);
+        }
+        self.discriminator = MERKLE_TREE_STATE_DISCRIMINATOR;
+        self.depth = depth;
+        self.next_index = 0;
+        self.root = zero;
+        Ok(())
+    }
+
+    /// Returns the number of leaves the tree can hold.
+    pub fn capacity(&self) -> u64 {
+        1 << self.depth
+    }
+
+    /// Appends a leaf node at `next_index` and returns the index it was assigned.
+    pub fn append(&mut self, leaf: [u8; 32]) -> Result<u64, MerkleTreeStateError> {
+        if self.next_index >= self.capacity() {
+            return Err(MerkleTreeStateError::TreeFull);
+        }
+        let leaf_index = self.next_index;
+        let mut current_index = leaf_index;
+        let mut node = leaf;
+        let mut zero = DEFAULT_LEAF;
+        for filled_subtree in self.filled_subtrees.iter_mut().take(self.depth as usize) {
+            node = if current_index % 2 == 0 {
+                *filled_subtree = node;
+                hash_pair(&node, &zero)
+            } else {
+                hash_pair(filled_subtree, &node)
+            };
+            zero = hash_pair(&zero, &zero);
+            current_index /= 2;
+        }
+        self.root = node;
+        self.next_index += 1;
+        Ok(leaf_index)
+    }
+
+    /// Appends a leaf node like `append`, first checking that `proof` opens the empty slot
+    /// at `next_index` under the root. The frontier is rebuilt from the proof, so this also
+    /// works when `filled_subtrees` can't be trusted. `MerkleTree::get_append_opening`
+    /// generates the proof off-chain.
+    pub fn append_with_proof(
+        &mut self,
+        leaf: [u8; 32],
+        proof: &[[u8; 32]],
+    ) -> Result<u64, MerkleTreeStateError> {
+        if self.next_index >= self.capacity() {
+            return Err(MerkleTreeStateError::TreeFull);
+        }
+        if proof.len() != self.depth as usize {
+            return Err(MerkleTreeStateError::InvalidProof);
+        }
+
+        let leaf_index = self.next_index;
+        let mut current_index = leaf_index;
+        let mut old_node = DEFAULT_LEAF;
+        let mut new_node = leaf;
+        let mut filled_subtrees = self.filled_subtrees;
+        for (level, partner) in proof.iter().enumerate() {
+            if current_index % 2 == 0 {
+                filled_subtrees[level] = new_node;
+                old_node = hash_pair(&old_node, partner);
+                new_node = hash_pair(&new_node, partner);
+            } else {
+                filled_subtrees[level] = *partner;
+                old_node = hash_pair(partner, &old_node);
+                new_node = hash_pair(partner, &new_node);
+            }
+            current_index /= 2;
+        }
+        if old_node != self.root {
+            return Err(MerkleTreeStateError::InvalidProof);
+        }
+        self.filled_subtrees = filled_subtrees;
+        self.root = new_node;
+        self.next_index += 1;
+        Ok(leaf_index)
+    }
+
+    /// Replaces an appended leaf after checking `old_leaf` and `proof` against the root.
+    /// `MerkleTree::get_opening` generates the proof off-chain.
+    pub fn set_leaf_with_proof(
+        &mut self,
+        leaf_index: u64,
+        old_leaf: [u8; 32],
+        new_leaf: [u8; 32],
+        proof: &[[u8; 32]],
+    ) -> Result<(), MerkleTreeStateError> {
+        if leaf_index >= self.next_index {
+            return Err(MerkleTreeStateError::LeafIndexOutOfBounds);
+        }
+        if proof.len() != self.depth as usize {
+            return Err(MerkleTreeStateError::InvalidProof);
+        }
+
+        let mut current_index = leaf_index;
+        let mut old_node = old_leaf;
+        let mut new_node = new_leaf;
+        let mut filled_subtrees = self.filled_subtrees;
+        for (level, partner) in proof.iter().enumerate() {
+            // The frontier holds the node left of the next append; keep it in sync if it's on our path
+            let next_index = self.next_index >> level;
+            if next_index % 2 == 1 && current_index == next_index - 1 {
+                filled_subtrees[level] = new_node;
+            }
+            if current_index % 2 == 0 {
+                old_node = hash_pair(&old_node, partner);
+                new_node = hash_pair(&new_node, partner);
+            } else {
+                old_node = hash_pair(partner, &old_node);
+                new_node = hash_pair(partner, &new_node);
+            }
+            current_index /= 2;
+        }
+        if old_node != self.root {
+            return Err(MerkleTreeStateError::InvalidProof);
+        }
+        self.filled_subtrees = filled_subtrees;
+        self.root = new_node;
+        Ok(())
+    }
+
+    /// Borrows the state stored at the start of the account data.
+    pub fn load(data: &[u8]) -> Result<&Self, MerkleTreeStateError> {
+        Self::check_account_data(data)?;
+        // Safety: the length and alignment were checked, and every bit pattern is valid
+        // for the plain integer and byte array fields of this repr(C) struct.
+        let state = unsafe { &*(data.as_ptr() as *const Self) };
+        if state.discriminator != MERKLE_TREE_STATE_DISCRIMINATOR {
+            return Err(MerkleTreeStateError::InvalidAccountData);
+        }
+        state.check_depth()?;
+        Ok(state)
+    }
+
+    /// Mutably borrows the state stored at the start of the account data.
+    /// The discriminator isn't checked so a freshly allocated account can be initialized.
+    pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, MerkleTreeStateError> {
+        Self::check_account_data(data)?;
+        // Safety: see `load`.
+        let state = unsafe { &mut *(data.as_mut_ptr() as *mut Self) };
+        state.check_depth()?;
+        Ok(state)
+    }
+
+    /// Rejects a stored depth the frontier can't hold, which would make `capacity` overflow
+    /// and let proofs longer than `filled_subtrees` index past it.
+    fn check_depth(&self) -> Result<(), MerkleTreeStateError> {
+        if self.depth as usize > MAX_DEPTH {
+            return Err(MerkleTreeStateError::InvalidDepth);
+        }
+        Ok(())
+    }
+
+    fn check_account_data(data: &[u8]) -> Result<(), MerkleTreeStateError> {
+        if data.len() < Self::LEN || data.as_ptr() as usize % align_of::<Self>() != 0 {
+            return Err(MerkleTreeStateError::InvalidAccountData);
+        }
+        Ok(())
+    }
+}
+
+impl MerkleTree {
+    /// Returns the opening of the next empty slot, the proof `MerkleTreeState::append_with_proof`
+    /// checks for a state mirroring this tree.
+    pub fn get_append_opening(&self) -> Result<Opening, MerkleTreeError> {
+        if self.current_leaf_index >= self.leaf_count {
+            return Err(MerkleTreeError::CapacityExceeded(format!(
+                "Tree is full at {} leaves",
+                self.leaf_count
+            )));
+        }
+        self.get_opening(self.current_leaf_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LEAF_PREFIX;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_state_matches_merkle_tree() {
+        // u64 backing keeps the account data aligned
+        const LEN: usize = merkle_tree_state_len(4);
+        assert_eq!(MerkleTreeState::<4>::LEN, LEN);
+        assert_eq!(MerkleTreeState::<4>::MAX_CAPACITY, 16);
+        let mut data = vec![0u64; LEN / 8];
+        let bytes = unsafe {
+            core::slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u8, data.len() * 8)
+        };
+        assert!(MerkleTreeState::<4>::load(bytes).is_err());
+        let state = MerkleTreeState::<4>::load_mut(bytes).unwrap();
+        assert_eq!(state.initialize(5), Err(MerkleTreeStateError::InvalidDepth));
+        state.initialize(3).unwrap();
+
+        let mut merkle_tree = MerkleTree::new(8);
+        assert_eq!(state.root, merkle_tree.get_root().unwrap().to_bytes());
+
+        for leaf in SAMPLE {
+            let leaf_index = state.append(hash_leaf!(leaf).to_bytes()).unwrap();
+            merkle_tree.update_leaf(leaf_index as usize, leaf).unwrap();
+            assert_eq!(state.root, merkle_tree.nodes.last().unwrap().to_bytes());
+        }
+
+        let opening = merkle_tree.get_opening(3).unwrap();
+        let proof: Vec<[u8; 32]> = opening.path.iter().map(|node| node.to_bytes()).collect();
+        let old_leaf = merkle_tree.nodes[3].to_bytes();
+        let new_leaf = b"foo";
+        assert_eq!(
+            state.set_leaf_with_proof(2, old_leaf, hash_leaf!(new_leaf).to_bytes(), &proof),
+            Err(MerkleTreeStateError::InvalidProof)
+        );
+        state
+            .set_leaf_with_proof(3, old_leaf, hash_leaf!(new_leaf).to_bytes(), &proof)
+            .unwrap();
+        merkle_tree.update_leaf(3, new_leaf).unwrap();
+        assert_eq!(state.root, merkle_tree.nodes.last().unwrap().to_bytes());
+
+        // Appends after a replace still build on the updated frontier
+        for leaf_index in 5..8usize {
+            let leaf = leaf_index.to_le_bytes();
+            state.append(hash_leaf!(leaf).to_bytes()).unwrap();
+            merkle_tree.update_leaf(leaf_index, leaf).unwrap();
+        }
+        assert_eq!(state.root, merkle_tree.nodes.last().unwrap().to_bytes());
+        assert_eq!(state.append([0; 32]), Err(MerkleTreeStateError::TreeFull));
+        assert_eq!(MerkleTreeState::<4>::load(bytes).unwrap().next_index, 8);
+    }
+
+    #[test]
+    fn test_append_with_proof() {
+        const LEN: usize = merkle_tree_state_len(3);
+        let mut data = vec![0u64; LEN / 8];
+        let bytes = unsafe {
+            core::slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u8, data.len() * 8)
+        };
+        let state = MerkleTreeState::<3>::load_mut(bytes).unwrap();
+        state.initialize(3).unwrap();
+
+        let mut merkle_tree = MerkleTree::new(8);
+        let _ = merkle_tree.get_root();
+        for leaf in SAMPLE {
+            let proof: Vec<[u8; 32]> = merkle_tree
+                .get_append_opening()
+                .unwrap()
+                .path
+                .iter()
+                .map(|node| node.to_bytes())
+                .collect();
+            let leaf_node = hash_leaf!(leaf).to_bytes();
+            assert_eq!(
+                state.append_with_proof(leaf_node, &proof[1..]),
+                Err(MerkleTreeStateError::InvalidProof)
+            );
+            state.append_with_proof(leaf_node, &proof).unwrap();
+            merkle_tree.insert(leaf).unwrap();
+            let _ = merkle_tree.get_root();
+            assert_eq!(state.root, merkle_tree.nodes.last().unwrap().to_bytes());
+        }
+
+        // A stale proof no longer opens the empty slot
+        let stale: Vec<[u8; 32]> = merkle_tree
+            .get_opening(4)
+            .unwrap()
+            .path
+            .iter()
+            .map(|node| node.to_bytes())
+            .collect();
+        assert_eq!(
+            state.append_with_proof([1; 32], &stale),
+            Err(MerkleTreeStateError::InvalidProof)
+        );
+        // The rebuilt frontier keeps plain appends consistent
+        let leaf = b"foo";
+        state.append(hash_leaf!(leaf).to_bytes()).unwrap();
+        merkle_tree.insert(leaf).unwrap();
+        assert_eq!(state.root, merkle_tree.get_root().unwrap().to_bytes());
+    }
+
+    #[test]
+    fn test_rejected_proof_keeps_state() {
+        const LEN: usize = merkle_tree_state_len(3);
+        let mut data = vec![0u64; LEN / 8];
+        let bytes = unsafe {
+            core::slice::from_raw_parts_mut(data.as_mut_ptr() as *mut u8, data.len() * 8)
+        };
+        let state = MerkleTreeState::<3>::load_mut(bytes).unwrap();
+        state.initialize(3).unwrap();
+        for leaf in SAMPLE {
+            state.append(hash_leaf!(leaf).to_bytes()).unwrap();
+        }
+        let before = *state;
+
+        // Leaf 4 sits left of the next append, so a bad proof for it would touch the frontier
+        let mut merkle_tree = MerkleTree::new(8);
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+        let proof: Vec<[u8; 32]> = merkle_tree
+            .get_opening(4)
+            .unwrap()
+            .path
+            .iter()
+            .map(|node| node.to_bytes())
+            .collect();
+        assert_eq!(
+            state.set_leaf_with_proof(4, [1; 32], [2; 32], &proof),
+            Err(MerkleTreeStateError::InvalidProof)
+        );
+        assert_eq!(*state, before);
+
+        let leaf = b"foo";
+        state.append(hash_leaf!(leaf).to_bytes()).unwrap();
+        merkle_tree.insert(leaf).unwrap();
+        assert_eq!(state.root, merkle_tree.get_root().unwrap().to_bytes());
+
+        state.depth = 4;
+        assert_eq!(
+            MerkleTreeState::<3>::load(bytes),
+            Err(MerkleTreeStateError::InvalidDepth)
+        );
+        assert_eq!(
+            MerkleTreeState::<3>::load_mut(bytes).map(|_| ()),
+            Err(MerkleTreeStateError::InvalidDepth)
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use crate::{chunked::read_chunk, MerkleTree, MerkleTreeError, Opening, LEAF_PREFIX};
+use solana_program::hash::{hashv, Hash, HASH_BYTES};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Identifies a verified chunk stream.
+pub const STREAM_MAGIC: &[u8; 4] = b"FMTB";
+
+/// Largest chunk size a stream can be encoded or read with, so a forged header can't make
+/// the reader allocate an arbitrary buffer.
+pub const MAX_STREAM_CHUNK_SIZE: usize = 1 << 24;
+
+fn check_chunk_size(chunk_size: usize) -> Result<(), MerkleTreeError> {
+    if chunk_size == 0 || chunk_size > MAX_STREAM_CHUNK_SIZE {
+        return Err(MerkleTreeError::InvalidChunkSize(format!(
+            "Chunk size {} must be between 1 and {}",
+            chunk_size, MAX_STREAM_CHUNK_SIZE
+        )));
+    }
+    Ok(())
+}
+
+/// Returns the number of partner nodes in an opening of a tree with the given leaf count.
+fn path_len(leaf_count: usize) -> usize {
+    let mut level_len = leaf_count;
+    let mut path_len = 0;
+    while level_len > 1 {
+        level_len = MerkleTree::calculate_next_level_len(level_len);
+        path_len += 1;
+    }
+    path_len
+}
+
+/// Encodes the reader as a stream of chunks, each preceded by its opening, and returns the
+/// root and chunk count a reader needs to trust the stream.
+/// Layout: magic || chunk_size (u32 LE) || chunk_count (u64 LE), then for every chunk
+/// chunk_len (u32 LE) || opening path || chunk bytes.
+pub fn encode_verified_stream<R: Read + Seek, W: Write>(
+    mut reader: R,
+    chunk_size: usize,
+    mut writer: W,
+) -> Result<(Hash, usize), MerkleTreeError> {
+    check_chunk_size(chunk_size)?;
+    let start = reader.stream_position()?;
+    let (merkle_tree, chunk_count) = MerkleTree::from_reader(&mut reader, chunk_size)?;
+    let root = *merkle_tree.nodes.last().ok_or_else(|| {
+        MerkleTreeError::InvalidChunkSize("Cannot encode an empty stream".to_string())
+    })?;
+    reader.seek(SeekFrom::Start(start))?;
+
+    writer.write_all(STREAM_MAGIC)?;
+    writer.write_all(&(chunk_size as u32).to_le_bytes())?;
+    writer.write_all(&(chunk_count as u64).to_le_bytes())?;
+
+    let mut chunk = vec![0u8; chunk_size];
+    for leaf_index in 0..chunk_count {
+        let chunk_len = read_chunk(&mut reader, &mut chunk)?;
+        writer.write_all(&(chunk_len as u32).to_le_bytes())?;
+        for partner in merkle_tree.get_opening(leaf_index)?.path {
+            writer.write_all(partner.as_ref())?;
+        }
+        writer.write_all(&chunk[..chunk_len])?;
+    }
+    writer.flush()?;
+    Ok((root, chunk_count))
+}
+
+/// Iterates over the chunks of an encoded stream, yielding each one only after its
+/// opening verifies against the trusted root. Stops after the first error.
+///
+/// The root alone doesn't fix the leaf count, so the chunk count is trusted alongside it
+/// and the header must match it; otherwise a stream cut after any full subtree would
+/// verify.
+pub struct VerifiedChunkReader<R: Read> {
+    reader: R,
+    root: Hash,
+    chunk_size: usize,
+    chunk_count: usize,
+    next_index: usize,
+    failed: bool,
+}
+
+impl<R: Read> VerifiedChunkReader<R> {
+    /// Reads the stream header, checking it against the trusted chunk count.
+    pub fn new(mut reader: R, root: Hash, chunk_count: usize) -> Result<Self, MerkleTreeError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != STREAM_MAGIC {
+            return Err(MerkleTreeError::ProofMismatch(
+                "Stream magic bytes don't match".to_string(),
+            ));
+        }
+        let mut chunk_size = [0u8; 4];
+        reader.read_exact(&mut chunk_size)?;
+        let chunk_size = u32::from_le_bytes(chunk_size) as usize;
+        check_chunk_size(chunk_size)?;
+        let mut header_count = [0u8; 8];
+        reader.read_exact(&mut header_count)?;
+        if u64::from_le_bytes(header_count) != chunk_count as u64 {
+            return Err(MerkleTreeError::ProofMismatch(format!(
+                "Stream has {} chunks, expected {}",
+                u64::from_le_bytes(header_count),
+                chunk_count
+            )));
+        }
+
+        Ok(Self {
+            reader,
+            root,
+            chunk_size,
+            chunk_count,
+            next_index: 0,
+            failed: false,
+        })
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunk_count
+    }
+
+    fn read_verified_chunk(&mut self) -> Result<Vec<u8>, MerkleTreeError> {
+        let mut chunk_len = [0u8; 4];
+        self.reader.read_exact(&mut chunk_len)?;
+        let chunk_len = u32::from_le_bytes(chunk_len) as usize;
+        let is_last = self.next_index + 1 == self.chunk_count;
+        if chunk_len == 0
+            || chunk_len > self.chunk_size
+            || (!is_last && chunk_len != self.chunk_size)
+        {
+            return Err(MerkleTreeError::ProofMismatch(format!(
+                "Chunk {} has an invalid length of {}",
+                self.next_index, chunk_len
+            )));
+        }
+
+        let mut path = Vec::with_capacity(path_len(self.chunk_count));
+        let mut partner = [0u8; HASH_BYTES];
+        for _ in 0..path.capacity() {
+            self.reader.read_exact(&mut partner)?;
+            path.push(Hash::new_from_array(partner));
+        }
+        let mut chunk = vec![0u8; chunk_len];
+        self.reader.read_exact(&mut chunk)?;
+
+        let opening = Opening {
+            leaf_index: self.next_index,
+            path,
+            root: self.root,
+        };
+        let leaf = chunk.as_slice();
+        if !opening.verify(&hash_leaf!(leaf)) {
+            return Err(MerkleTreeError::ProofMismatch(format!(
+                "Chunk {} doesn't verify against the root",
+                self.next_index
+            )));
+        }
+        Ok(chunk)
+    }
+}
+
+impl<R: Read> Iterator for VerifiedChunkReader<R> {
+    type Item = Result<Vec<u8>, MerkleTreeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.next_index == self.chunk_count {
+            return None;
+        }
+        let chunk = self.read_verified_chunk();
+        self.failed = chunk.is_err();
+        self.next_index += 1;
+        Some(chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_verified_stream() {
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        let mut encoded = vec![];
+        let (root, chunk_count) =
+            encode_verified_stream(Cursor::new(&data), 64, &mut encoded).unwrap();
+
+        let chunks = VerifiedChunkReader::new(encoded.as_slice(), root, chunk_count).unwrap();
+        assert_eq!(chunks.chunk_count(), 16);
+        let decoded: Vec<u8> = chunks.flat_map(Result::unwrap).collect();
+        assert_eq!(decoded, data);
+
+        // Flip a byte in the third chunk's data
+        let chunk_offset = 16 + 2 * (4 + 4 * HASH_BYTES + 64) + 4 + 4 * HASH_BYTES;
+        encoded[chunk_offset] ^= 1;
+        let results: Vec<_> = VerifiedChunkReader::new(encoded.as_slice(), root, chunk_count)
+            .unwrap()
+            .collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[..2].iter().all(Result::is_ok));
+        assert!(results[2].is_err());
+    }
+
+    #[test]
+    fn test_truncated_stream() {
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        let mut encoded = vec![];
+        let (root, chunk_count) =
+            encode_verified_stream(Cursor::new(&data), 64, &mut encoded).unwrap();
+
+        // Any count from 9 to 16 gives the same path length, so the first 9 chunks would
+        // all verify under a header rewritten to 9
+        let record_len = 4 + 4 * HASH_BYTES + 64;
+        let mut truncated = encoded[..16 + 9 * record_len].to_vec();
+        truncated[8..16].copy_from_slice(&9u64.to_le_bytes());
+        assert!(VerifiedChunkReader::new(truncated.as_slice(), root, chunk_count).is_err());
+
+        // Cutting the stream short fails on the missing chunk
+        let results: Vec<_> =
+            VerifiedChunkReader::new(&encoded[..16 + 8 * record_len], root, chunk_count)
+                .unwrap()
+                .collect();
+        assert_eq!(results.len(), 9);
+        assert!(results[..8].iter().all(Result::is_ok));
+        assert!(results[8].is_err());
+    }
+
+    #[test]
+    fn test_oversized_chunk_size() {
+        let data = [0u8; 16];
+        let mut encoded = vec![];
+        assert!(encode_verified_stream(
+            Cursor::new(&data),
+            MAX_STREAM_CHUNK_SIZE + 1,
+            &mut encoded
+        )
+        .is_err());
+
+        let (root, chunk_count) =
+            encode_verified_stream(Cursor::new(&data), 16, &mut encoded).unwrap();
+        encoded[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            VerifiedChunkReader::new(encoded.as_slice(), root, chunk_count),
+            Err(MerkleTreeError::InvalidChunkSize(_))
+        ));
+    }
+}
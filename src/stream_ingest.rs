@@ -0,0 +1,85 @@
+use crate::{MerkleTree, MerkleTreeError, LEAF_PREFIX};
+use futures_util::{Stream, StreamExt};
+use solana_program::hash::{hashv, Hash};
+use std::io::ErrorKind;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+impl MerkleTree {
+    /// Builds a tree from leaves as they arrive on the stream. Only the 32 byte leaf hashes
+    /// are kept, and the next item isn't polled until the current one is hashed, so a slow
+    /// build applies backpressure to the producer.
+    pub async fn from_stream<S, B>(stream: S) -> Self
+    where
+        S: Stream<Item = B>,
+        B: AsRef<[u8]>,
+    {
+        let mut stream = std::pin::pin!(stream);
+        let mut nodes: Vec<Hash> = vec![];
+        while let Some(leaf) = stream.next().await {
+            let leaf = leaf.as_ref();
+            nodes.push(hash_leaf!(leaf));
+        }
+        Self::from_leaf_hashes(nodes)
+    }
+
+    /// Like `from_reader`, for an async reader.
+    pub async fn from_async_reader<R: AsyncRead + Unpin>(
+        mut reader: R,
+        chunk_size: usize,
+    ) -> Result<(Self, usize), MerkleTreeError> {
+        if chunk_size == 0 {
+            return Err(MerkleTreeError::InvalidChunkSize(
+                "Chunk size must be greater than 0".to_string(),
+            ));
+        }
+
+        let mut nodes = vec![];
+        let mut chunk = vec![0u8; chunk_size];
+        loop {
+            let mut filled = 0;
+            while filled < chunk_size {
+                match reader.read(&mut chunk[filled..]).await {
+                    Ok(0) => break,
+                    Ok(read) => filled += read,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            if filled == 0 {
+                break;
+            }
+            let leaf = &chunk[..filled];
+            nodes.push(hash_leaf!(leaf));
+        }
+
+        let chunk_count = nodes.len();
+        Ok((Self::from_leaf_hashes(nodes), chunk_count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[tokio::test]
+    async fn test_from_stream() {
+        let stream = futures_util::stream::iter(SAMPLE.iter().map(|leaf| leaf.to_vec()));
+        let merkle_tree = MerkleTree::from_stream(stream).await;
+        assert_eq!(merkle_tree.nodes, MerkleTree::from_leaves(SAMPLE).nodes);
+
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        let (merkle_tree, chunk_count) = MerkleTree::from_async_reader(data.as_slice(), 64)
+            .await
+            .unwrap();
+        assert_eq!(chunk_count, 16);
+        assert_eq!(
+            merkle_tree.nodes,
+            MerkleTree::from_reader(data.as_slice(), 64)
+                .unwrap()
+                .0
+                .nodes
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{MerkleTree, MerkleTreeError, Opening};
+use solana_program::hash::Hash;
+use std::ops::Range;
+
+/// Proof that the root of an aligned subtree is included under the tree's root. The
+/// opening's `leaf_index` is the subtree's position within its level.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubtreeProof {
+    /// Height of the subtree above the leaves; it covers up to `2^level` leaves.
+    pub level: usize,
+    pub opening: Opening,
+}
+
+impl SubtreeProof {
+    /// Range of leaf indices covered by the subtree, assuming it's full.
+    pub fn leaf_range(&self) -> Range<usize> {
+        let start = self.opening.leaf_index << self.level;
+        start..start + (1 << self.level)
+    }
+
+    /// Returns true if the subtree root computes to the root embedded in the proof.
+    pub fn verify(&self, subtree_root: &Hash) -> bool {
+        self.opening.verify(subtree_root)
+    }
+
+    /// Builds the subtree from its `2^level` leaves and verifies its root, so a batch of
+    /// appended leaves is checked with a single proof.
+    pub fn verify_leaves<T: AsRef<[u8]>>(&self, leaves: &[T]) -> bool {
+        if leaves.len() != 1 << self.level {
+            return false;
+        }
+        let subtree = MerkleTree::from_leaves(leaves);
+        subtree
+            .nodes
+            .last()
+            .map_or(false, |subtree_root| self.verify(subtree_root))
+    }
+}
+
+impl Opening {
+    /// Returns true if the leaf computes to a trusted subtree root, e.g. one stored in a
+    /// canopy, rather than to the root embedded in the opening.
+    pub fn verify_to(&self, leaf: &Hash, subtree_root: &Hash) -> bool {
+        self.compute_root(leaf) == *subtree_root
+    }
+}
+
+impl MerkleTree {
+    /// Returns the opening of a leaf up to its ancestor on `ancestor_level`, whose root is
+    /// that ancestor. Level 0 gives an empty path whose root is the leaf itself.
+    pub fn get_opening_to(
+        &self,
+        leaf_index: usize,
+        ancestor_level: usize,
+    ) -> Result<Opening, MerkleTreeError> {
+        let mut opening = self.get_opening(leaf_index)?;
+        if ancestor_level > opening.path.len() {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Tree has no level {}",
+                ancestor_level
+            )));
+        }
+        opening.path.truncate(ancestor_level);
+        opening.root = self.subtree_root(ancestor_level, leaf_index >> ancestor_level)?;
+        Ok(opening)
+    }
+
+    /// Returns the proof that the node at `index` on `level` is included under the root.
+    /// Level 0 holds the leaves, so `prove_subtree_inclusion(0, i)` is the opening of leaf `i`.
+    pub fn prove_subtree_inclusion(
+        &self,
+        level: usize,
+        index: usize,
+    ) -> Result<SubtreeProof, MerkleTreeError> {
+        if !self.is_built() || !self.dirty_leaves.is_empty() {
+            return Err(MerkleTreeError::RootNotComputed(
+                "Tree must be built before proving subtrees, call root() first".to_string(),
+            ));
+        }
+
+        let mut level_offset = 0;
+        let mut level_len = self.leaf_count;
+        for _ in 0..level {
+            if level_len <= 1 {
+                return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                    "Tree has no level {}",
+                    level
+                )));
+            }
+            level_offset += level_len;
+            level_len = Self::calculate_next_level_len(level_len);
+        }
+        if index >= level_len {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Level {} has {} nodes but index given was {}",
+                level, level_len, index
+            )));
+        }
+
+        let mut path = vec![];
+        let mut position = index;
+        while level_len > 1 {
+            let partner = if position ^ 1 < level_len {
+                position ^ 1
+            } else {
+                position
+            };
+            path.push(self.nodes[level_offset + partner]);
+            position /= 2;
+            level_offset += level_len;
+            level_len = Self::calculate_next_level_len(level_len);
+        }
+
+        Ok(SubtreeProof {
+            level,
+            opening: Opening {
+                leaf_index: index,
+                path,
+                root: self.nodes[self.nodes.len() - 1],
+            },
+        })
+    }
+
+    /// Returns the node at `index` on `level`, i.e. the root of the aligned subtree over
+    /// leaves `index << level` up to `(index + 1) << level`.
+    pub fn subtree_root(&self, level: usize, index: usize) -> Result<Hash, MerkleTreeError> {
+        self.try_root()?;
+        let Some(range) = self.layout().level_range(level) else {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Tree has no level {}",
+                level
+            )));
+        };
+        if index >= range.len() {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Level {} has {} nodes but index given was {}",
+                level,
+                range.len(),
+                index
+            )));
+        }
+        Ok(self.nodes[range.start + index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[
+        b"lorem",
+        b"ipsum",
+        b"dolor",
+        b"sit",
+        b"amet",
+        b"consectetur",
+        b"adipiscing",
+        b"elit",
+    ];
+
+    #[test]
+    fn test_subtree_inclusion() {
+        let merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        assert_eq!(
+            merkle_tree.prove_subtree_inclusion(0, 5).unwrap().opening,
+            merkle_tree.get_opening(5).unwrap()
+        );
+
+        let proof = merkle_tree.prove_subtree_inclusion(2, 1).unwrap();
+        assert_eq!(proof.leaf_range(), 4..8);
+        assert_eq!(proof.opening.path.len(), 1);
+        assert!(proof.verify_leaves(&SAMPLE[4..]));
+        assert!(!proof.verify_leaves(&SAMPLE[..4]));
+        assert!(!proof.verify_leaves(&SAMPLE[4..7]));
+
+        let root_proof = merkle_tree.prove_subtree_inclusion(3, 0).unwrap();
+        assert!(root_proof.verify(merkle_tree.nodes.last().unwrap()));
+        assert!(merkle_tree.prove_subtree_inclusion(2, 2).is_err());
+        assert!(merkle_tree.prove_subtree_inclusion(4, 0).is_err());
+
+        assert!(proof.verify(&merkle_tree.subtree_root(2, 1).unwrap()));
+        assert_eq!(
+            merkle_tree.subtree_root(2, 0).unwrap(),
+            proof.opening.path[0]
+        );
+        assert!(merkle_tree.subtree_root(2, 2).is_err());
+    }
+
+    #[test]
+    fn test_opening_to_ancestor() {
+        let merkle_tree = MerkleTree::from_leaves(&SAMPLE[..7]);
+        let leaf = merkle_tree.nodes[6];
+        let opening = merkle_tree.get_opening_to(6, 2).unwrap();
+        assert_eq!(opening.path.len(), 2);
+        assert!(opening.verify(&leaf));
+        assert!(opening.verify_to(&leaf, &merkle_tree.subtree_root(2, 1).unwrap()));
+        assert!(!opening.verify_to(&leaf, &merkle_tree.subtree_root(2, 0).unwrap()));
+
+        assert_eq!(
+            merkle_tree.get_opening_to(6, 3).unwrap(),
+            merkle_tree.get_opening(6).unwrap()
+        );
+        assert_eq!(merkle_tree.get_opening_to(6, 0).unwrap().root, leaf);
+        assert!(merkle_tree.get_opening_to(6, 4).is_err());
+    }
+}
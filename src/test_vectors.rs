@@ -0,0 +1,83 @@
+//! Canonical fixtures for checking that other implementations (e.g. on-chain verifiers)
+//! produce the same roots and openings as this crate.
+use crate::{HashDomain, MerkleTree, Opening};
+use solana_program::hash::Hash;
+
+/// Leaf counts covered by the fixtures, including odd levels where the last node is duplicated.
+pub const TEST_VECTOR_LEAF_COUNTS: &[usize] = &[1, 2, 3, 4, 5, 7, 8, 13, 16];
+
+/// Domain separation schemes covered by the fixtures.
+pub const TEST_VECTOR_DOMAINS: &[HashDomain] = &[HashDomain::Prefix, HashDomain::Tagged];
+
+/// A tree built from deterministic leaves, with its root and the opening of every leaf.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestVector {
+    pub domain: HashDomain,
+    /// Raw leaves, `b"leaf-0"`, `b"leaf-1"`, ...
+    pub leaves: Vec<Vec<u8>>,
+    pub leaf_hashes: Vec<Hash>,
+    pub root: Hash,
+    pub openings: Vec<Opening>,
+}
+
+impl TestVector {
+    /// Builds the fixture for the given domain and number of leaves.
+    pub fn generate(domain: HashDomain, leaf_count: usize) -> Self {
+        let leaves: Vec<Vec<u8>> = (0..leaf_count)
+            .map(|i| format!("leaf-{}", i).into_bytes())
+            .collect();
+        let mut merkle_tree = MerkleTree::new(leaf_count);
+        merkle_tree.hash_domain = domain;
+        merkle_tree
+            .insert_batch(&leaves)
+            .expect("tree was sized for the leaves");
+        let root = *merkle_tree.get_root().expect("fixtures have leaves");
+        let openings = (0..leaf_count)
+            .map(|i| merkle_tree.get_opening(i).expect("leaf index is in bounds"))
+            .collect();
+        Self {
+            domain,
+            leaf_hashes: merkle_tree.nodes[..leaf_count].to_vec(),
+            leaves,
+            root,
+            openings,
+        }
+    }
+}
+
+/// Returns the fixtures for every combination of domain and leaf count.
+pub fn test_vectors() -> Vec<TestVector> {
+    TEST_VECTOR_DOMAINS
+        .iter()
+        .flat_map(|&domain| {
+            TEST_VECTOR_LEAF_COUNTS
+                .iter()
+                .map(move |&leaf_count| TestVector::generate(domain, leaf_count))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vectors_are_consistent() {
+        let vectors = test_vectors();
+        assert_eq!(
+            vectors.len(),
+            TEST_VECTOR_DOMAINS.len() * TEST_VECTOR_LEAF_COUNTS.len()
+        );
+        for vector in &vectors {
+            for (opening, leaf) in vector.openings.iter().zip(&vector.leaf_hashes) {
+                assert_eq!(opening.root, vector.root);
+                assert!(opening.verify_in(vector.domain, leaf));
+            }
+        }
+        assert_eq!(vectors[0].root, vectors[0].leaf_hashes[0]);
+        assert_eq!(
+            TestVector::generate(HashDomain::Prefix, 5).root.to_string(),
+            "CWF6d2QtWseF7qJHzsTxctyEQ3fzZRjG1VF2c97S5F6V"
+        );
+    }
+}
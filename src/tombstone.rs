@@ -0,0 +1,156 @@
+use crate::{MerkleTree, MerkleTreeError, Opening, DEFAULT_LEAF};
+use solana_program::hash::Hash;
+
+/// Leaf hash written over deleted leaves, `SHA256("FastMerkleTree/tombstone")`. It has no
+/// known leaf preimage, so it can't be confused with an inserted value.
+pub const TOMBSTONE_LEAF: [u8; 32] = [
+    220, 172, 207, 233, 150, 56, 73, 251, 157, 59, 59, 149, 55, 10, 230, 149, 173, 178, 54, 27,
+    214, 251, 16, 79, 112, 13, 18, 24, 143, 31, 14, 169,
+];
+
+impl MerkleTree {
+    /// Logically deletes an inserted leaf by replacing it with `TOMBSTONE_LEAF`, keeping the
+    /// indices of the other leaves. Returns the leaf hash that was removed. Deleting a
+    /// deleted leaf does nothing.
+    pub fn delete_leaf(&mut self, leaf_index: usize) -> Result<Hash, MerkleTreeError> {
+        if leaf_index >= self.current_leaf_index {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Tree has {} inserted leaves but index given was {}",
+                self.current_leaf_index, leaf_index
+            )));
+        }
+        let previous = self.nodes[leaf_index];
+        if !self.is_deleted(leaf_index) {
+            self.set_leaf_hashes(&[(leaf_index, Hash::new_from_array(TOMBSTONE_LEAF))])?;
+        }
+        Ok(previous)
+    }
+
+    /// Returns true if the leaf at the index has been deleted.
+    pub fn is_deleted(&self, leaf_index: usize) -> bool {
+        leaf_index < self.current_leaf_index && self.nodes[leaf_index].to_bytes() == TOMBSTONE_LEAF
+    }
+
+    /// Counts the deleted leaves among the inserted ones.
+    pub fn deleted_count(&self) -> usize {
+        self.nodes[..self.current_leaf_index]
+            .iter()
+            .filter(|leaf| leaf.to_bytes() == TOMBSTONE_LEAF)
+            .count()
+    }
+
+    /// Returns the opening of the tombstone at a deleted leaf, proving that whatever value
+    /// the leaf held is no longer in the tree at that index.
+    pub fn prove_deleted(&self, leaf_index: usize) -> Result<Opening, MerkleTreeError> {
+        if !self.is_deleted(leaf_index) {
+            return Err(MerkleTreeError::ProofMismatch(format!(
+                "Leaf {} hasn't been deleted",
+                leaf_index
+            )));
+        }
+        self.get_opening(leaf_index)
+    }
+
+    /// Moves the live leaves down over the deleted ones, freeing their slots for new inserts,
+    /// and rebuilds the tree if it was built. Returns the new index of every previously
+    /// inserted leaf, or `None` for deleted leaves. The undo history is dropped since it
+    /// refers to the old indices.
+    pub fn compact(&mut self) -> Vec<Option<usize>> {
+        let mut remap = Vec::with_capacity(self.current_leaf_index);
+        let mut kept = 0;
+        for leaf_index in 0..self.current_leaf_index {
+            if self.is_deleted(leaf_index) {
+                remap.push(None);
+                continue;
+            }
+            self.nodes[kept] = self.nodes[leaf_index];
+            remap.push(Some(kept));
+            kept += 1;
+        }
+        if kept == self.current_leaf_index {
+            return remap;
+        }
+
+        let was_built = self.is_built();
+        self.nodes[kept..self.current_leaf_index].fill(DEFAULT_LEAF.into());
+        self.current_leaf_index = kept;
+        self.undo_log.clear();
+        if self.duplicates.is_some() {
+            self.set_duplicate_policy(self.duplicate_policy());
+        }
+        if was_built {
+            let _ = self.get_root();
+        }
+        remap
+    }
+}
+
+impl Opening {
+    /// Returns true if the opening proves its leaf index holds a tombstone, i.e. that the
+    /// value previously at that index has been removed.
+    pub fn verify_deleted(&self) -> bool {
+        self.verify(&Hash::new_from_array(TOMBSTONE_LEAF))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LEAF_PREFIX;
+    use solana_program::hash::{hash, hashv};
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_tombstone_leaf() {
+        assert_eq!(hash(b"FastMerkleTree/tombstone").to_bytes(), TOMBSTONE_LEAF);
+    }
+
+    #[test]
+    fn test_delete_leaf() {
+        let mut merkle_tree = MerkleTree::new(8);
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+        let opening = merkle_tree.get_opening(2).unwrap();
+
+        let leaf = SAMPLE[2];
+        assert_eq!(merkle_tree.delete_leaf(2).unwrap(), hash_leaf!(leaf));
+        merkle_tree.delete_leaf(2).unwrap();
+        assert!(merkle_tree.delete_leaf(SAMPLE.len()).is_err());
+        assert_eq!(merkle_tree.deleted_count(), 1);
+        assert!(merkle_tree.is_deleted(2) && !merkle_tree.is_deleted(3));
+
+        // The old opening no longer verifies and the slot now proves the deletion
+        assert!(!merkle_tree.verify_opening(&opening).unwrap());
+        let deleted = merkle_tree.prove_deleted(2).unwrap();
+        assert!(deleted.verify_deleted());
+        assert!(!deleted.verify(&hash_leaf!(leaf)));
+        assert!(merkle_tree.prove_deleted(3).is_err());
+
+        merkle_tree.update_leaf(2, b"foo").unwrap();
+        assert_eq!(merkle_tree.deleted_count(), 0);
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut merkle_tree = MerkleTree::new(SAMPLE.len());
+        merkle_tree.insert_batch(SAMPLE).unwrap();
+        let _ = merkle_tree.get_root();
+        assert_eq!(merkle_tree.compact(), (0..5).map(Some).collect::<Vec<_>>());
+
+        merkle_tree.delete_leaf(1).unwrap();
+        merkle_tree.delete_leaf(3).unwrap();
+        assert_eq!(
+            merkle_tree.compact(),
+            vec![Some(0), None, Some(1), None, Some(2)]
+        );
+        assert_eq!(merkle_tree.deleted_count(), 0);
+
+        let mut expected = MerkleTree::new(SAMPLE.len());
+        expected
+            .insert_batch(&[SAMPLE[0], SAMPLE[2], SAMPLE[4]])
+            .unwrap();
+        assert_eq!(merkle_tree.root(), expected.get_root());
+        assert_eq!(merkle_tree.insert_indexed(SAMPLE[1]).unwrap(), 3);
+    }
+}
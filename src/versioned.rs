@@ -0,0 +1,192 @@
+use crate::{MerkleTree, MerkleTreeError, MerkleTreeView, Opening};
+use solana_program::hash::Hash;
+use std::collections::BTreeMap;
+
+/// A tree whose committed versions stay provable. Each commit stores only the nodes that
+/// changed since the previous one, so older versions are rebuilt by replaying the changes.
+#[derive(Clone, Debug)]
+pub struct VersionedTree {
+    tree: MerkleTree,
+    /// Nodes of version 0.
+    base: Vec<Hash>,
+    /// Nodes changed by each later version, keyed by position in `nodes`.
+    deltas: Vec<BTreeMap<usize, Hash>>,
+    /// Nodes of the latest committed version, to diff the next commit against.
+    committed: Vec<Hash>,
+}
+
+/// The value a leaf held at a version, with its opening against that version's root.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LeafVersion {
+    pub version: u64,
+    pub leaf: Hash,
+    pub opening: Opening,
+}
+
+impl LeafVersion {
+    /// Returns true if the leaf computes to the root of its version.
+    pub fn verify(&self) -> bool {
+        self.opening.verify(&self.leaf)
+    }
+}
+
+impl VersionedTree {
+    /// Commits the tree as version 0. It must be built.
+    pub fn new(tree: MerkleTree) -> Result<Self, MerkleTreeError> {
+        tree.try_root()?;
+        Ok(Self {
+            base: tree.nodes.clone(),
+            committed: tree.nodes.clone(),
+            deltas: vec![],
+            tree,
+        })
+    }
+
+    /// Returns the working tree. Changes become a version on the next `commit`.
+    pub fn tree_mut(&mut self) -> &mut MerkleTree {
+        &mut self.tree
+    }
+
+    pub fn tree(&self) -> &MerkleTree {
+        &self.tree
+    }
+
+    /// Returns the latest committed version.
+    pub fn version(&self) -> u64 {
+        self.deltas.len() as u64
+    }
+
+    /// Rebuilds the working tree if needed and commits it as the next version, storing
+    /// the nodes that changed. Returns the new version. The working tree must keep the
+    /// node count of version 0, since deltas are stored by position.
+    pub fn commit(&mut self) -> Result<u64, MerkleTreeError> {
+        let _ = self.tree.root();
+        self.tree.try_root()?;
+        if self.tree.nodes.len() != self.committed.len() {
+            return Err(MerkleTreeError::CapacityExceeded(format!(
+                "Working tree has {} nodes but committed versions have {}",
+                self.tree.nodes.len(),
+                self.committed.len()
+            )));
+        }
+        let delta = self
+            .tree
+            .nodes
+            .iter()
+            .zip(&self.committed)
+            .enumerate()
+            .filter(|(_, (node, committed))| node != committed)
+            .map(|(position, (node, _))| (position, *node))
+            .collect();
+        self.committed.clone_from(&self.tree.nodes);
+        self.deltas.push(delta);
+        Ok(self.version())
+    }
+
+    /// Returns the root of a committed version.
+    pub fn root_at(&self, version: u64) -> Option<Hash> {
+        let root_position = self.base.len() - 1;
+        if version > self.version() {
+            return None;
+        }
+        self.deltas[..version as usize]
+            .iter()
+            .rev()
+            .find_map(|delta| delta.get(&root_position).copied())
+            .or(Some(self.base[root_position]))
+    }
+
+    /// Returns the leaf's value at `from_version` and at every later version up to
+    /// `to_version` where it changed, each with its opening against that version's root.
+    pub fn prove_leaf_history(
+        &self,
+        leaf_index: usize,
+        from_version: u64,
+        to_version: u64,
+    ) -> Result<Vec<LeafVersion>, MerkleTreeError> {
+        if leaf_index >= self.tree.leaf_count {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Tree has {} leaves but index given was {}",
+                self.tree.leaf_count, leaf_index
+            )));
+        }
+        if from_version > to_version || to_version > self.version() {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Versions {} to {} aren't within the {} committed",
+                from_version,
+                to_version,
+                self.version()
+            )));
+        }
+        let mut nodes = self.base.clone();
+        let mut history: Vec<LeafVersion> = vec![];
+        for version in 0..=to_version {
+            if version > 0 {
+                for (position, node) in &self.deltas[version as usize - 1] {
+                    nodes[*position] = *node;
+                }
+            }
+            let changed = history
+                .last()
+                .map_or(true, |previous| previous.leaf != nodes[leaf_index]);
+            if version >= from_version && changed {
+                let view = MerkleTreeView::new(self.tree.leaf_count, &nodes)?;
+                history.push(LeafVersion {
+                    version,
+                    leaf: nodes[leaf_index],
+                    opening: view.get_opening(leaf_index)?,
+                });
+            }
+        }
+        Ok(history)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_leaf_history() {
+        let mut versioned = VersionedTree::new(MerkleTree::from_leaves(SAMPLE)).unwrap();
+        let roots: Vec<Hash> = [b"foo".as_slice(), b"bar", b"baz"]
+            .iter()
+            .enumerate()
+            .map(|(i, leaf)| {
+                // Leaf 1 changes in versions 1 and 3, leaf 2 in version 2
+                versioned.tree_mut().update_leaf(1 + i % 2, leaf).unwrap();
+                versioned.commit().unwrap();
+                *versioned.tree_mut().root().unwrap()
+            })
+            .collect();
+        assert_eq!(versioned.version(), 3);
+        assert_eq!(versioned.root_at(2), Some(roots[1]));
+
+        let history = versioned.prove_leaf_history(1, 0, 3).unwrap();
+        let versions: Vec<u64> = history.iter().map(|entry| entry.version).collect();
+        assert_eq!(versions, vec![0, 1, 3]);
+        for entry in &history {
+            assert!(entry.verify());
+            assert_eq!(Some(entry.opening.root), versioned.root_at(entry.version));
+        }
+
+        let history = versioned.prove_leaf_history(1, 2, 2).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(
+            history[0].leaf,
+            versioned.prove_leaf_history(1, 1, 1).unwrap()[0].leaf
+        );
+        assert!(versioned.prove_leaf_history(1, 0, 4).is_err());
+        assert!(matches!(
+            versioned.prove_leaf_history(SAMPLE.len(), 0, 3),
+            Err(MerkleTreeError::LeafIndexOutOfBounds(_))
+        ));
+
+        // Deltas are positional, so a resized working tree can't be committed
+        *versioned.tree_mut() = MerkleTree::from_leaves(&SAMPLE[..4]);
+        assert!(versioned.commit().is_err());
+        assert_eq!(versioned.version(), 3);
+    }
+}
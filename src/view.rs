@@ -0,0 +1,130 @@
+use crate::{MerkleTree, MerkleTreeError, Opening, TreeLayout};
+use solana_program::hash::{Hash, HASH_BYTES};
+
+/// Read-only view over the nodes of a built tree that it doesn't own,
+/// e.g. a slice of account data or a memory-mapped snapshot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MerkleTreeView<'a> {
+    pub leaf_count: usize,
+    pub nodes: &'a [Hash],
+}
+
+impl<'a> MerkleTreeView<'a> {
+    /// Wraps the nodes of a built tree with the given number of leaves.
+    pub fn new(leaf_count: usize, nodes: &'a [Hash]) -> Result<Self, MerkleTreeError> {
+        let node_count = MerkleTree::calculate_max_capacity(leaf_count);
+        if leaf_count == 0 || nodes.len() != node_count {
+            return Err(MerkleTreeError::RootNotComputed(format!(
+                "A built tree of {} leaves has {} nodes but {} were given",
+                leaf_count,
+                node_count,
+                nodes.len()
+            )));
+        }
+        Ok(Self { leaf_count, nodes })
+    }
+
+    /// Wraps raw node bytes, 32 bytes per node.
+    pub fn from_bytes(leaf_count: usize, bytes: &'a [u8]) -> Result<Self, MerkleTreeError> {
+        if bytes.len() % HASH_BYTES != 0 {
+            return Err(MerkleTreeError::RootNotComputed(format!(
+                "{} bytes isn't a whole number of nodes",
+                bytes.len()
+            )));
+        }
+        // Safety: `Hash` is a repr(transparent) wrapper around `[u8; 32]`, so it has
+        // alignment 1 and every byte pattern is valid.
+        let nodes = unsafe {
+            std::slice::from_raw_parts(bytes.as_ptr() as *const Hash, bytes.len() / HASH_BYTES)
+        };
+        Self::new(leaf_count, nodes)
+    }
+
+    /// Returns the Merkle Root of the tree.
+    pub fn root(&self) -> &'a Hash {
+        &self.nodes[self.nodes.len() - 1]
+    }
+
+    /// Returns the leaf at given index.
+    pub fn get_value(&self, leaf_index: usize) -> Option<&'a Hash> {
+        self.nodes[0..self.leaf_count].get(leaf_index)
+    }
+
+    /// Returns the opening for the tree.
+    /// Opening - A list of all partner nodes with which when hashed together computes to the root,
+    /// along with the leaf index and the root itself.
+    pub fn get_opening(&self, leaf_index: usize) -> Result<Opening, MerkleTreeError> {
+        self.get_opening_in(&TreeLayout::new(self.leaf_count), leaf_index)
+    }
+
+    /// Like `get_opening`, walking a layout already computed for the view's leaf count.
+    pub(crate) fn get_opening_in(
+        &self,
+        layout: &TreeLayout,
+        leaf_index: usize,
+    ) -> Result<Opening, MerkleTreeError> {
+        if leaf_index >= self.leaf_count {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Tree has {} leaves but index given was {}",
+                self.leaf_count, leaf_index
+            )));
+        };
+        let mut path = Vec::with_capacity(layout.level_count() - 1);
+        let mut current_index = leaf_index;
+        for level in 0..layout.level_count() - 1 {
+            if let (Some(sibling), Some(parent)) = (
+                layout.sibling(level, current_index),
+                layout.parent(level, current_index),
+            ) {
+                path.push(self.nodes[layout.levels()[level].start + sibling]);
+                current_index = parent;
+            }
+        }
+
+        Ok(Opening {
+            leaf_index,
+            path,
+            root: self.nodes[self.nodes.len() - 1],
+        })
+    }
+
+    /// Returns a bool in a result signifying if the opening is valid and the leaf stored at
+    /// its index computes to its root.
+    pub fn verify_opening(&self, opening: &Opening) -> Result<bool, MerkleTreeError> {
+        match self.get_value(opening.leaf_index) {
+            Some(leaf) => Ok(opening.verify(leaf)),
+            None => Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Tree has {} leaves but index given was {}",
+                self.leaf_count, opening.leaf_index
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_view_over_bytes() {
+        let merkle_tree = MerkleTree::from_leaves(SAMPLE);
+        let bytes: Vec<u8> = merkle_tree
+            .nodes
+            .iter()
+            .flat_map(|node| node.to_bytes())
+            .collect();
+
+        let view = MerkleTreeView::from_bytes(SAMPLE.len(), &bytes).unwrap();
+        assert_eq!(view.root(), merkle_tree.nodes.last().unwrap());
+        for leaf_index in 0..SAMPLE.len() {
+            let opening = view.get_opening(leaf_index).unwrap();
+            assert_eq!(opening, merkle_tree.get_opening(leaf_index).unwrap());
+            assert!(view.verify_opening(&opening).unwrap());
+        }
+        assert!(view.get_opening(SAMPLE.len()).is_err());
+        assert!(MerkleTreeView::from_bytes(SAMPLE.len(), &bytes[32..]).is_err());
+        assert!(MerkleTreeView::new(SAMPLE.len(), &merkle_tree.nodes[..SAMPLE.len()]).is_err());
+    }
+}
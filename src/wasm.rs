@@ -0,0 +1,41 @@
+use crate::{MerkleProof, MerkleTree, Root, LEAF_PREFIX};
+use solana_program::hash::{hashv, Hash};
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+/// A built tree handed out to JavaScript.
+#[wasm_bindgen(js_name = MerkleTree)]
+pub struct JsMerkleTree {
+    inner: MerkleTree,
+}
+
+#[wasm_bindgen(js_class = MerkleTree)]
+impl JsMerkleTree {
+    /// Base58 encoded Merkle Root of the tree.
+    pub fn root(&self) -> Option<String> {
+        self.inner.nodes.last().map(Hash::to_string)
+    }
+}
+
+/// Builds a tree over the UTF-8 bytes of each leaf string.
+#[wasm_bindgen(js_name = buildTree)]
+pub fn build_tree(leaves: Vec<String>) -> JsMerkleTree {
+    JsMerkleTree {
+        inner: MerkleTree::from_leaves(&leaves),
+    }
+}
+
+/// Returns the proof of a leaf as `<index>:<node>,<node>,...` with base58 encoded nodes.
+#[wasm_bindgen(js_name = getProof)]
+pub fn get_proof(tree: &JsMerkleTree, leaf_index: usize) -> Result<String, JsError> {
+    let opening = tree.inner.get_opening(leaf_index)?;
+    Ok(MerkleProof::try_from(&opening)?.to_string())
+}
+
+/// Checks a proof from `getProof` for the raw leaf string against a base58 or hex root.
+#[wasm_bindgen(js_name = verifyProof)]
+pub fn verify_proof(root: &str, proof: &str, leaf: &str) -> Result<bool, JsError> {
+    let opening = MerkleProof::from_str(proof)?.into_opening(Root::from_str(root)?.into());
+    let leaf = leaf.as_bytes();
+    Ok(opening.verify(&hash_leaf!(leaf)))
+}
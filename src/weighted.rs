@@ -0,0 +1,259 @@
+use crate::{MerkleTreeError, LEAF_PREFIX, NODE_PREFIX};
+use solana_program::hash::{hashv, Hash};
+
+/// How a node's weight is derived from its children's.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WeightAggregate {
+    /// The total weight below the node, for sampling proportional to weight.
+    #[default]
+    Sum,
+    /// The largest weight below the node, for proving a leaf has the maximum weight.
+    Max,
+}
+
+impl WeightAggregate {
+    fn combine(&self, lweight: u64, rweight: u64) -> Option<u64> {
+        match self {
+            WeightAggregate::Sum => lweight.checked_add(rweight),
+            WeightAggregate::Max => Some(lweight.max(rweight)),
+        }
+    }
+}
+
+/// A node and the aggregated weight below it.
+pub type WeightedNode = (Hash, u64);
+
+/// Hashes a leaf together with its weight.
+pub fn hash_weighted_leaf(leaf: &[u8], weight: u64) -> Hash {
+    hashv(&[LEAF_PREFIX, &weight.to_le_bytes(), leaf])
+}
+
+/// Hashes a parent from its children and their weights, so every weight is committed to.
+fn hash_weighted_node(lnode: &WeightedNode, rnode: &WeightedNode) -> Hash {
+    hashv(&[
+        NODE_PREFIX,
+        lnode.0.as_ref(),
+        &lnode.1.to_le_bytes(),
+        rnode.0.as_ref(),
+        &rnode.1.to_le_bytes(),
+    ])
+}
+
+fn weighted_parent(
+    aggregate: WeightAggregate,
+    lnode: &WeightedNode,
+    rnode: &WeightedNode,
+) -> Option<WeightedNode> {
+    let weight = aggregate.combine(lnode.1, rnode.1)?;
+    Some((hash_weighted_node(lnode, rnode), weight))
+}
+
+/// Tree carrying a weight per leaf, aggregated up to the root. The last node of an odd level
+/// is paired with an empty node of weight 0 rather than duplicated, so sums stay exact.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WeightedTree {
+    pub aggregate: WeightAggregate,
+    /// Levels from the leaves up to the root.
+    levels: Vec<Vec<WeightedNode>>,
+}
+
+/// Opening of a weighted leaf, with the weight of every partner node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WeightedOpening {
+    pub leaf_index: usize,
+    pub aggregate: WeightAggregate,
+    pub path: Vec<WeightedNode>,
+    pub root: WeightedNode,
+}
+
+impl WeightedTree {
+    /// Builds the tree over `(leaf, weight)` pairs. Fails if there are no leaves or a sum
+    /// of weights overflows a u64.
+    pub fn new<T: AsRef<[u8]>>(
+        leaves: &[(T, u64)],
+        aggregate: WeightAggregate,
+    ) -> Result<Self, MerkleTreeError> {
+        if leaves.is_empty() {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(
+                "Weighted tree needs at least one leaf".to_string(),
+            ));
+        }
+        let mut levels = vec![leaves
+            .iter()
+            .map(|(leaf, weight)| (hash_weighted_leaf(leaf.as_ref(), *weight), *weight))
+            .collect::<Vec<_>>()];
+        while let Some(level) = levels.last().filter(|level| level.len() > 1) {
+            let next_level = level
+                .chunks(2)
+                .map(|pair| {
+                    let empty = (Hash::default(), 0);
+                    weighted_parent(aggregate, &pair[0], pair.get(1).unwrap_or(&empty))
+                })
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| {
+                    MerkleTreeError::CapacityExceeded("Sum of weights overflows a u64".to_string())
+                })?;
+            levels.push(next_level);
+        }
+        Ok(Self { aggregate, levels })
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Returns the root and the aggregated weight of the whole tree.
+    pub fn root(&self) -> WeightedNode {
+        self.levels[self.levels.len() - 1][0]
+    }
+
+    /// Returns the weight of the leaf at the index.
+    pub fn weight(&self, leaf_index: usize) -> Option<u64> {
+        self.levels[0].get(leaf_index).map(|(_, weight)| *weight)
+    }
+
+    /// Returns the index of the first leaf with the largest weight.
+    pub fn max_leaf(&self) -> usize {
+        let max_weight = self.levels[0].iter().map(|(_, weight)| *weight).max();
+        self.levels[0]
+            .iter()
+            .position(|(_, weight)| Some(*weight) == max_weight)
+            .unwrap_or_default()
+    }
+
+    /// Picks the leaf whose cumulative weight range contains `point`, so a uniformly random
+    /// point below the total weight selects leaves proportionally to their weight. Only
+    /// available for `Sum` trees.
+    pub fn sample(&self, point: u64) -> Result<usize, MerkleTreeError> {
+        let total_weight = self.root().1;
+        if self.aggregate != WeightAggregate::Sum || point >= total_weight {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Sampling needs a Sum tree and a point below its total weight {}",
+                total_weight
+            )));
+        }
+        let mut position = 0;
+        let mut remaining = point;
+        for level in self.levels[..self.levels.len() - 1].iter().rev() {
+            let lweight = level[position * 2].1;
+            position *= 2;
+            if remaining >= lweight {
+                remaining -= lweight;
+                position += 1;
+            }
+        }
+        Ok(position)
+    }
+
+    pub fn get_opening(&self, leaf_index: usize) -> Result<WeightedOpening, MerkleTreeError> {
+        if leaf_index >= self.leaf_count() {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Tree has {} leaves but index given was {}",
+                self.leaf_count(),
+                leaf_index
+            )));
+        }
+        let mut position = leaf_index;
+        let mut path = Vec::with_capacity(self.levels.len() - 1);
+        for level in &self.levels[..self.levels.len() - 1] {
+            path.push(
+                level
+                    .get(position ^ 1)
+                    .copied()
+                    .unwrap_or((Hash::default(), 0)),
+            );
+            position /= 2;
+        }
+        Ok(WeightedOpening {
+            leaf_index,
+            aggregate: self.aggregate,
+            path,
+            root: self.root(),
+        })
+    }
+}
+
+impl WeightedOpening {
+    /// Returns true if the leaf with the given weight computes to the root and its weight.
+    pub fn verify(&self, leaf: &[u8], weight: u64) -> bool {
+        let mut current_index = self.leaf_index;
+        let mut node = Some((hash_weighted_leaf(leaf, weight), weight));
+        for partner in &self.path {
+            node = node.and_then(|node| {
+                if current_index % 2 == 0 {
+                    weighted_parent(self.aggregate, &node, partner)
+                } else {
+                    weighted_parent(self.aggregate, partner, &node)
+                }
+            });
+            current_index /= 2;
+        }
+        node == Some(self.root)
+    }
+
+    /// Returns true if the opening proves the leaf has the maximum weight in a `Max` tree.
+    pub fn proves_max(&self, leaf: &[u8], weight: u64) -> bool {
+        self.aggregate == WeightAggregate::Max && weight == self.root.1 && self.verify(leaf, weight)
+    }
+
+    /// Returns true if the opening proves `sample(point)` selects the leaf in a `Sum` tree:
+    /// the point lies between the weight of the leaves left of it and that plus its weight.
+    pub fn proves_sample(&self, leaf: &[u8], weight: u64, point: u64) -> bool {
+        let mut current_index = self.leaf_index;
+        let mut weight_before: u64 = 0;
+        for (_, partner_weight) in &self.path {
+            if current_index % 2 == 1 {
+                weight_before = weight_before.saturating_add(*partner_weight);
+            }
+            current_index /= 2;
+        }
+        self.aggregate == WeightAggregate::Sum
+            && weight_before <= point
+            && point - weight_before < weight
+            && self.verify(leaf, weight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+    const WEIGHTS: [u64; 5] = [3, 0, 7, 2, 5];
+
+    fn leaves() -> Vec<(&'static [u8], u64)> {
+        SAMPLE.iter().copied().zip(WEIGHTS).collect()
+    }
+
+    #[test]
+    fn test_max_weight_proof() {
+        let tree = WeightedTree::new(&leaves(), WeightAggregate::Max).unwrap();
+        assert_eq!(tree.root().1, 7);
+        assert_eq!(tree.max_leaf(), 2);
+
+        let opening = tree.get_opening(2).unwrap();
+        assert!(opening.proves_max(SAMPLE[2], 7));
+        assert!(!opening.verify(SAMPLE[2], 8));
+        let opening = tree.get_opening(4).unwrap();
+        assert!(opening.verify(SAMPLE[4], 5));
+        assert!(!opening.proves_max(SAMPLE[4], 5));
+    }
+
+    #[test]
+    fn test_weighted_sampling() {
+        let tree = WeightedTree::new(&leaves(), WeightAggregate::Sum).unwrap();
+        assert_eq!(tree.root().1, 17);
+
+        let mut picks = vec![0u64; SAMPLE.len()];
+        for point in 0..17 {
+            let leaf_index = tree.sample(point).unwrap();
+            picks[leaf_index] += 1;
+            let opening = tree.get_opening(leaf_index).unwrap();
+            assert!(opening.proves_sample(SAMPLE[leaf_index], WEIGHTS[leaf_index], point));
+            assert!(!opening.proves_sample(SAMPLE[leaf_index], WEIGHTS[leaf_index], point + 17));
+        }
+        assert_eq!(picks, WEIGHTS);
+        assert!(tree.sample(17).is_err());
+        assert!(WeightedTree::new(&[(b"a", u64::MAX), (b"b", 1)], WeightAggregate::Sum).is_err());
+    }
+}
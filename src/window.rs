@@ -0,0 +1,105 @@
+use crate::{MerkleTree, MerkleTreeError, Opening, DEFAULT_MAX_LEAF_COUNT};
+use solana_program::hash::Hash;
+
+/// Tree committing to only the most recent `window` leaves. Leaves are kept in a ring, so
+/// the leaf with sequence number `s` sits at index `s % window` and overwrites the leaf that
+/// expires, rehashing a single path per append.
+#[derive(Clone, Debug)]
+pub struct SlidingWindowTree {
+    pub tree: MerkleTree,
+    /// Total number of leaves ever appended, i.e. the sequence number of the next one.
+    pub appended: u64,
+}
+
+impl SlidingWindowTree {
+    /// Creates an empty window of the given size, with every slot holding the default leaf.
+    pub fn new(window: usize) -> Result<Self, MerkleTreeError> {
+        if window == 0 {
+            return Err(MerkleTreeError::CapacityExceeded(
+                "Window must hold at least one leaf".to_string(),
+            ));
+        }
+        MerkleTree::check_capacity(window, DEFAULT_MAX_LEAF_COUNT)?;
+        let mut tree = MerkleTree::new(window);
+        let _ = tree.get_root();
+        Ok(Self { tree, appended: 0 })
+    }
+
+    pub fn window(&self) -> usize {
+        self.tree.leaf_count
+    }
+
+    /// Returns the number of leaves currently in the window.
+    pub fn len(&self) -> usize {
+        self.appended.min(self.window() as u64) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.appended == 0
+    }
+
+    /// Returns the sequence number of the oldest leaf still in the window.
+    pub fn oldest(&self) -> u64 {
+        self.appended - self.len() as u64
+    }
+
+    /// Appends a leaf, expiring the oldest one if the window is full, and returns its
+    /// sequence number.
+    pub fn append<T: AsRef<[u8]>>(&mut self, leaf: T) -> Result<u64, MerkleTreeError> {
+        let sequence = self.appended;
+        self.tree.update_leaf(self.slot(sequence), leaf)?;
+        self.appended += 1;
+        self.tree.current_leaf_index = self.len();
+        Ok(sequence)
+    }
+
+    /// Returns the root over the leaves in the window.
+    pub fn root(&self) -> Result<Hash, MerkleTreeError> {
+        self.tree.try_root()
+    }
+
+    /// Returns the opening of the leaf with the given sequence number, failing if it has
+    /// expired or hasn't been appended yet.
+    pub fn get_opening(&self, sequence: u64) -> Result<Opening, MerkleTreeError> {
+        if sequence < self.oldest() || sequence >= self.appended {
+            return Err(MerkleTreeError::LeafIndexOutOfBounds(format!(
+                "Window holds leaves {} to {} but sequence given was {}",
+                self.oldest(),
+                self.appended,
+                sequence
+            )));
+        }
+        self.tree.get_opening(self.slot(sequence))
+    }
+
+    fn slot(&self, sequence: u64) -> usize {
+        (sequence % self.window() as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &[&[u8]] = &[b"lorem", b"ipsum", b"dolor", b"sit", b"amet"];
+
+    #[test]
+    fn test_sliding_window() {
+        let mut window = SlidingWindowTree::new(3).unwrap();
+        assert!(SlidingWindowTree::new(0).is_err());
+        for leaf in SAMPLE {
+            window.append(leaf).unwrap();
+        }
+        assert_eq!((window.len(), window.oldest()), (3, 2));
+
+        // Slots hold sequence numbers 3, 4 and 2
+        let expected = MerkleTree::from_leaves(&[SAMPLE[3], SAMPLE[4], SAMPLE[2]]);
+        assert_eq!(window.root().unwrap(), expected.try_root().unwrap());
+
+        let opening = window.get_opening(4).unwrap();
+        assert_eq!(opening.leaf_index, 1);
+        assert!(opening.verify(&expected.nodes[1]));
+        assert!(window.get_opening(1).is_err());
+        assert!(window.get_opening(5).is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+fn fmt_cli(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_fmt-cli"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fmt-cli-{}-{}", std::process::id(), name))
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone())
+        .unwrap()
+        .trim()
+        .to_string()
+}
+
+/// Builds a snapshot from `contents` and returns the printed leaf count, root and snapshot path.
+fn build(name: &str, contents: &str) -> (String, String, PathBuf) {
+    let leaf_file = temp_path(name);
+    let snapshot = temp_path(&format!("{}.snapshot", name));
+    fs::write(&leaf_file, contents).unwrap();
+    let output = stdout(&fmt_cli(&[
+        "build",
+        leaf_file.to_str().unwrap(),
+        snapshot.to_str().unwrap(),
+    ]));
+    fs::remove_file(&leaf_file).unwrap();
+    let mut lines = output.lines();
+    let leaves = lines.next().unwrap().trim_start_matches("leaves: ");
+    let root = lines.next().unwrap().trim_start_matches("root: ");
+    (leaves.to_string(), root.to_string(), snapshot)
+}
+
+#[test]
+fn test_build_prove_verify() {
+    let (leaves, root, snapshot) = build("sample.txt", "lorem\nipsum\ndolor\nsit\namet\n");
+    assert_eq!(leaves, "5");
+    for (index, leaf) in ["lorem", "ipsum", "dolor", "sit", "amet"]
+        .iter()
+        .enumerate()
+    {
+        let proof = stdout(&fmt_cli(&[
+            "prove",
+            snapshot.to_str().unwrap(),
+            &index.to_string(),
+        ]));
+        assert_eq!(stdout(&fmt_cli(&["verify", &root, &proof, leaf])), "valid");
+        assert!(!fmt_cli(&["verify", &root, &proof, "nope"]).status.success());
+    }
+    fs::remove_file(snapshot).unwrap();
+}
+
+#[test]
+fn test_csv_quoted_fields() {
+    let (leaves, csv_root, csv_snapshot) = build(
+        "quoted.csv",
+        "lorem,\"ipsum, dolor\"\r\n\"sit \"\"amet\"\"\",\"con\nsectetur\"\n",
+    );
+    assert_eq!(leaves, "4");
+    let (_, json_root, json_snapshot) = build(
+        "quoted.json",
+        r#"["lorem", "ipsum, dolor", "sit \"amet\"", "con\nsectetur"]"#,
+    );
+    assert_eq!(csv_root, json_root);
+
+    let proof = stdout(&fmt_cli(&["prove", csv_snapshot.to_str().unwrap(), "1"]));
+    assert_eq!(
+        stdout(&fmt_cli(&["verify", &csv_root, &proof, "ipsum, dolor"])),
+        "valid"
+    );
+    fs::remove_file(&csv_snapshot).unwrap();
+    fs::remove_file(json_snapshot).unwrap();
+}
+
+#[test]
+fn test_csv_unterminated_quote() {
+    let leaf_file = temp_path("unterminated.csv");
+    let snapshot = temp_path("unterminated.snapshot");
+    fs::write(&leaf_file, "lorem,\"ipsum\n").unwrap();
+    let output = fmt_cli(&[
+        "build",
+        leaf_file.to_str().unwrap(),
+        snapshot.to_str().unwrap(),
+    ]);
+    fs::remove_file(&leaf_file).unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unterminated quoted field"));
+    assert!(!snapshot.exists());
+}